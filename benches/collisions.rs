@@ -0,0 +1,85 @@
+// how long a tick of collision checks takes as the number of colliders grows, run with
+// `cargo bench --bench collisions`
+use std::time::{Duration, Instant};
+
+use bevy::prelude::*;
+use flappy_bird::{
+    collider::Collider,
+    game::GameTick,
+    physics::{CollisionEvent, PhysicsPlugin, Velocity},
+};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+const TICK: Duration = Duration::from_millis(16);
+const WARMUP_TICKS: u32 = 10;
+const TICKS: u32 = 100;
+// roughly one collider per this many square units, however many there are
+const AREA_PER_COLLIDER: f32 = 2000.0;
+
+// bullets flying every which way with the odd bigger shape in between
+fn app(count: usize) -> App {
+    let mut app = App::new();
+    app.add_plugins(PhysicsPlugin)
+        .insert_resource(Time::<()>::default());
+
+    let mut rng = ChaCha8Rng::seed_from_u64(count as u64);
+    let half_extent = (count as f32 * AREA_PER_COLLIDER).sqrt() * 0.5;
+    for index in 0..count {
+        let position = Vec2::new(
+            rng.gen_range(-half_extent..half_extent),
+            rng.gen_range(-half_extent..half_extent),
+        );
+        let velocity = Vec2::from_angle(rng.gen_range(0.0..std::f32::consts::TAU)) * 200.0;
+        let collider = match index % 10 {
+            0 => Collider::Box {
+                size: Vec2::new(40.0, 12.0),
+            },
+            1 => Collider::Capsule {
+                half_length: 8.0,
+                radius: 4.0,
+            },
+            _ => Collider::Circle { radius: 3.0 },
+        };
+        app.world.spawn((
+            TransformBundle::from_transform(Transform::from_translation(position.extend(0.0))),
+            collider,
+            Velocity(velocity),
+        ));
+    }
+    app
+}
+
+// average time per tick and how many collisions came out of them
+fn run(app: &mut App, ticks: u32) -> (Duration, usize) {
+    let mut collisions = 0;
+    let start = Instant::now();
+    for _ in 0..ticks {
+        app.world.resource_mut::<Time>().advance_by(TICK);
+        app.world.run_schedule(GameTick);
+        collisions += app
+            .world
+            .resource_mut::<Events<CollisionEvent>>()
+            .drain()
+            .count();
+    }
+    (start.elapsed() / ticks, collisions)
+}
+
+fn main() {
+    println!(
+        "{:>10} {:>12} {:>16} {:>12}",
+        "colliders", "per tick", "per collider", "collisions"
+    );
+    for count in [500, 1000, 2000, 4000, 8000, 16000] {
+        let mut app = app(count);
+        run(&mut app, WARMUP_TICKS);
+        let (per_tick, collisions) = run(&mut app, TICKS);
+        println!(
+            "{count:>10} {:>12} {:>16} {:>12}",
+            format!("{per_tick:.2?}"),
+            format!("{:.2?}", per_tick / count as u32),
+            collisions / TICKS as usize,
+        );
+    }
+}
@@ -0,0 +1,41 @@
+use std::net::{Ipv4Addr, SocketAddr};
+
+use flappy_lobby::{protocol::DEFAULT_PORT, server::Server};
+
+const USAGE: &str = "usage: flappy_lobby [--bind <address>]";
+
+fn main() {
+    let address = parse_args(std::env::args().skip(1)).unwrap_or_else(|err| {
+        eprintln!("{err}");
+        std::process::exit(2);
+    });
+
+    let mut server = Server::bind(address).unwrap_or_else(|err| {
+        eprintln!("failed to bind {address}: {err}");
+        std::process::exit(1);
+    });
+    println!("lobby listening on {address}");
+    if let Err(err) = server.run() {
+        eprintln!("lobby stopped: {err}");
+        std::process::exit(1);
+    }
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<SocketAddr, String> {
+    let mut address = (Ipv4Addr::UNSPECIFIED, DEFAULT_PORT).into();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--bind" => {
+                let value = args
+                    .next()
+                    .ok_or(format!("missing value for {arg}\n{USAGE}"))?;
+                address = value
+                    .parse()
+                    .map_err(|_| format!("invalid value {value:?} for {arg}\n{USAGE}"))?;
+            }
+            "--help" | "-h" => return Err(USAGE.into()),
+            _ => return Err(format!("unknown argument {arg:?}\n{USAGE}")),
+        }
+    }
+    Ok(address)
+}
@@ -0,0 +1,258 @@
+use std::{
+    fmt,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+};
+
+pub const DEFAULT_PORT: u16 = 7200;
+pub const ROOM_SIZE: usize = 2;
+
+const MAGIC: &[u8; 4] = b"FBLB";
+const VERSION: u8 = 1;
+// letters that can't be mistaken for each other when read out loud or off a screen
+const CODE_LETTERS: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ";
+
+// four letters players share to end up in the same room
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RoomCode(pub [u8; 4]);
+
+impl RoomCode {
+    pub fn from_index(mut index: u64) -> Self {
+        let mut code = [0; 4];
+        for letter in code.iter_mut() {
+            *letter = CODE_LETTERS[(index % CODE_LETTERS.len() as u64) as usize];
+            index /= CODE_LETTERS.len() as u64;
+        }
+        Self(code)
+    }
+
+    // case doesn't matter, anything that isn't one of the code letters does
+    pub fn parse(code: &str) -> Option<Self> {
+        let code = code.trim().to_ascii_uppercase();
+        let bytes: [u8; 4] = code.as_bytes().try_into().ok()?;
+        bytes
+            .iter()
+            .all(|letter| CODE_LETTERS.contains(letter))
+            .then_some(Self(bytes))
+    }
+
+    pub fn is_letter(letter: char) -> bool {
+        letter.is_ascii() && CODE_LETTERS.contains(&(letter.to_ascii_uppercase() as u8))
+    }
+}
+
+impl fmt::Display for RoomCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", String::from_utf8_lossy(&self.0))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LobbyError {
+    RoomNotFound,
+    RoomFull,
+    AlreadyStarted,
+}
+
+impl LobbyError {
+    fn to_byte(self) -> u8 {
+        match self {
+            LobbyError::RoomNotFound => 0,
+            LobbyError::RoomFull => 1,
+            LobbyError::AlreadyStarted => 2,
+        }
+    }
+
+    fn from_byte(byte: u8) -> Option<Self> {
+        match byte {
+            0 => Some(LobbyError::RoomNotFound),
+            1 => Some(LobbyError::RoomFull),
+            2 => Some(LobbyError::AlreadyStarted),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for LobbyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LobbyError::RoomNotFound => write!(f, "room not found"),
+            LobbyError::RoomFull => write!(f, "room is full"),
+            LobbyError::AlreadyStarted => write!(f, "race already started"),
+        }
+    }
+}
+
+// a room as one of its members sees it
+#[derive(Debug, Clone, PartialEq)]
+pub struct RoomState {
+    pub code: RoomCode,
+    // index of the member the state was sent to, which is also its player index
+    pub player: u8,
+    pub ready: Vec<bool>,
+    pub seed: u64,
+    // the other member's address, sent once everyone is ready and the race starts
+    pub peer: Option<SocketAddr>,
+}
+
+// everything is sent over UDP, clients repeat their latest request until it's answered
+#[derive(Debug, Clone, PartialEq)]
+pub enum Message {
+    // the seed is the creator's pick, `None` lets the server roll one
+    Create { seed: Option<u64> },
+    Join { code: RoomCode },
+    Ready { code: RoomCode, ready: bool },
+    Leave { code: RoomCode },
+    // forwarded to the other member of the room as `Relayed`
+    Relay { code: RoomCode, payload: Vec<u8> },
+    Room(RoomState),
+    Error(LobbyError),
+    Relayed { payload: Vec<u8> },
+}
+
+impl Message {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(VERSION);
+        match self {
+            Message::Create { seed } => {
+                bytes.push(0);
+                bytes.push(seed.is_some() as u8);
+                bytes.extend_from_slice(&seed.unwrap_or_default().to_le_bytes());
+            }
+            Message::Join { code } => {
+                bytes.push(1);
+                bytes.extend_from_slice(&code.0);
+            }
+            Message::Ready { code, ready } => {
+                bytes.push(2);
+                bytes.extend_from_slice(&code.0);
+                bytes.push(*ready as u8);
+            }
+            Message::Leave { code } => {
+                bytes.push(3);
+                bytes.extend_from_slice(&code.0);
+            }
+            Message::Relay { code, payload } => {
+                bytes.push(4);
+                bytes.extend_from_slice(&code.0);
+                bytes.extend_from_slice(payload);
+            }
+            Message::Room(room) => {
+                bytes.push(5);
+                bytes.extend_from_slice(&room.code.0);
+                bytes.push(room.player);
+                bytes.push(room.ready.len() as u8);
+                bytes.extend(room.ready.iter().map(|ready| *ready as u8));
+                bytes.extend_from_slice(&room.seed.to_le_bytes());
+                match room.peer {
+                    None => bytes.push(0),
+                    Some(peer) => encode_address(&mut bytes, peer),
+                }
+            }
+            Message::Error(err) => {
+                bytes.push(6);
+                bytes.push(err.to_byte());
+            }
+            Message::Relayed { payload } => {
+                bytes.push(7);
+                bytes.extend_from_slice(payload);
+            }
+        }
+        bytes
+    }
+
+    // anything malformed is dropped like a lost packet
+    pub fn decode(bytes: &[u8]) -> Option<Self> {
+        let mut reader = Reader(bytes);
+        if reader.take::<4>()? != *MAGIC || reader.take::<1>()?[0] != VERSION {
+            return None;
+        }
+
+        match reader.take::<1>()?[0] {
+            0 => {
+                let has_seed = reader.take::<1>()?[0] != 0;
+                let seed = u64::from_le_bytes(reader.take()?);
+                Some(Message::Create {
+                    seed: has_seed.then_some(seed),
+                })
+            }
+            1 => Some(Message::Join {
+                code: RoomCode(reader.take()?),
+            }),
+            2 => Some(Message::Ready {
+                code: RoomCode(reader.take()?),
+                ready: reader.take::<1>()?[0] != 0,
+            }),
+            3 => Some(Message::Leave {
+                code: RoomCode(reader.take()?),
+            }),
+            4 => Some(Message::Relay {
+                code: RoomCode(reader.take()?),
+                payload: reader.0.to_vec(),
+            }),
+            5 => {
+                let code = RoomCode(reader.take()?);
+                let player = reader.take::<1>()?[0];
+                let len = reader.take::<1>()?[0];
+                let mut ready = Vec::with_capacity(len as usize);
+                for _ in 0..len {
+                    ready.push(reader.take::<1>()?[0] != 0);
+                }
+                let seed = u64::from_le_bytes(reader.take()?);
+                let peer = decode_address(&mut reader)?;
+                Some(Message::Room(RoomState {
+                    code,
+                    player,
+                    ready,
+                    seed,
+                    peer,
+                }))
+            }
+            6 => LobbyError::from_byte(reader.take::<1>()?[0]).map(Message::Error),
+            7 => Some(Message::Relayed {
+                payload: reader.0.to_vec(),
+            }),
+            _ => None,
+        }
+    }
+}
+
+fn encode_address(bytes: &mut Vec<u8>, address: SocketAddr) {
+    match address.ip() {
+        IpAddr::V4(ip) => {
+            bytes.push(4);
+            bytes.extend_from_slice(&ip.octets());
+        }
+        IpAddr::V6(ip) => {
+            bytes.push(6);
+            bytes.extend_from_slice(&ip.octets());
+        }
+    }
+    bytes.extend_from_slice(&address.port().to_le_bytes());
+}
+
+// `Some(None)` when no address was sent
+fn decode_address(reader: &mut Reader) -> Option<Option<SocketAddr>> {
+    let ip = match reader.take::<1>()?[0] {
+        0 => return Some(None),
+        4 => IpAddr::V4(Ipv4Addr::from(reader.take::<4>()?)),
+        6 => IpAddr::V6(Ipv6Addr::from(reader.take::<16>()?)),
+        _ => return None,
+    };
+    let port = u16::from_le_bytes(reader.take()?);
+    Some(Some(SocketAddr::new(ip, port)))
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl Reader<'_> {
+    fn take<const N: usize>(&mut self) -> Option<[u8; N]> {
+        if self.0.len() < N {
+            return None;
+        }
+        let (head, tail) = self.0.split_at(N);
+        self.0 = tail;
+        head.try_into().ok()
+    }
+}
@@ -0,0 +1,289 @@
+use std::{
+    collections::HashMap,
+    io::{self, ErrorKind},
+    net::{SocketAddr, UdpSocket},
+    thread,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use crate::protocol::{LobbyError, Message, RoomCode, RoomState, ROOM_SIZE};
+
+const MAX_PACKET_SIZE: usize = 1500;
+// members that stop sending anything, relayed packets included, are dropped
+const MEMBER_TIMEOUT: Duration = Duration::from_secs(10);
+const IDLE_SLEEP: Duration = Duration::from_millis(1);
+
+struct Member {
+    address: SocketAddr,
+    ready: bool,
+    last_seen: Instant,
+}
+
+struct Room {
+    seed: u64,
+    members: Vec<Member>,
+    started: bool,
+}
+
+impl Room {
+    fn state(&self, code: RoomCode, player: usize) -> RoomState {
+        RoomState {
+            code,
+            player: player as u8,
+            ready: self.members.iter().map(|member| member.ready).collect(),
+            seed: self.seed,
+            peer: self
+                .started
+                .then(|| {
+                    self.members
+                        .iter()
+                        .enumerate()
+                        .find(|(index, _)| *index != player)
+                        .map(|(_, member)| member.address)
+                })
+                .flatten(),
+        }
+    }
+
+    fn member(&self, address: SocketAddr) -> Option<usize> {
+        self.members
+            .iter()
+            .position(|member| member.address == address)
+    }
+}
+
+// matches players up in rooms and forwards their packets when they can't reach each other
+pub struct Server {
+    socket: UdpSocket,
+    rooms: HashMap<RoomCode, Room>,
+    rng: u64,
+    relayed: u64,
+}
+
+impl Server {
+    pub fn bind(address: SocketAddr) -> io::Result<Self> {
+        let socket = UdpSocket::bind(address)?;
+        socket.set_nonblocking(true)?;
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        Ok(Self {
+            socket,
+            rooms: HashMap::new(),
+            // xorshift gets stuck on zero
+            rng: time.as_nanos() as u64 | 1,
+            relayed: 0,
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.socket.local_addr()
+    }
+
+    pub fn rooms(&self) -> usize {
+        self.rooms.len()
+    }
+
+    // packets forwarded between members so far
+    pub fn relayed(&self) -> u64 {
+        self.relayed
+    }
+
+    pub fn run(&mut self) -> io::Result<()> {
+        loop {
+            if !self.poll()? {
+                thread::sleep(IDLE_SLEEP);
+            }
+        }
+    }
+
+    // handles everything that has arrived without blocking, returns whether there was anything
+    pub fn poll(&mut self) -> io::Result<bool> {
+        let mut buffer = [0; MAX_PACKET_SIZE];
+        let mut received = false;
+        loop {
+            let (len, from) = match self.socket.recv_from(&mut buffer) {
+                Ok(received) => received,
+                Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                // e.g. a client that went away without leaving
+                Err(err) if err.kind() == ErrorKind::ConnectionReset => continue,
+                Err(err) => return Err(err),
+            };
+            received = true;
+            if let Some(message) = Message::decode(&buffer[..len]) {
+                self.handle(message, from);
+            }
+        }
+        self.expire();
+        Ok(received)
+    }
+
+    fn handle(&mut self, message: Message, from: SocketAddr) {
+        let now = Instant::now();
+        if let Some(code) = match &message {
+            Message::Join { code }
+            | Message::Ready { code, .. }
+            | Message::Leave { code }
+            | Message::Relay { code, .. } => Some(*code),
+            _ => None,
+        } {
+            let member = self.rooms.get_mut(&code).and_then(|room| {
+                let index = room.member(from)?;
+                Some(&mut room.members[index])
+            });
+            if let Some(member) = member {
+                member.last_seen = now;
+            }
+        }
+
+        match message {
+            Message::Create { seed } => {
+                // the answer got lost and the request was repeated
+                if let Some(code) = self
+                    .rooms
+                    .iter()
+                    .find(|(_, room)| room.member(from).is_some())
+                    .map(|(code, _)| *code)
+                {
+                    self.send_state(code);
+                    return;
+                }
+
+                let code = loop {
+                    let code = RoomCode::from_index(self.random());
+                    if !self.rooms.contains_key(&code) {
+                        break code;
+                    }
+                };
+                // short enough to read off the game over screen, like locally rolled seeds
+                let seed = seed.unwrap_or_else(|| self.random() as u32 as u64);
+                self.rooms.insert(
+                    code,
+                    Room {
+                        seed,
+                        members: vec![Member {
+                            address: from,
+                            ready: false,
+                            last_seen: now,
+                        }],
+                        started: false,
+                    },
+                );
+                println!("{from} created room {code}");
+                self.send_state(code);
+            }
+            Message::Join { code } => {
+                let Some(room) = self.rooms.get_mut(&code) else {
+                    self.send(Message::Error(LobbyError::RoomNotFound), from);
+                    return;
+                };
+                if room.member(from).is_none() {
+                    if room.started {
+                        self.send(Message::Error(LobbyError::AlreadyStarted), from);
+                        return;
+                    }
+                    if room.members.len() >= ROOM_SIZE {
+                        self.send(Message::Error(LobbyError::RoomFull), from);
+                        return;
+                    }
+                    room.members.push(Member {
+                        address: from,
+                        ready: false,
+                        last_seen: now,
+                    });
+                    println!("{from} joined room {code}");
+                }
+                self.send_state(code);
+            }
+            Message::Ready { code, ready } => {
+                let Some(room) = self.rooms.get_mut(&code) else {
+                    self.send(Message::Error(LobbyError::RoomNotFound), from);
+                    return;
+                };
+                let Some(index) = room.member(from) else {
+                    self.send(Message::Error(LobbyError::RoomNotFound), from);
+                    return;
+                };
+                if !room.started {
+                    room.members[index].ready = ready;
+                    room.started = room.members.len() == ROOM_SIZE
+                        && room.members.iter().all(|member| member.ready);
+                    if room.started {
+                        println!("room {code} started with seed {}", room.seed);
+                    }
+                }
+                self.send_state(code);
+            }
+            Message::Leave { code } => {
+                let Some(room) = self.rooms.get_mut(&code) else {
+                    return;
+                };
+                let Some(index) = room.member(from) else {
+                    return;
+                };
+                room.members.remove(index);
+                println!("{from} left room {code}");
+                if room.members.is_empty() {
+                    self.rooms.remove(&code);
+                } else {
+                    self.send_state(code);
+                }
+            }
+            Message::Relay { code, payload } => {
+                let Some(room) = self.rooms.get(&code) else {
+                    return;
+                };
+                if room.member(from).is_none() {
+                    return;
+                }
+                let bytes = Message::Relayed { payload }.encode();
+                for member in room.members.iter().filter(|member| member.address != from) {
+                    // lost packets are resent by the game anyway
+                    let _ = self.socket.send_to(&bytes, member.address);
+                    self.relayed += 1;
+                }
+            }
+            // only ever sent by the server
+            Message::Room(_) | Message::Error(_) | Message::Relayed { .. } => {}
+        }
+    }
+
+    // every member hears about a change right away instead of on its next request
+    fn send_state(&self, code: RoomCode) {
+        let Some(room) = self.rooms.get(&code) else {
+            return;
+        };
+        for (player, member) in room.members.iter().enumerate() {
+            self.send(Message::Room(room.state(code, player)), member.address);
+        }
+    }
+
+    fn send(&self, message: Message, to: SocketAddr) {
+        let _ = self.socket.send_to(&message.encode(), to);
+    }
+
+    fn expire(&mut self) {
+        let now = Instant::now();
+        let mut changed = Vec::new();
+        self.rooms.retain(|code, room| {
+            let members = room.members.len();
+            room.members
+                .retain(|member| now.duration_since(member.last_seen) < MEMBER_TIMEOUT);
+            if room.members.len() != members {
+                changed.push(*code);
+            }
+            !room.members.is_empty()
+        });
+        for code in changed {
+            self.send_state(code);
+        }
+    }
+
+    // xorshift64*, room codes and seeds don't need anything better
+    fn random(&mut self) -> u64 {
+        self.rng ^= self.rng >> 12;
+        self.rng ^= self.rng << 25;
+        self.rng ^= self.rng >> 27;
+        self.rng.wrapping_mul(0x2545f4914f6cdd1d)
+    }
+}
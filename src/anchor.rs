@@ -30,7 +30,6 @@ fn transform(
     query.for_each_mut(|(mut transform, material_handle, anchored_sprite)| {
         let material = materials.get(material_handle).unwrap();
         let image_handle = material.texture.clone().unwrap();
-        let (camera_transform, projection) = projection_query.single();
         if let Some(image) = images.get(image_handle) {
             let game_size = game_boundaries.size();
             let anchor_position = game_boundaries.min
@@ -43,9 +42,17 @@ fn transform(
 
             // definitely don't like this implementation
             if anchored_sprite.stretch {
-                let min_area = projection.area.min + camera_transform.translation.xy();
-                transform.scale.y = game_boundaries.min.y - min_area.y;
-                transform.translation.y = min_area.y + transform.scale.y * 0.5;
+                // down to the bottom of the lowest view
+                let min_y = projection_query
+                    .iter()
+                    .map(|(camera_transform, projection)| {
+                        projection.area.min.y + camera_transform.translation.y
+                    })
+                    .reduce(f32::min);
+                if let Some(min_y) = min_y {
+                    transform.scale.y = game_boundaries.min.y - min_y;
+                    transform.translation.y = min_y + transform.scale.y * 0.5;
+                }
             }
         }
     });
@@ -6,11 +6,15 @@ pub struct Animation(pub benimator::Animation);
 #[derive(Default, Component, Deref, DerefMut)]
 pub struct AnimationState(pub benimator::State);
 
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AnimationSet;
+
 pub struct AnimationPlugin;
 
 impl Plugin for AnimationPlugin {
     fn build(&self, app: &mut App) {
-        app.init_asset::<Animation>().add_systems(Update, animate);
+        app.init_asset::<Animation>()
+            .add_systems(Update, animate.in_set(AnimationSet));
     }
 }
 
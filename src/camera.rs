@@ -2,7 +2,14 @@ use bevy::prelude::*;
 use bevy_camera_shake::{CameraShakePlugin, RandomSource, Shake2d};
 use rand::{thread_rng, Rng};
 
-use crate::{game::GameBoundaries, player::Player};
+use crate::{
+    game::GameBoundaries,
+    player::{LocalPlayer, PlayerId},
+};
+
+// the bird a split screen view follows, views without one follow the `LocalPlayer`
+#[derive(Component, Clone, Copy, PartialEq, Eq, Deref)]
+pub struct PlayerView(pub PlayerId);
 
 pub struct GameCameraPlugin;
 
@@ -29,9 +36,24 @@ impl Plugin for GameCameraPlugin {
 }
 
 fn setup(mut commands: Commands) {
+    spawn_camera(&mut commands, camera_bundle(0), ());
+}
+
+pub fn camera_bundle(order: isize) -> Camera2dBundle {
     let mut camera_bundle = Camera2dBundle::default();
+    camera_bundle.camera.order = order;
     camera_bundle.projection.scale *= 0.25;
     // camera_bundle.transform.translation.x += 50.0;
+    camera_bundle
+}
+
+// the shake is applied to the parent so it doesn't fight with following the bird,
+// `bundle` goes on both
+pub fn spawn_camera(
+    commands: &mut Commands,
+    camera_bundle: Camera2dBundle,
+    bundle: impl Bundle + Clone,
+) -> Entity {
     commands
         .spawn((
             SpatialBundle::default(),
@@ -43,22 +65,40 @@ fn setup(mut commands: Commands) {
                 decay: 0.75,
                 random_sources: [Box::new(Random), Box::new(Random), Box::new(Random)],
             },
+            bundle.clone(),
         ))
         .with_children(|parent| {
-            parent.spawn(camera_bundle);
-        });
+            parent.spawn((camera_bundle, bundle));
+        })
+        .id()
+}
+
+// lowest and highest a camera following a bird can go, `None` when the view is too tall to move at all
+pub fn camera_clamp(
+    game_boundaries: &GameBoundaries,
+    projection: &OrthographicProjection,
+) -> Option<(f32, f32)> {
+    let min_clamp = game_boundaries.min.y + projection.area.half_size().y * 0.75;
+    let max_clamp = game_boundaries.max.y - projection.area.half_size().y * 0.75;
+    (min_clamp <= max_clamp).then_some((min_clamp, max_clamp))
 }
 
 fn update_camera(
-    mut query: Query<(&mut Transform, &OrthographicProjection), With<Camera>>,
-    player_query: Query<&Transform, (With<Player>, Without<Camera>)>,
+    mut query: Query<(&mut Transform, &OrthographicProjection, Option<&PlayerView>), With<Camera>>,
+    player_query: Query<(&Transform, &PlayerId), Without<Camera>>,
     game_boundaries: Res<GameBoundaries>,
+    local_player: Res<LocalPlayer>,
 ) {
-    query.for_each_mut(|(mut transform, projection)| {
+    query.for_each_mut(|(mut transform, projection, player_view)| {
         let width = game_boundaries.max.x * 0.5 - game_boundaries.min.x * 0.5;
         transform.translation.x = width * 0.25;
 
-        player_query.for_each(|player_transform| {
+        let followed = player_view.map_or(**local_player, |player_view| **player_view);
+        player_query.for_each(|(player_transform, player_id)| {
+            if *player_id != followed {
+                return;
+            }
+
             if player_transform.translation.y
                 > transform.translation.y + projection.area.size().y * 0.25
             {
@@ -68,14 +108,11 @@ fn update_camera(
                 transform.translation.y = player_transform.translation.y;
             }
 
-            let min_clamp = game_boundaries.min.y + projection.area.half_size().y * 0.75;
-            let max_clamp = game_boundaries.max.y - projection.area.half_size().y * 0.75;
-
-            if min_clamp > max_clamp {
+            let Some((min_clamp, max_clamp)) = camera_clamp(&game_boundaries, projection) else {
                 transform.translation.y =
                     game_boundaries.size().y * 0.5 - projection.area.half_size().y * 0.5;
                 return;
-            }
+            };
 
             if transform.translation.y > max_clamp {
                 transform.translation.y = max_clamp;
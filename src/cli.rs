@@ -0,0 +1,151 @@
+use std::{net::SocketAddr, path::PathBuf, str::FromStr};
+
+use bevy::prelude::*;
+
+use crate::{difficulty::Difficulty, networking::NUM_PLAYERS};
+
+const USAGE: &str = "usage: flappy_bird [--seed <number>] [--difficulty <easy|normal|hard>] [--tick-rate <hz>] [--replay <file>]
+       flappy_bird --headless [--seed <number>] [--difficulty <preset>] [--tick-rate <hz>] [--script <file> | --replay <file>] [--max-ticks <number>]
+       flappy_bird [--headless] --peer <address> [--bind <address>] [--player <index>] [--seed <number>] [--difficulty <preset>]
+       flappy_bird --loopback [--seed <number>] [--difficulty <preset>] [--script <file>]
+       flappy_bird [--headless] --broadcast <address> [--seed <number>] [--difficulty <preset>] [--tick-rate <hz>] [--script <file>]
+       flappy_bird [--headless] --spectate <address> [--delay <seconds>]
+       flappy_bird [--lobby <address>] [--relay] [--seed <number>] [--difficulty <preset>]";
+
+#[derive(Resource, Default, Clone, Debug)]
+pub struct Args {
+    pub seed: Option<u64>,
+    pub difficulty: Option<Difficulty>,
+    pub tick_rate: Option<f64>,
+    pub replay: Option<PathBuf>,
+    pub headless: bool,
+    pub script: Option<PathBuf>,
+    pub max_ticks: Option<u32>,
+    pub peer: Option<SocketAddr>,
+    pub bind: Option<SocketAddr>,
+    pub player: usize,
+    pub loopback: bool,
+    pub broadcast: Option<SocketAddr>,
+    pub spectate: Option<SocketAddr>,
+    pub delay: Option<f32>,
+    pub lobby: Option<SocketAddr>,
+    pub relay: bool,
+}
+
+impl Args {
+    pub fn from_env() -> Result<Self, String> {
+        Self::parse(std::env::args().skip(1))
+    }
+
+    pub fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut parsed = Self::default();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--seed" => parsed.seed = Some(value(&mut args, &arg)?),
+                "--difficulty" => parsed.difficulty = Some(value(&mut args, &arg)?),
+                "--tick-rate" => parsed.tick_rate = Some(value(&mut args, &arg)?),
+                "--replay" => parsed.replay = Some(value(&mut args, &arg)?),
+                "--headless" => parsed.headless = true,
+                "--script" => parsed.script = Some(value(&mut args, &arg)?),
+                "--max-ticks" => parsed.max_ticks = Some(value(&mut args, &arg)?),
+                "--peer" => parsed.peer = Some(value(&mut args, &arg)?),
+                "--bind" => parsed.bind = Some(value(&mut args, &arg)?),
+                "--player" => parsed.player = value(&mut args, &arg)?,
+                "--loopback" => parsed.loopback = true,
+                "--broadcast" => parsed.broadcast = Some(value(&mut args, &arg)?),
+                "--spectate" => parsed.spectate = Some(value(&mut args, &arg)?),
+                "--delay" => parsed.delay = Some(value(&mut args, &arg)?),
+                "--lobby" => parsed.lobby = Some(value(&mut args, &arg)?),
+                "--relay" => parsed.relay = true,
+                "--help" | "-h" => return Err(USAGE.into()),
+                _ => return Err(format!("unknown argument {arg:?}\n{USAGE}")),
+            }
+        }
+
+        // a loopback game scripts the second peer
+        if parsed.script.is_some() && !parsed.headless && !parsed.loopback {
+            return Err(format!(
+                "--script requires --headless or --loopback\n{USAGE}"
+            ));
+        }
+        if parsed.peer.is_none() && (parsed.bind.is_some() || parsed.player != 0) {
+            return Err(format!("--bind and --player require --peer\n{USAGE}"));
+        }
+        if parsed.player >= NUM_PLAYERS {
+            return Err(format!("--player must be less than {NUM_PLAYERS}\n{USAGE}"));
+        }
+        if parsed.loopback && (parsed.peer.is_some() || parsed.headless) {
+            return Err(format!(
+                "--loopback can't be combined with --peer or --headless\n{USAGE}"
+            ));
+        }
+        if parsed.replay.is_some() && (parsed.peer.is_some() || parsed.loopback) {
+            return Err(format!("--replay can't be used in a network game\n{USAGE}"));
+        }
+        // network races tick at the same rate on both sides and replays at the one they were recorded at
+        if parsed.tick_rate.is_some()
+            && (parsed.peer.is_some()
+                || parsed.loopback
+                || parsed.lobby.is_some()
+                || parsed.replay.is_some())
+        {
+            return Err(format!(
+                "--tick-rate can't be used in a network game or with --replay\n{USAGE}"
+            ));
+        }
+        if parsed
+            .tick_rate
+            .is_some_and(|tick_rate| !(10.0..=240.0).contains(&tick_rate))
+        {
+            return Err(format!("--tick-rate must be between 10 and 240\n{USAGE}"));
+        }
+        // only solo runs are broadcast
+        if parsed.broadcast.is_some() && (parsed.peer.is_some() || parsed.loopback) {
+            return Err(format!(
+                "--broadcast can't be used in a network game\n{USAGE}"
+            ));
+        }
+        // everything about the run comes from the broadcast
+        if parsed.spectate.is_some()
+            && (parsed.seed.is_some()
+                || parsed.difficulty.is_some()
+                || parsed.tick_rate.is_some()
+                || parsed.replay.is_some()
+                || parsed.script.is_some()
+                || parsed.peer.is_some()
+                || parsed.loopback
+                || parsed.broadcast.is_some())
+        {
+            return Err(format!(
+                "--spectate can only be combined with --headless and --delay\n{USAGE}"
+            ));
+        }
+        // the lobby is picked from the main menu
+        if (parsed.lobby.is_some() || parsed.relay)
+            && (parsed.headless || parsed.peer.is_some() || parsed.loopback)
+        {
+            return Err(format!(
+                "--lobby and --relay can't be combined with --headless, --peer or --loopback\n{USAGE}"
+            ));
+        }
+        if parsed.delay.is_some() && parsed.spectate.is_none() {
+            return Err(format!("--delay requires --spectate\n{USAGE}"));
+        }
+        if parsed
+            .delay
+            .is_some_and(|delay| !(0.0..=60.0).contains(&delay))
+        {
+            return Err(format!("--delay must be between 0 and 60 seconds\n{USAGE}"));
+        }
+        Ok(parsed)
+    }
+}
+
+fn value<T: FromStr>(args: &mut impl Iterator<Item = String>, name: &str) -> Result<T, String> {
+    let value = args
+        .next()
+        .ok_or(format!("missing value for {name}\n{USAGE}"))?;
+    value
+        .parse()
+        .map_err(|_| format!("invalid value {value:?} for {name}\n{USAGE}"))
+}
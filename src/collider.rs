@@ -0,0 +1,541 @@
+use std::sync::Arc;
+
+use bevy::{
+    math::{Affine2, Mat2},
+    prelude::*,
+    render::render_resource::TextureFormat,
+};
+
+// described in the entity's own space and placed with its `GlobalTransform`, so a collider turns,
+// scales and flips along with whatever it's attached to
+#[derive(Component, Clone, Debug)]
+pub enum Collider {
+    Box { size: Vec2 },
+    Circle { radius: f32 },
+    // round at both ends, lying along the local x axis
+    Capsule { half_length: f32, radius: f32 },
+    // solid wherever the image it was made from is
+    Mask(Arc<AlphaMask>),
+}
+
+impl From<Vec2> for Collider {
+    fn from(value: Vec2) -> Self {
+        Self::Box { size: value }
+    }
+}
+
+impl Collider {
+    // every pixel at least `threshold` opaque is solid, `None` for images that aren't plain rgba
+    pub fn mask(image: &Image, threshold: u8) -> Option<Self> {
+        if !matches!(
+            image.texture_descriptor.format,
+            TextureFormat::Rgba8Unorm | TextureFormat::Rgba8UnormSrgb
+        ) {
+            return None;
+        }
+        let size = image.size();
+        let solid = image
+            .data
+            .chunks_exact(4)
+            .map(|pixel| pixel[3] >= threshold)
+            .collect::<Vec<_>>();
+        if solid.len() != (size.x * size.y) as usize {
+            return None;
+        }
+        Some(Self::Mask(Arc::new(AlphaMask { size, solid })))
+    }
+
+    // half the width and height before the transform is applied
+    pub fn half_size(&self) -> Vec2 {
+        match self {
+            Collider::Box { size } => *size * 0.5,
+            Collider::Circle { radius } => Vec2::splat(*radius),
+            Collider::Capsule {
+                half_length,
+                radius,
+            } => Vec2::new(half_length + radius, *radius),
+            Collider::Mask(mask) => mask.size.as_vec2() * 0.5,
+        }
+    }
+
+    pub(crate) fn shape(&self, transform: &GlobalTransform) -> Shape<'_> {
+        let affine = transform.affine();
+        let to_world = Affine2::from_mat2_translation(
+            Mat2::from_cols(affine.matrix3.x_axis.xy(), affine.matrix3.y_axis.xy()),
+            affine.translation.xy(),
+        );
+        // round shapes can't be stretched, so they take the larger of the two scales
+        let scale = to_world
+            .matrix2
+            .x_axis
+            .length()
+            .max(to_world.matrix2.y_axis.length());
+        let point = |point: Vec2| to_world.transform_point2(point);
+
+        match self {
+            Collider::Box { size } => {
+                let half = *size * 0.5;
+                Shape::Convex(Convex {
+                    points: [
+                        point(Vec2::new(-half.x, -half.y)),
+                        point(Vec2::new(half.x, -half.y)),
+                        point(Vec2::new(half.x, half.y)),
+                        point(Vec2::new(-half.x, half.y)),
+                    ],
+                    len: 4,
+                    radius: 0.0,
+                })
+            }
+            Collider::Circle { radius } => Shape::Convex(Convex {
+                points: [point(Vec2::ZERO); 4],
+                len: 1,
+                radius: radius * scale,
+            }),
+            Collider::Capsule {
+                half_length,
+                radius,
+            } => Shape::Convex(Convex {
+                points: [
+                    point(Vec2::new(-half_length, 0.0)),
+                    point(Vec2::new(*half_length, 0.0)),
+                    Vec2::ZERO,
+                    Vec2::ZERO,
+                ],
+                len: 2,
+                radius: radius * scale,
+            }),
+            Collider::Mask(mask) => Shape::Mask { mask, to_world },
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Layer {
+    Bird,
+    Pipe,
+    Score,
+}
+
+impl Layer {
+    pub const ALL: [Layer; 3] = [Layer::Bird, Layer::Pipe, Layer::Score];
+
+    fn bit(self) -> u32 {
+        1 << self as u32
+    }
+}
+
+// which layers a collider is on and which ones it looks for, a pair is only checked when each of
+// them is looking for the other. colliders without one are on every layer and look for all of them
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CollisionLayers {
+    memberships: u32,
+    filters: u32,
+}
+
+impl Default for CollisionLayers {
+    fn default() -> Self {
+        Self {
+            memberships: u32::MAX,
+            filters: u32::MAX,
+        }
+    }
+}
+
+impl CollisionLayers {
+    pub fn new(
+        memberships: impl IntoIterator<Item = Layer>,
+        filters: impl IntoIterator<Item = Layer>,
+    ) -> Self {
+        Self {
+            memberships: layer_bits(memberships),
+            filters: layer_bits(filters),
+        }
+    }
+
+    pub fn contains(&self, layer: Layer) -> bool {
+        self.memberships & layer.bit() != 0
+    }
+
+    pub fn interacts_with(&self, other: &Self) -> bool {
+        self.memberships & other.filters != 0 && other.memberships & self.filters != 0
+    }
+}
+
+fn layer_bits(layers: impl IntoIterator<Item = Layer>) -> u32 {
+    layers.into_iter().fold(0, |bits, layer| bits | layer.bit())
+}
+
+// reports what it overlaps without being solid, nothing is pushed out of a sensor
+#[derive(Component, Clone, Copy, Default, Debug)]
+pub struct Sensor;
+
+#[derive(Debug)]
+pub struct AlphaMask {
+    size: UVec2,
+    // row by row from the top, like the image
+    solid: Vec<bool>,
+}
+
+impl AlphaMask {
+    pub fn size(&self) -> UVec2 {
+        self.size
+    }
+
+    pub fn is_solid(&self, x: u32, y: u32) -> bool {
+        x < self.size.x && y < self.size.y && self.solid[(y * self.size.x + x) as usize]
+    }
+
+    // the middle of a pixel in the mask's own space, which is centered and has y pointing up
+    fn pixel_center(&self, x: u32, y: u32) -> Vec2 {
+        Vec2::new(
+            x as f32 + 0.5 - self.size.x as f32 * 0.5,
+            self.size.y as f32 * 0.5 - y as f32 - 0.5,
+        )
+    }
+
+    // pixels that could be inside `rect`, given in the mask's own space
+    fn pixels_within(&self, rect: Rect) -> impl Iterator<Item = (u32, u32)> + '_ {
+        let half = self.size.as_vec2() * 0.5;
+        let min = (Vec2::new(rect.min.x + half.x, half.y - rect.max.y) - 0.5)
+            .floor()
+            .max(Vec2::ZERO);
+        let max = (Vec2::new(rect.max.x + half.x, half.y - rect.min.y) + 0.5)
+            .ceil()
+            .min(self.size.as_vec2());
+        let (min, max) = (min.as_uvec2(), max.as_uvec2());
+        (min.y..max.y)
+            .flat_map(move |y| (min.x..max.x).map(move |x| (x, y)))
+            .filter(|(x, y)| self.is_solid(*x, *y))
+    }
+}
+
+// a point, line or quad grown by a radius, in world space
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Convex {
+    points: [Vec2; 4],
+    len: usize,
+    radius: f32,
+}
+
+impl Convex {
+    fn points(&self) -> &[Vec2] {
+        &self.points[..self.len]
+    }
+
+    fn center(&self) -> Vec2 {
+        self.points().iter().sum::<Vec2>() / self.len as f32
+    }
+
+    fn edges(&self) -> impl Iterator<Item = (Vec2, Vec2)> + '_ {
+        let points = self.points();
+        (0..points.len()).map(|i| (points[i], points[(i + 1) % points.len()]))
+    }
+
+    // everything that can separate this from another shape, with lines also tried end on
+    fn axes(&self) -> impl Iterator<Item = Vec2> + '_ {
+        self.edges()
+            .filter(|(start, end)| start != end)
+            .flat_map(move |(start, end)| {
+                let direction = (end - start).normalize();
+                let ends = (self.len == 2).then_some(direction);
+                std::iter::once(direction.perp()).chain(ends)
+            })
+    }
+
+    fn project(&self, axis: Vec2) -> (f32, f32) {
+        self.points()
+            .iter()
+            .map(|point| point.dot(axis))
+            .fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), value| {
+                (min.min(value), max.max(value))
+            })
+    }
+
+    fn support(&self, direction: Vec2) -> Vec2 {
+        *self
+            .points()
+            .iter()
+            .max_by(|a, b| a.dot(direction).total_cmp(&b.dot(direction)))
+            .unwrap()
+    }
+
+    fn closest_point(&self, point: Vec2) -> Vec2 {
+        if self.len == 1 {
+            return self.points[0];
+        }
+        self.edges()
+            .map(|(start, end)| {
+                let edge = end - start;
+                let t = ((point - start).dot(edge) / edge.length_squared().max(f32::EPSILON))
+                    .clamp(0.0, 1.0);
+                start + edge * t
+            })
+            .min_by(|a, b| {
+                a.distance_squared(point)
+                    .total_cmp(&b.distance_squared(point))
+            })
+            .unwrap()
+    }
+
+    fn contains(&self, point: Vec2) -> bool {
+        let inside_core = self.len == 4
+            && self.axes().all(|axis| {
+                let (min, max) = self.project(axis);
+                (min..=max).contains(&point.dot(axis))
+            });
+        inside_core || self.closest_point(point).distance(point) <= self.radius
+    }
+
+    fn aabb(&self) -> Rect {
+        let (min, max) = self.points().iter().fold(
+            (Vec2::splat(f32::INFINITY), Vec2::splat(f32::NEG_INFINITY)),
+            |(min, max), point| (min.min(*point), max.max(*point)),
+        );
+        Rect::from_corners(min - self.radius, max + self.radius)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Shape<'a> {
+    Convex(Convex),
+    Mask {
+        mask: &'a AlphaMask,
+        to_world: Affine2,
+    },
+}
+
+impl Shape<'_> {
+    pub fn aabb(&self) -> Rect {
+        match self {
+            Shape::Convex(convex) => convex.aabb(),
+            Shape::Mask { mask, to_world } => {
+                let half = mask.size.as_vec2() * 0.5;
+                let corners = [
+                    Vec2::new(-half.x, -half.y),
+                    Vec2::new(half.x, -half.y),
+                    Vec2::new(half.x, half.y),
+                    Vec2::new(-half.x, half.y),
+                ]
+                .map(|corner| to_world.transform_point2(corner));
+                let min = corners.into_iter().reduce(Vec2::min).unwrap();
+                let max = corners.into_iter().reduce(Vec2::max).unwrap();
+                Rect::from_corners(min, max)
+            }
+        }
+    }
+
+    pub fn translated(&self, offset: Vec2) -> Self {
+        let mut shape = *self;
+        match &mut shape {
+            Shape::Convex(convex) => {
+                for point in convex.points.iter_mut() {
+                    *point += offset;
+                }
+            }
+            Shape::Mask { to_world, .. } => to_world.translation += offset,
+        }
+        shape
+    }
+
+    // whether the shape can be swept exactly as its bounding box
+    pub fn is_aabb(&self) -> bool {
+        match self {
+            Shape::Convex(convex) => {
+                convex.len == 4
+                    && convex.radius == 0.0
+                    && convex.edges().all(|(start, end)| {
+                        let edge = end - start;
+                        edge.x == 0.0 || edge.y == 0.0
+                    })
+            }
+            Shape::Mask { .. } => false,
+        }
+    }
+
+    pub fn draw(&self, gizmos: &mut Gizmos, color: Color) {
+        match self {
+            Shape::Convex(convex) => {
+                let points = convex.points();
+                match points.len() {
+                    1 => {
+                        gizmos.circle_2d(points[0], convex.radius, color);
+                    }
+                    2 => {
+                        let side =
+                            (points[1] - points[0]).perp().normalize_or_zero() * convex.radius;
+                        for point in points {
+                            gizmos.circle_2d(*point, convex.radius, color);
+                        }
+                        gizmos.line_2d(points[0] + side, points[1] + side, color);
+                        gizmos.line_2d(points[0] - side, points[1] - side, color);
+                    }
+                    _ => gizmos.linestrip_2d(points.iter().chain(&points[..1]).copied(), color),
+                }
+            }
+            Shape::Mask { mask, to_world } => {
+                let half = mask.size.as_vec2() * 0.5;
+                let corners = [
+                    Vec2::new(-half.x, -half.y),
+                    Vec2::new(half.x, -half.y),
+                    Vec2::new(half.x, half.y),
+                    Vec2::new(-half.x, half.y),
+                    Vec2::new(-half.x, -half.y),
+                ];
+                gizmos.linestrip_2d(
+                    corners.map(|corner| to_world.transform_point2(corner)),
+                    color,
+                );
+            }
+        }
+    }
+
+    fn center(&self) -> Vec2 {
+        match self {
+            Shape::Convex(convex) => convex.center(),
+            Shape::Mask { to_world, .. } => to_world.translation,
+        }
+    }
+}
+
+// where two shapes touch, with the normal pointing from the second towards the first and how far
+// the first has to move along it to be clear
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Contact {
+    pub point: Vec2,
+    pub normal: Vec2,
+    pub depth: f32,
+}
+
+pub(crate) fn contact(a: &Shape, b: &Shape) -> Option<Contact> {
+    match (a, b) {
+        (Shape::Convex(a), Shape::Convex(b)) => convex_contact(a, b),
+        (Shape::Mask { .. }, _) | (_, Shape::Mask { .. }) => mask_contact(a, b),
+    }
+}
+
+fn convex_contact(a: &Convex, b: &Convex) -> Option<Contact> {
+    let radius = a.radius + b.radius;
+    let away = a.center() - b.center();
+
+    // the cores overlap unless some axis separates them, the shallowest one is the way out
+    let mut shallowest: Option<(Vec2, f32)> = None;
+    for axis in a.axes().chain(b.axes()) {
+        let (a_min, a_max) = a.project(axis);
+        let (b_min, b_max) = b.project(axis);
+        let overlap = a_max.min(b_max) - a_min.max(b_min);
+        if overlap < 0.0 {
+            shallowest = None;
+            break;
+        }
+        // when a is further along the axis it gets out the positive way
+        let (axis, depth) = if a_min + a_max >= b_min + b_max {
+            (axis, b_max - a_min)
+        } else {
+            (-axis, a_max - b_min)
+        };
+        if shallowest.map_or(true, |(_, shallowest)| depth < shallowest) {
+            shallowest = Some((axis, depth));
+        }
+    }
+    if let Some((normal, depth)) = shallowest {
+        let point = (a.support(-normal) + b.support(normal)) * 0.5;
+        return Some(Contact {
+            point,
+            normal,
+            depth: depth + radius,
+        });
+    }
+
+    // otherwise only the rounded parts can touch
+    let (a_point, b_point) = a
+        .points()
+        .iter()
+        .map(|point| (*point, b.closest_point(*point)))
+        .chain(
+            b.points()
+                .iter()
+                .map(|point| (a.closest_point(*point), *point)),
+        )
+        .min_by(|(a1, b1), (a2, b2)| a1.distance(*b1).total_cmp(&a2.distance(*b2)))
+        .unwrap();
+    let distance = a_point.distance(b_point);
+    if distance > radius {
+        return None;
+    }
+    let normal = if distance > 0.0 {
+        (a_point - b_point) / distance
+    } else {
+        away.try_normalize().unwrap_or(Vec2::Y)
+    };
+    Some(Contact {
+        point: b_point + normal * b.radius,
+        normal,
+        depth: radius - distance,
+    })
+}
+
+// masks are checked pixel by pixel, the contact is worked out from the overlapping pixels
+fn mask_contact(a: &Shape, b: &Shape) -> Option<Contact> {
+    let (mask, to_world, other) = match (a, b) {
+        (Shape::Mask { mask, to_world }, other) | (other, Shape::Mask { mask, to_world }) => {
+            (*mask, *to_world, other)
+        }
+        _ => unreachable!(),
+    };
+    let to_local = to_world.inverse();
+    let bounds = other.aabb();
+    let local_corners = [
+        bounds.min,
+        Vec2::new(bounds.max.x, bounds.min.y),
+        bounds.max,
+        Vec2::new(bounds.min.x, bounds.max.y),
+    ]
+    .map(|corner| to_local.transform_point2(corner));
+    let local_bounds = Rect::from_corners(
+        local_corners.into_iter().reduce(Vec2::min).unwrap(),
+        local_corners.into_iter().reduce(Vec2::max).unwrap(),
+    );
+
+    let overlapping = mask
+        .pixels_within(local_bounds)
+        .map(|(x, y)| to_world.transform_point2(mask.pixel_center(x, y)))
+        .filter(|point| match other {
+            Shape::Convex(convex) => convex.contains(*point),
+            Shape::Mask { mask, to_world } => {
+                let local = to_world.inverse().transform_point2(*point);
+                let half = mask.size.as_vec2() * 0.5;
+                let pixel = Vec2::new(local.x + half.x, half.y - local.y);
+                pixel.x >= 0.0 && pixel.y >= 0.0 && mask.is_solid(pixel.x as u32, pixel.y as u32)
+            }
+        })
+        .collect::<Vec<_>>();
+    if overlapping.is_empty() {
+        return None;
+    }
+
+    let point = overlapping.iter().sum::<Vec2>() / overlapping.len() as f32;
+    let min = overlapping.iter().copied().reduce(Vec2::min).unwrap();
+    let max = overlapping.iter().copied().reduce(Vec2::max).unwrap();
+    // a pixel is as big as the mask is scaled
+    let extent = max - min
+        + to_world
+            .matrix2
+            .x_axis
+            .length()
+            .max(to_world.matrix2.y_axis.length());
+    // the thinner side of the overlap is the quickest way out, towards wherever a is
+    let away = a.center() - b.center();
+    Some(if extent.x < extent.y {
+        Contact {
+            point,
+            normal: Vec2::X * away.x.signum(),
+            depth: extent.x,
+        }
+    } else {
+        Contact {
+            point,
+            normal: Vec2::Y * away.y.signum(),
+            depth: extent.y,
+        }
+    })
+}
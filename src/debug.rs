@@ -0,0 +1,227 @@
+use bevy::{gizmos::GizmoConfig, input::common_conditions::input_just_pressed, prelude::*};
+
+use crate::{
+    camera::camera_clamp,
+    collider::{Collider, CollisionLayers, Layer},
+    difficulty::Difficulty,
+    game::{DistanceTraveled, GameBoundaries, GameScore, GameState},
+    hud::label_style,
+    physics::{CollisionEvent, Velocity},
+    pipes::Pipes,
+    player::{Player, PlayerId, PlayerScore},
+    tuning::Tuning,
+};
+
+// how far ahead a velocity arrow reaches
+const VELOCITY_SECONDS: f32 = 0.1;
+const CONTACT_RADIUS: f32 = 1.5;
+
+// everything the physics and the camera work with drawn over the game, toggled with F3
+#[derive(Resource, Default, Deref, DerefMut)]
+pub struct DebugOverlay(pub bool);
+
+// where the most recent collisions touched and which way they pushed, kept until the next ones
+#[derive(Resource, Default, Deref, DerefMut)]
+struct LastContacts(Vec<(Vec2, Vec2)>);
+
+#[derive(Component)]
+pub struct DebugPanel;
+
+pub struct DebugPlugin;
+
+impl Plugin for DebugPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DebugOverlay>()
+            .init_resource::<LastContacts>()
+            .add_systems(
+                Update,
+                (
+                    toggle_overlay.run_if(input_just_pressed(KeyCode::F3)),
+                    show_panel.run_if(resource_changed::<DebugOverlay>()),
+                    record_contacts,
+                    update_panel.run_if(overlay_enabled),
+                )
+                    .chain(),
+            )
+            // headless runs have nothing to draw with
+            .add_systems(
+                Update,
+                (draw_colliders, draw_velocities, draw_bounds, draw_contacts)
+                    .after(record_contacts)
+                    .run_if(overlay_enabled.and_then(resource_exists::<GizmoConfig>())),
+            );
+    }
+}
+
+fn overlay_enabled(overlay: Res<DebugOverlay>) -> bool {
+    **overlay
+}
+
+fn toggle_overlay(mut overlay: ResMut<DebugOverlay>) {
+    **overlay = !**overlay;
+}
+
+fn layer_color(layers: Option<&CollisionLayers>) -> Color {
+    // colliders without layers are on all of them
+    let Some(layers) = layers else {
+        return Color::WHITE;
+    };
+    match Layer::ALL.into_iter().find(|layer| layers.contains(*layer)) {
+        Some(Layer::Bird) => Color::YELLOW,
+        Some(Layer::Pipe) => Color::LIME_GREEN,
+        Some(Layer::Score) => Color::CYAN,
+        None => Color::GRAY,
+    }
+}
+
+fn show_panel(
+    mut commands: Commands,
+    panel_query: Query<Entity, With<DebugPanel>>,
+    overlay: Res<DebugOverlay>,
+) {
+    panel_query.for_each(|entity| {
+        commands.entity(entity).despawn_recursive();
+    });
+    if !**overlay {
+        return;
+    }
+
+    commands.spawn((
+        TextBundle {
+            // under the tuning errors, above everything else
+            z_index: ZIndex::Global(i32::MAX - 1),
+            ..TextBundle::from_section(
+                "",
+                TextStyle {
+                    color: Color::YELLOW,
+                    ..label_style()
+                },
+            )
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                left: Val::Px(4.0),
+                top: Val::Px(4.0),
+                ..default()
+            })
+        },
+        DebugPanel,
+    ));
+}
+
+fn record_contacts(
+    mut collision_events: EventReader<CollisionEvent>,
+    mut last_contacts: ResMut<LastContacts>,
+) {
+    let contacts = collision_events
+        .read()
+        .map(|event| (event.contact, event.normal * event.depth))
+        .collect::<Vec<_>>();
+    if !contacts.is_empty() {
+        **last_contacts = contacts;
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn update_panel(
+    mut panel_query: Query<&mut Text, With<DebugPanel>>,
+    entity_query: Query<()>,
+    collider_query: Query<(), With<Collider>>,
+    pipes_query: Query<(), With<Pipes>>,
+    player_query: Query<(&PlayerId, &PlayerScore), With<Player>>,
+    game_state: Res<State<GameState>>,
+    game_score: Res<GameScore>,
+    distance_traveled: Res<DistanceTraveled>,
+    tuning: Res<Tuning>,
+    difficulty: Res<Difficulty>,
+) {
+    let level = tuning.level(*difficulty, **distance_traveled);
+    let mut birds = player_query.iter().collect::<Vec<_>>();
+    birds.sort_by_key(|(player_id, _)| ***player_id);
+    // every bird keeps its own score whenever there's more than one
+    let score = if birds.len() > 1 {
+        birds
+            .iter()
+            .map(|(player_id, score)| format!("P{} {}", ***player_id + 1, score.score))
+            .collect::<Vec<_>>()
+            .join(", ")
+    } else {
+        game_score.to_string()
+    };
+
+    let lines = [
+        format!("state: {:?}", game_state.get()),
+        format!("score: {score}"),
+        format!(
+            "speed: {:.1} ({:?} x{:.2})",
+            tuning.game_speed * level.speed,
+            *difficulty,
+            level.speed
+        ),
+        format!("distance: {:.0}", **distance_traveled),
+        format!(
+            "entities: {}, colliders: {}, pipes: {}, birds: {}",
+            entity_query.iter().count(),
+            collider_query.iter().count(),
+            pipes_query.iter().count(),
+            birds.len()
+        ),
+    ];
+    panel_query.for_each_mut(|mut text| {
+        text.sections[0].value = lines.join("\n");
+    });
+}
+
+fn draw_colliders(
+    mut gizmos: Gizmos,
+    query: Query<(&GlobalTransform, &Collider, Option<&CollisionLayers>)>,
+) {
+    query.for_each(|(transform, collider, layers)| {
+        collider
+            .shape(transform)
+            .draw(&mut gizmos, layer_color(layers));
+    });
+}
+
+fn draw_velocities(mut gizmos: Gizmos, query: Query<(&GlobalTransform, &Velocity)>) {
+    query.for_each(|(transform, velocity)| {
+        gizmos.ray_2d(
+            transform.translation().xy(),
+            **velocity * VELOCITY_SECONDS,
+            Color::RED,
+        );
+    });
+}
+
+fn draw_bounds(
+    mut gizmos: Gizmos,
+    camera_query: Query<&OrthographicProjection, With<Camera>>,
+    game_boundaries: Res<GameBoundaries>,
+) {
+    gizmos.rect_2d(
+        game_boundaries.center(),
+        0.0,
+        game_boundaries.size(),
+        Color::ORANGE,
+    );
+
+    // the band a following camera's center is kept within
+    camera_query.for_each(|projection| {
+        let Some((min_clamp, max_clamp)) = camera_clamp(&game_boundaries, projection) else {
+            return;
+        };
+        for y in [min_clamp, max_clamp] {
+            gizmos.line_2d(
+                Vec2::new(game_boundaries.min.x, y),
+                Vec2::new(game_boundaries.max.x, y),
+                Color::FUCHSIA,
+            );
+        }
+    });
+}
+
+fn draw_contacts(mut gizmos: Gizmos, last_contacts: Res<LastContacts>) {
+    for (point, push) in last_contacts.iter() {
+        gizmos.circle_2d(*point, CONTACT_RADIUS, Color::RED);
+        gizmos.ray_2d(*point, *push, Color::WHITE);
+    }
+}
@@ -0,0 +1,160 @@
+use std::{
+    fmt::{Debug, Write},
+    fs, io,
+    path::PathBuf,
+};
+
+use bevy::prelude::*;
+
+use crate::{player::PlayerId, replay::ByteReader, save::SavePath};
+
+// fnv-1a, unlike `DefaultHasher` it can't change between builds
+pub fn checksum(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BirdCheck {
+    pub player_id: PlayerId,
+    pub translation: Vec2,
+    pub velocity: Vec2,
+}
+
+// the parts of a frame that peers compare every `CHECK_DISTANCE` frames
+#[derive(Debug, Clone, PartialEq)]
+pub struct CheckedState {
+    pub frame: u32,
+    pub birds: Vec<BirdCheck>,
+    pub pipes: Vec<Vec2>,
+    pub score: i32,
+    pub distance: f32,
+}
+
+impl CheckedState {
+    pub fn encode(&self, bytes: &mut Vec<u8>) {
+        bytes.extend_from_slice(&self.frame.to_le_bytes());
+        bytes.push(self.birds.len() as u8);
+        for bird in self.birds.iter() {
+            bytes.push(*bird.player_id as u8);
+            for value in [bird.translation, bird.velocity] {
+                bytes.extend_from_slice(&value.x.to_le_bytes());
+                bytes.extend_from_slice(&value.y.to_le_bytes());
+            }
+        }
+        bytes.push(self.pipes.len() as u8);
+        for pipe in self.pipes.iter() {
+            bytes.extend_from_slice(&pipe.x.to_le_bytes());
+            bytes.extend_from_slice(&pipe.y.to_le_bytes());
+        }
+        bytes.extend_from_slice(&self.score.to_le_bytes());
+        bytes.extend_from_slice(&self.distance.to_le_bytes());
+    }
+
+    pub fn decode(reader: &mut ByteReader) -> Option<Self> {
+        let vec2 = |reader: &mut ByteReader| -> Option<Vec2> {
+            Some(Vec2::new(
+                f32::from_le_bytes(reader.take().ok()?),
+                f32::from_le_bytes(reader.take().ok()?),
+            ))
+        };
+
+        let frame = u32::from_le_bytes(reader.take().ok()?);
+        let len = reader.take::<1>().ok()?[0];
+        let mut birds = Vec::with_capacity(len as usize);
+        for _ in 0..len {
+            birds.push(BirdCheck {
+                player_id: PlayerId(reader.take::<1>().ok()?[0] as usize),
+                translation: vec2(reader)?,
+                velocity: vec2(reader)?,
+            });
+        }
+        let len = reader.take::<1>().ok()?[0];
+        let mut pipes = Vec::with_capacity(len as usize);
+        for _ in 0..len {
+            pipes.push(vec2(reader)?);
+        }
+
+        Some(Self {
+            frame,
+            birds,
+            pipes,
+            score: i32::from_le_bytes(reader.take().ok()?),
+            distance: f32::from_le_bytes(reader.take().ok()?),
+        })
+    }
+
+    pub fn checksum(&self) -> u64 {
+        let mut bytes = Vec::new();
+        self.encode(&mut bytes);
+        checksum(&bytes)
+    }
+
+    // one line per compared component, marking the ones that differ
+    pub fn report(&self, player: PlayerId, remote: &Self) -> String {
+        let mut report = format!(
+            "desync at frame {}, written by player {}\n\n{:<24}{:<40}{:<40}\n",
+            self.frame, *player, "component", "local", "remote"
+        );
+        let mut line = |name: &str, local: &dyn Debug, remote: &dyn Debug| {
+            let (local, remote) = (format!("{local:?}"), format!("{remote:?}"));
+            let marker = if local == remote { "" } else { "diverged" };
+            let _ = writeln!(report, "{name:<24}{local:<40}{remote:<40}{marker}");
+        };
+
+        let mut player_ids = self
+            .birds
+            .iter()
+            .chain(remote.birds.iter())
+            .map(|bird| bird.player_id)
+            .collect::<Vec<_>>();
+        player_ids.sort_by_key(|player_id| **player_id);
+        player_ids.dedup();
+        for player_id in player_ids {
+            let bird = |state: &Self| {
+                state
+                    .birds
+                    .iter()
+                    .find(|bird| bird.player_id == player_id)
+                    .cloned()
+            };
+            let (local, remote) = (bird(self), bird(remote));
+            line(
+                &format!("bird {} translation", *player_id),
+                &local.as_ref().map(|bird| bird.translation),
+                &remote.as_ref().map(|bird| bird.translation),
+            );
+            line(
+                &format!("bird {} velocity", *player_id),
+                &local.as_ref().map(|bird| bird.velocity),
+                &remote.as_ref().map(|bird| bird.velocity),
+            );
+        }
+        line("pipe spawn positions", &self.pipes, &remote.pipes);
+        line("score", &self.score, &remote.score);
+        line("distance", &self.distance, &remote.distance);
+        report
+    }
+}
+
+// next to the save file, or the temp directory when there isn't one
+pub fn desync_dir(save_path: &SavePath) -> PathBuf {
+    match &save_path.0 {
+        Some(path) => path.with_file_name("desyncs"),
+        None => std::env::temp_dir().join("flappy_bird_desyncs"),
+    }
+}
+
+pub fn write_report(
+    save_path: &SavePath,
+    player: PlayerId,
+    local: &CheckedState,
+    remote: &CheckedState,
+) -> io::Result<PathBuf> {
+    let dir = desync_dir(save_path);
+    fs::create_dir_all(&dir)?;
+    let path = dir.join(format!("frame-{}-player-{}.txt", local.frame, *player));
+    fs::write(&path, local.report(player, remote))?;
+    Ok(path)
+}
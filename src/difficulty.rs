@@ -0,0 +1,234 @@
+use std::{fmt, str::FromStr};
+
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::{
+    cli::Args,
+    replay::{ByteReader, ReplayError},
+};
+
+// picked in the settings, how quickly a run gets harder comes from its curve in the tuning file
+#[derive(Resource, Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Difficulty {
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+}
+
+impl Difficulty {
+    pub const ALL: [Difficulty; 3] = [Difficulty::Easy, Difficulty::Normal, Difficulty::Hard];
+
+    pub fn next(self) -> Self {
+        match self {
+            Difficulty::Easy => Difficulty::Normal,
+            Difficulty::Normal => Difficulty::Hard,
+            Difficulty::Hard => Difficulty::Easy,
+        }
+    }
+
+    pub fn to_byte(self) -> u8 {
+        match self {
+            Difficulty::Easy => 0,
+            Difficulty::Normal => 1,
+            Difficulty::Hard => 2,
+        }
+    }
+
+    pub fn from_byte(byte: u8) -> Option<Self> {
+        Self::ALL.get(byte as usize).copied()
+    }
+}
+
+impl fmt::Display for Difficulty {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Difficulty::Easy => write!(f, "easy"),
+            Difficulty::Normal => write!(f, "normal"),
+            Difficulty::Hard => write!(f, "hard"),
+        }
+    }
+}
+
+impl FromStr for Difficulty {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|difficulty| difficulty.to_string() == s.to_ascii_lowercase())
+            .ok_or(())
+    }
+}
+
+// every pair goes from its first to its second value over `ramp_distance` and stays there
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct DifficultyCurve {
+    pub ramp_distance: f32,
+    // multipliers for the matching base values of the tuning
+    pub speed: (f32, f32),
+    pub gap: (f32, f32),
+    pub spacing: (f32, f32),
+    // how much of the free height gaps are spread over, at 1 they can be anywhere
+    pub gap_variance: (f32, f32),
+    // chance of a pair moving around instead of standing still
+    pub moving: (f32, f32),
+}
+
+impl DifficultyCurve {
+    pub const ENCODED_SIZE: usize = 4 * 11;
+
+    fn pairs(&self) -> [(&'static str, (f32, f32)); 5] {
+        [
+            ("speed", self.speed),
+            ("gap", self.gap),
+            ("spacing", self.spacing),
+            ("gap_variance", self.gap_variance),
+            ("moving", self.moving),
+        ]
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if !self.ramp_distance.is_finite() || self.ramp_distance <= 0.0 {
+            return Err(format!(
+                "ramp_distance must be positive, got {}",
+                self.ramp_distance
+            ));
+        }
+        for (name, (start, end)) in self.pairs() {
+            for value in [start, end] {
+                if !value.is_finite() || value < 0.0 {
+                    return Err(format!("{name} can't be negative, got {value}"));
+                }
+            }
+        }
+        // only moving pairs can be left out entirely
+        for (name, (start, end)) in [
+            ("speed", self.speed),
+            ("gap", self.gap),
+            ("spacing", self.spacing),
+            ("gap_variance", self.gap_variance),
+        ] {
+            if start == 0.0 || end == 0.0 {
+                return Err(format!("{name} can't be zero"));
+            }
+        }
+        for (name, (start, end)) in [("gap_variance", self.gap_variance), ("moving", self.moving)] {
+            if start > 1.0 || end > 1.0 {
+                return Err(format!("{name} can't be more than 1, got ({start}, {end})"));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn sample(&self, distance: f32) -> DifficultyLevel {
+        let t = (distance / self.ramp_distance).clamp(0.0, 1.0);
+        let lerp = |(start, end): (f32, f32)| start + (end - start) * t;
+        DifficultyLevel {
+            speed: lerp(self.speed),
+            gap: lerp(self.gap),
+            spacing: lerp(self.spacing),
+            gap_variance: lerp(self.gap_variance),
+            moving: lerp(self.moving),
+            progress: t,
+        }
+    }
+
+    pub fn encode(&self, bytes: &mut Vec<u8>) {
+        bytes.extend_from_slice(&self.ramp_distance.to_le_bytes());
+        for (_, (start, end)) in self.pairs() {
+            bytes.extend_from_slice(&start.to_le_bytes());
+            bytes.extend_from_slice(&end.to_le_bytes());
+        }
+    }
+
+    pub fn decode(reader: &mut ByteReader) -> Result<Self, ReplayError> {
+        let mut value = || reader.take().map(f32::from_le_bytes);
+        Ok(Self {
+            ramp_distance: value()?,
+            speed: (value()?, value()?),
+            gap: (value()?, value()?),
+            spacing: (value()?, value()?),
+            gap_variance: (value()?, value()?),
+            moving: (value()?, value()?),
+        })
+    }
+}
+
+// the curve at one point of a run
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DifficultyLevel {
+    pub speed: f32,
+    pub gap: f32,
+    pub spacing: f32,
+    pub gap_variance: f32,
+    pub moving: f32,
+    // how far along the ramp, from 0 to 1
+    pub progress: f32,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct DifficultyPresets {
+    pub easy: DifficultyCurve,
+    pub normal: DifficultyCurve,
+    pub hard: DifficultyCurve,
+}
+
+impl DifficultyPresets {
+    pub fn curve(&self, difficulty: Difficulty) -> &DifficultyCurve {
+        match difficulty {
+            Difficulty::Easy => &self.easy,
+            Difficulty::Normal => &self.normal,
+            Difficulty::Hard => &self.hard,
+        }
+    }
+}
+
+impl Default for DifficultyPresets {
+    fn default() -> Self {
+        Self {
+            easy: DifficultyCurve {
+                ramp_distance: 3000.0,
+                speed: (0.9, 1.2),
+                gap: (1.2, 1.0),
+                spacing: (1.2, 1.0),
+                gap_variance: (0.4, 0.8),
+                moving: (0.0, 0.2),
+            },
+            normal: DifficultyCurve {
+                ramp_distance: 2000.0,
+                speed: (1.0, 1.4),
+                gap: (1.0, 0.85),
+                spacing: (1.0, 0.9),
+                gap_variance: (0.6, 1.0),
+                moving: (0.0, 0.4),
+            },
+            hard: DifficultyCurve {
+                ramp_distance: 1500.0,
+                speed: (1.2, 1.8),
+                gap: (0.9, 0.75),
+                spacing: (0.9, 0.8),
+                gap_variance: (0.8, 1.0),
+                moving: (0.2, 0.6),
+            },
+        }
+    }
+}
+
+pub struct DifficultyPlugin;
+
+impl Plugin for DifficultyPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Difficulty>()
+            .add_systems(Startup, apply_args);
+    }
+}
+
+fn apply_args(mut difficulty: ResMut<Difficulty>, args: Option<Res<Args>>) {
+    if let Some(picked) = args.and_then(|args| args.difficulty) {
+        *difficulty = picked;
+    }
+}
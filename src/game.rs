@@ -1,4 +1,4 @@
-use bevy::{app::PluginGroupBuilder, prelude::*};
+use bevy::{app::PluginGroupBuilder, ecs::schedule::ScheduleLabel, prelude::*};
 use bevy_asset_loader::{
     asset_collection::AssetCollection,
     loading_state::{LoadingState, LoadingStateAppExt},
@@ -6,33 +6,69 @@ use bevy_asset_loader::{
 
 use crate::{
     anchor::AnchorPlugin,
-    animation::AnimationPlugin,
+    animation::{AnimationPlugin, AnimationSet},
     camera::GameCameraPlugin,
+    debug::DebugPlugin,
+    difficulty::{Difficulty, DifficultyPlugin},
+    ghost::GhostPlugin,
+    hud::HudPlugin,
     level::LevelPlugin,
-    physics::{Gravity, PhysicsPlugin},
+    lobby::LobbyPlugin,
+    menu::MenuPlugin,
+    networking::{NetSession, NetworkingPlugin, Spectator},
+    patterns::{PatternFile, PatternsPlugin},
+    pause::PausePlugin,
+    physics::{PhysicsPlugin, PhysicsSet},
     pipes::PipesPlugin,
     player::PlayerPlugin,
+    rating::RatingPlugin,
+    replay::ReplayPlugin,
+    save::SavePlugin,
+    seed::SeedPlugin,
     tiling::TilingPlugin,
+    tuning::{Tuning, TuningFile, TuningPlugin},
+    versus::VersusPlugin,
 };
 
 #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, States)]
 pub enum GameState {
     #[default]
     Loading,
+    MainMenu,
+    Settings,
+    Lobby,
     Waiting,
     Playing,
+    Paused,
     Stopped,
     Dead,
 }
 
+// one step of the simulation, run every `FixedUpdate` or several times over when rolling back
+#[derive(ScheduleLabel, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct GameTick;
+
+// gameplay systems in `GameTick` are ordered around the physics step
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum GameSet {
+    Input,
+    Simulation,
+    Response,
+}
+
 #[derive(Resource, Deref, DerefMut)]
 pub struct DistanceTraveled(pub f32);
 
-#[derive(Resource, Deref, DerefMut)]
-pub struct GameScore(i32);
+// distance between the last two fixed ticks, blended for anything drawn every frame
+#[derive(Resource, Default, Deref, DerefMut)]
+pub struct RenderedDistance {
+    previous: f32,
+    #[deref]
+    current: f32,
+}
 
 #[derive(Resource, Deref, DerefMut)]
-pub struct GameSpeed(f32);
+pub struct GameScore(i32);
 
 #[derive(Resource, Default, Deref, DerefMut)]
 pub struct GameBoundaries(Rect);
@@ -51,6 +87,8 @@ pub struct GameAssets {
     pub clouds_image: Handle<Image>,
     #[asset(path = "sprites/bird", collection(typed))]
     pub player_sprite_folder: Vec<Handle<Image>>,
+    #[asset(path = "sprites/spritesheet.png")]
+    pub spritesheet: Handle<Image>,
     #[asset(path = "sprites/ui_background.png")]
     pub ui_background: Handle<Image>,
     #[asset(path = "flappy-bird-assets/audio/wing.ogg")]
@@ -61,6 +99,10 @@ pub struct GameAssets {
     pub fall_audio: Handle<AudioSource>,
     #[asset(path = "flappy-bird-assets/audio/point.ogg")]
     pub point_audio: Handle<AudioSource>,
+    #[asset(path = "game.tuning.ron")]
+    pub tuning: Handle<TuningFile>,
+    #[asset(path = "patterns", collection(typed))]
+    pub patterns: Vec<Handle<PatternFile>>,
 }
 
 pub struct GamePlugins;
@@ -72,12 +114,27 @@ impl PluginGroup for GamePlugins {
             .add(AnchorPlugin)
             .add(TilingPlugin)
             .add(PhysicsPlugin)
-            // .add(PhysicsDebugPlugin)
             .add(GamePlugin)
+            .add(TuningPlugin)
+            .add(PatternsPlugin)
+            .add(DifficultyPlugin)
+            .add(LoadingPlugin)
             .add(GameCameraPlugin)
             .add(LevelPlugin)
             .add(PlayerPlugin)
             .add(PipesPlugin)
+            .add(RatingPlugin)
+            .add(SavePlugin)
+            .add(SeedPlugin)
+            .add(ReplayPlugin)
+            .add(GhostPlugin)
+            .add(HudPlugin)
+            .add(MenuPlugin)
+            .add(PausePlugin)
+            .add(NetworkingPlugin)
+            .add(LobbyPlugin)
+            .add(VersusPlugin)
+            .add(DebugPlugin)
     }
 }
 
@@ -86,49 +143,128 @@ pub struct GamePlugin;
 impl Plugin for GamePlugin {
     fn build(&self, app: &mut App) {
         app.add_state::<GameState>()
-            .add_loading_state(
-                LoadingState::new(GameState::Loading).continue_to_state(GameState::Waiting),
-            )
-            .add_collection_to_loading_state::<_, GameAssets>(GameState::Loading)
+            .init_schedule(GameTick)
             .insert_resource(ClearColor(Color::hex("#4EC0CA").unwrap()))
-            .insert_resource(Gravity::from(-Vec2::Y * 400.0))
             .insert_resource(GameScore(0))
-            .insert_resource(GameSpeed(50.0))
             .insert_resource(DistanceTraveled(0.0))
+            .init_resource::<RenderedDistance>()
             .insert_resource(GameBoundaries::default())
+            .configure_sets(
+                GameTick,
+                (
+                    PhysicsSet::Restore,
+                    GameSet::Input,
+                    GameSet::Simulation,
+                    PhysicsSet::Step,
+                    PhysicsSet::Collide,
+                    GameSet::Response,
+                    PhysicsSet::Store,
+                )
+                    .chain(),
+            )
+            .configure_sets(
+                GameTick,
+                PhysicsSet::Step.run_if(not(in_state(GameState::Paused))),
+            )
+            .configure_sets(
+                Update,
+                AnimationSet.run_if(not(in_state(GameState::Paused))),
+            )
+            // transitions requested by a tick are applied before the next one runs
+            .add_systems(
+                GameTick,
+                apply_state_transition::<GameState>
+                    .after(PhysicsSet::Restore)
+                    .before(GameSet::Input),
+            )
+            // a network session or spectator drives the ticks itself
+            .add_systems(
+                FixedUpdate,
+                run_game_tick
+                    .run_if(not(resource_exists::<NetSession>()))
+                    .run_if(not(resource_exists::<Spectator>())),
+            )
             .add_systems(OnEnter(GameState::Waiting), restart)
             .add_systems(PreUpdate, update_boundaries)
-            .add_systems(PreUpdate, update_distance);
+            .add_systems(GameTick, update_distance.in_set(GameSet::Simulation))
+            .add_systems(Update, interpolate_distance);
     }
 }
 
-fn restart(mut distance_traveled: ResMut<DistanceTraveled>, mut game_score: ResMut<GameScore>) {
+// loads `GameAssets` from disk, headless runs replace this with stubbed assets
+pub struct LoadingPlugin;
+
+impl Plugin for LoadingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_loading_state(
+            LoadingState::new(GameState::Loading).continue_to_state(GameState::MainMenu),
+        )
+        .add_collection_to_loading_state::<_, GameAssets>(GameState::Loading);
+    }
+}
+
+fn run_game_tick(world: &mut World) {
+    world.run_schedule(GameTick);
+}
+
+fn restart(
+    mut distance_traveled: ResMut<DistanceTraveled>,
+    mut rendered_distance: ResMut<RenderedDistance>,
+    mut game_score: ResMut<GameScore>,
+) {
     **distance_traveled = 0.0;
+    *rendered_distance = RenderedDistance::default();
     **game_score = 0;
 }
 
-fn update_distance(
+pub fn update_distance(
     mut distance_traveled: ResMut<DistanceTraveled>,
+    mut rendered_distance: ResMut<RenderedDistance>,
     game_state: Res<State<GameState>>,
-    game_speed: Res<GameSpeed>,
+    tuning: Res<Tuning>,
+    difficulty: Res<Difficulty>,
     time: Res<Time>,
 ) {
-    if *game_state == GameState::Stopped || *game_state == GameState::Dead {
+    rendered_distance.previous = **distance_traveled;
+    if *game_state == GameState::Stopped
+        || *game_state == GameState::Dead
+        || *game_state == GameState::Paused
+    {
         return;
     }
-    **distance_traveled += game_speed.0 * time.delta_seconds();
+    let level = tuning.level(*difficulty, **distance_traveled);
+    **distance_traveled += tuning.game_speed * level.speed * time.delta_seconds();
+}
+
+pub fn interpolate_distance(
+    mut rendered_distance: ResMut<RenderedDistance>,
+    distance_traveled: Res<DistanceTraveled>,
+    fixed_time: Res<Time<Fixed>>,
+) {
+    let previous = rendered_distance.previous;
+    rendered_distance.current =
+        previous + (**distance_traveled - previous) * fixed_time.overstep_percentage();
 }
 
 fn update_boundaries(
     mut game_boundaries: ResMut<GameBoundaries>,
     camera_query: Query<(&Transform, &OrthographicProjection)>,
+    tuning: Res<Tuning>,
 ) {
-    if let Ok((transform, projection)) = camera_query.get_single() {
-        let view_extents = Vec2::new(projection.area.width(), projection.area.height()) * 0.5
-            + transform.translation.xy();
-        let game_height = 175.0;
+    // wide enough for every view when the screen is split
+    let view_extents = camera_query
+        .iter()
+        .map(|(transform, projection)| {
+            Vec2::new(projection.area.width(), projection.area.height()) * 0.5
+                + transform.translation.xy()
+        })
+        .reduce(Vec2::max);
+    if let Some(view_extents) = view_extents {
         game_boundaries.min = Vec2::new(-view_extents.x * 1.25, -50.0);
-        game_boundaries.max = Vec2::new(view_extents.x * 1.25, game_boundaries.min.y + game_height);
+        game_boundaries.max = Vec2::new(
+            view_extents.x * 1.25,
+            game_boundaries.min.y + tuning.game_height,
+        );
         // max: Vec2::new(view_extents.x * 1.25, view_extents.y - 20.0),
     }
 }
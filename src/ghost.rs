@@ -0,0 +1,260 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use bevy::prelude::*;
+
+use crate::{
+    animation::AnimationState,
+    game::{GameScore, GameSet, GameState, GameTick},
+    menu::GameSettings,
+    networking::NetSession,
+    physics::InterpolatedTransform,
+    player::{Player, PlayerAnimations, PlayerAtlas},
+    replay::{replay_dir, ByteReader, ReplayError, ReplayPlayback},
+    save::{record_run, SaveData, SavePath},
+    versus::LocalVersus,
+};
+
+const GHOST_MAGIC: &[u8; 4] = b"FBGH";
+const GHOST_VERSION: u8 = 1;
+const GHOST_ALPHA: f32 = 0.4;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GhostFrame {
+    pub translation: Vec2,
+    pub rotation: f32,
+}
+
+// bird transform for every fixed tick spent in `Playing`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GhostTrajectory {
+    pub score: i32,
+    pub frames: Vec<GhostFrame>,
+}
+
+impl GhostTrajectory {
+    pub fn read(path: &Path) -> Result<Self, ReplayError> {
+        let bytes = fs::read(path)?;
+        let mut reader = ByteReader(&bytes);
+        if reader.take::<4>()? != *GHOST_MAGIC {
+            return Err(ReplayError::InvalidMagic);
+        }
+        let version = reader.take::<1>()?[0];
+        if version != GHOST_VERSION {
+            return Err(ReplayError::UnsupportedVersion(version));
+        }
+
+        let score = i32::from_le_bytes(reader.take()?);
+        let len = u32::from_le_bytes(reader.take()?);
+        let mut frames = Vec::with_capacity(len.min(u16::MAX as u32) as usize);
+        for _ in 0..len {
+            frames.push(GhostFrame {
+                translation: Vec2::new(
+                    f32::from_le_bytes(reader.take()?),
+                    f32::from_le_bytes(reader.take()?),
+                ),
+                rotation: f32::from_le_bytes(reader.take()?),
+            });
+        }
+
+        Ok(Self { score, frames })
+    }
+
+    pub fn write(&self, path: &Path) -> Result<(), ReplayError> {
+        let mut bytes = Vec::with_capacity(16 + self.frames.len() * 12);
+        bytes.extend_from_slice(GHOST_MAGIC);
+        bytes.push(GHOST_VERSION);
+        bytes.extend_from_slice(&self.score.to_le_bytes());
+        bytes.extend_from_slice(&(self.frames.len() as u32).to_le_bytes());
+        for frame in self.frames.iter() {
+            bytes.extend_from_slice(&frame.translation.x.to_le_bytes());
+            bytes.extend_from_slice(&frame.translation.y.to_le_bytes());
+            bytes.extend_from_slice(&frame.rotation.to_le_bytes());
+        }
+
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, bytes)?;
+        Ok(())
+    }
+}
+
+#[derive(Resource, Default, Deref, DerefMut)]
+pub struct GhostRecording(Vec<GhostFrame>);
+
+#[derive(Resource, Default, Deref, DerefMut)]
+pub struct BestGhost(pub Option<GhostTrajectory>);
+
+#[derive(Component, Default)]
+pub struct Ghost {
+    frame: usize,
+}
+
+pub struct GhostPlugin;
+
+impl Plugin for GhostPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GhostRecording>()
+            .init_resource::<BestGhost>()
+            .add_systems(OnExit(GameState::Loading), load)
+            .add_systems(OnEnter(GameState::Waiting), restart)
+            .add_systems(
+                GameTick,
+                (
+                    move_ghost.in_set(GameSet::Simulation),
+                    record_frame.after(GameSet::Response),
+                )
+                    .run_if(in_state(GameState::Playing))
+                    .run_if(not(resource_exists::<NetSession>()))
+                    .run_if(not(resource_exists::<LocalVersus>())),
+            )
+            .add_systems(
+                OnEnter(GameState::Dead),
+                keep_best
+                    .before(record_run)
+                    .run_if(not(resource_exists::<ReplayPlayback>()))
+                    .run_if(not(resource_exists::<NetSession>()))
+                    .run_if(not(resource_exists::<LocalVersus>())),
+            )
+            .add_systems(Update, apply_ghost_settings);
+    }
+}
+
+fn ghost_path(save_path: &SavePath) -> Option<PathBuf> {
+    replay_dir(save_path).map(|dir| dir.join("best.ghost"))
+}
+
+fn load(mut best_ghost: ResMut<BestGhost>, save_path: Res<SavePath>) {
+    let Some(path) = ghost_path(&save_path) else {
+        return;
+    };
+    if !path.exists() {
+        return;
+    }
+
+    match GhostTrajectory::read(&path) {
+        Ok(trajectory) => **best_ghost = Some(trajectory),
+        Err(err) => warn!("failed to read ghost {path:?}: {err}"),
+    }
+}
+
+fn restart(
+    mut commands: Commands,
+    mut recording: ResMut<GhostRecording>,
+    mut query: Query<(&mut Ghost, &mut Transform, &mut Visibility)>,
+    best_ghost: Res<BestGhost>,
+    player_atlas: Res<PlayerAtlas>,
+    player_animations: Res<PlayerAnimations>,
+) {
+    recording.clear();
+
+    if query.is_empty() {
+        commands.spawn((
+            SpriteSheetBundle {
+                sprite: TextureAtlasSprite {
+                    index: player_atlas.index,
+                    color: Color::WHITE.with_a(GHOST_ALPHA),
+                    ..default()
+                },
+                texture_atlas: player_atlas.handle.clone(),
+                // just behind the live bird
+                transform: Transform::from_translation(Vec3::Z * 499.0),
+                visibility: Visibility::Hidden,
+                ..default()
+            },
+            AnimationState::default(),
+            player_animations.idle.clone(),
+            InterpolatedTransform::default(),
+            Ghost::default(),
+        ));
+        return;
+    }
+
+    let start = best_ghost
+        .0
+        .as_ref()
+        .and_then(|trajectory| trajectory.frames.first());
+    query.for_each_mut(|(mut ghost, mut transform, mut visibility)| {
+        ghost.frame = 0;
+        *visibility = Visibility::Hidden;
+        // moved outside the fixed timestep, so this isn't interpolated
+        if let Some(frame) = start {
+            transform.translation = frame.translation.extend(transform.translation.z);
+            transform.rotation = Quat::from_rotation_z(frame.rotation);
+        }
+    });
+}
+
+fn move_ghost(
+    mut query: Query<(&mut Ghost, &mut Transform, &mut Visibility)>,
+    best_ghost: Res<BestGhost>,
+    game_settings: Res<GameSettings>,
+) {
+    query.for_each_mut(|(mut ghost, mut transform, mut visibility)| {
+        let frame = best_ghost
+            .0
+            .as_ref()
+            .and_then(|trajectory| trajectory.frames.get(ghost.frame));
+        let Some(frame) = frame else {
+            // the best run ended here
+            *visibility = Visibility::Hidden;
+            return;
+        };
+
+        transform.translation = frame.translation.extend(transform.translation.z);
+        transform.rotation = Quat::from_rotation_z(frame.rotation);
+        if game_settings.ghost {
+            *visibility = Visibility::Inherited;
+        }
+        ghost.frame += 1;
+    });
+}
+
+fn record_frame(mut recording: ResMut<GhostRecording>, query: Query<&Transform, With<Player>>) {
+    query.for_each(|transform| {
+        let (_, _, rotation) = transform.rotation.to_euler(EulerRot::XYZ);
+        recording.push(GhostFrame {
+            translation: transform.translation.xy(),
+            rotation,
+        });
+    });
+}
+
+fn keep_best(
+    mut recording: ResMut<GhostRecording>,
+    mut best_ghost: ResMut<BestGhost>,
+    save_path: Res<SavePath>,
+    save_data: Res<SaveData>,
+    game_score: Res<GameScore>,
+) {
+    if **game_score <= save_data.best_score {
+        return;
+    }
+
+    let trajectory = GhostTrajectory {
+        score: **game_score,
+        frames: std::mem::take(&mut **recording),
+    };
+    if let Some(path) = ghost_path(&save_path) {
+        if let Err(err) = trajectory.write(&path) {
+            warn!("failed to write ghost {path:?}: {err}");
+        }
+    }
+    **best_ghost = Some(trajectory);
+}
+
+fn apply_ghost_settings(
+    mut query: Query<&mut Visibility, With<Ghost>>,
+    game_settings: Res<GameSettings>,
+) {
+    if !game_settings.is_changed() || game_settings.ghost {
+        return;
+    }
+
+    query.for_each_mut(|mut visibility| {
+        *visibility = Visibility::Hidden;
+    });
+}
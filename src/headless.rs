@@ -0,0 +1,223 @@
+use std::{fs, path::Path, time::Duration};
+
+use bevy::{
+    app::{AppExit, ScheduleRunnerPlugin},
+    core::FrameCount,
+    input::InputPlugin,
+    prelude::*,
+    render::{
+        camera::CameraProjection,
+        render_resource::{Extent3d, TextureDimension, TextureFormat},
+    },
+    sprite::ColorMaterial,
+    time::TimeUpdateStrategy,
+    ui::UiScale,
+    window::ExitCondition,
+};
+
+use crate::{
+    cli::Args,
+    game::{DistanceTraveled, GameAssets, GamePlugins, GameScore, GameState, LoadingPlugin},
+    networking::FPS,
+    physics::DEFAULT_TICK_RATE,
+    player::{DeathCause, RunDeath},
+    replay::{ReplayAction, ReplayInput, ReplayPlayback, RunTick},
+    save::SavePath,
+    seed::GameSeed,
+};
+
+// ten minutes of game time
+const DEFAULT_MAX_TICKS: u32 = 60 * 60 * 10;
+const VIEWPORT_SIZE: Vec2 = Vec2::new(480.0, 720.0);
+
+#[derive(Resource)]
+pub struct MaxTicks(pub u32);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunSummary {
+    pub seed: u64,
+    pub score: i32,
+    pub distance: f32,
+    pub ticks: u32,
+    pub death: Option<DeathCause>,
+}
+
+impl std::fmt::Display for RunSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let death = match self.death {
+            Some(DeathCause::Ground) => "ground",
+            Some(DeathCause::Pipe) => "pipe",
+            None => "none (timed out)",
+        };
+        writeln!(f, "seed: {}", self.seed)?;
+        writeln!(f, "score: {}", self.score)?;
+        writeln!(f, "distance: {:.2}", self.distance)?;
+        writeln!(f, "ticks: {}", self.ticks)?;
+        write!(f, "cause of death: {death}")
+    }
+}
+
+// builds the full game without a window, renderer, audio or asset files
+pub fn app(args: Args) -> Result<App, String> {
+    // a network peer or a broadcast has to keep pace with the other side instead of running flat out
+    let online = args.peer.is_some() || args.broadcast.is_some() || args.spectate.is_some();
+    let minimal_plugins = if online {
+        MinimalPlugins.set(ScheduleRunnerPlugin::run_loop(Duration::from_secs_f64(
+            1.0 / FPS as f64,
+        )))
+    } else {
+        MinimalPlugins.build()
+    };
+
+    let mut app = App::new();
+    app.add_plugins((
+        minimal_plugins,
+        AssetPlugin::default(),
+        TransformPlugin,
+        HierarchyPlugin,
+        InputPlugin,
+        WindowPlugin {
+            primary_window: None,
+            exit_condition: ExitCondition::DontExit,
+            close_when_requested: false,
+        },
+    ))
+    .init_asset::<Image>()
+    .init_asset::<Mesh>()
+    .init_asset::<ColorMaterial>()
+    .init_asset::<TextureAtlas>()
+    .init_resource::<UiScale>()
+    // one fixed tick per update, however fast the machine is
+    .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f64(
+        1.0 / args.tick_rate.unwrap_or(DEFAULT_TICK_RATE),
+    )))
+    .insert_resource(SavePath(None))
+    .insert_resource(MaxTicks(args.max_ticks.unwrap_or(DEFAULT_MAX_TICKS)));
+
+    if args.replay.is_none() {
+        let inputs = match &args.script {
+            Some(path) => read_script(path)?,
+            None => Vec::new(),
+        };
+        app.insert_resource(ReplayPlayback::scripted(args.seed.unwrap_or(0), inputs));
+    }
+
+    app.insert_resource(args)
+        .add_plugins(GamePlugins.build().disable::<LoadingPlugin>())
+        .add_systems(Startup, stub_assets)
+        .add_systems(PostStartup, resize_viewport)
+        .add_systems(Last, (sync_time_step, exit_on_timeout))
+        .add_systems(OnEnter(GameState::Dead), exit_on_death);
+    Ok(app)
+}
+
+// one tick per line, optionally followed by the action, e.g. `120 flap`
+pub fn read_script(path: &Path) -> Result<Vec<ReplayInput>, String> {
+    let script = fs::read_to_string(path).map_err(|err| format!("{path:?}: {err}"))?;
+    parse_script(&script).map_err(|err| format!("{path:?}: {err}"))
+}
+
+pub fn parse_script(script: &str) -> Result<Vec<ReplayInput>, String> {
+    let mut inputs: Vec<ReplayInput> = Vec::new();
+    for (line_number, line) in script.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let mut parts = line.split_whitespace();
+        let tick = parts
+            .next()
+            .and_then(|tick| tick.parse().ok())
+            .ok_or(format!("line {}: expected a tick", line_number + 1))?;
+        let action = match parts.next() {
+            None | Some("flap") => ReplayAction::Flap,
+            Some(action) => {
+                return Err(format!(
+                    "line {}: unknown action {action:?}",
+                    line_number + 1
+                ))
+            }
+        };
+        if inputs.last().is_some_and(|input| input.tick > tick) {
+            return Err(format!("line {}: ticks must be in order", line_number + 1));
+        }
+
+        inputs.push(ReplayInput { tick, action });
+    }
+    Ok(inputs)
+}
+
+pub fn summary(world: &World) -> RunSummary {
+    RunSummary {
+        seed: **world.resource::<GameSeed>(),
+        score: **world.resource::<GameScore>(),
+        distance: **world.resource::<DistanceTraveled>(),
+        ticks: **world.resource::<RunTick>(),
+        death: **world.resource::<RunDeath>(),
+    }
+}
+
+fn stub_assets(
+    mut commands: Commands,
+    mut images: ResMut<Assets<Image>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    // only the sizes matter, they're what colliders and layout are built from
+    let mut image = |width: u32, height: u32| {
+        images.add(Image::new_fill(
+            Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            &[255, 255, 255, 255],
+            TextureFormat::Rgba8UnormSrgb,
+        ))
+    };
+
+    commands.insert_resource(GameAssets {
+        ground_image: image(168, 13),
+        pipe_image: image(26, 160),
+        mountains_image: image(144, 48),
+        buildings_image: image(144, 48),
+        clouds_image: image(144, 48),
+        player_sprite_folder: vec![image(17, 12), image(17, 12), image(17, 12)],
+        spritesheet: image(512, 512),
+        ui_background: image(10, 10),
+        flap_audio: Handle::default(),
+        hit_audio: Handle::default(),
+        fall_audio: Handle::default(),
+        point_audio: Handle::default(),
+        tuning: Handle::default(),
+        patterns: Vec::new(),
+    });
+    next_state.set(GameState::MainMenu);
+}
+
+fn resize_viewport(mut query: Query<&mut OrthographicProjection>) {
+    query.for_each_mut(|mut projection| {
+        projection.update(VIEWPORT_SIZE.x, VIEWPORT_SIZE.y);
+    });
+}
+
+fn sync_time_step(
+    mut time_update_strategy: ResMut<TimeUpdateStrategy>,
+    fixed_time: Res<Time<Fixed>>,
+) {
+    *time_update_strategy = TimeUpdateStrategy::ManualDuration(fixed_time.timestep());
+}
+
+fn exit_on_timeout(world: &mut World) {
+    if world.resource::<FrameCount>().0 < world.resource::<MaxTicks>().0 {
+        return;
+    }
+    println!("{}", summary(world));
+    world.send_event(AppExit);
+}
+
+fn exit_on_death(world: &mut World) {
+    println!("{}", summary(world));
+    world.send_event(AppExit);
+}
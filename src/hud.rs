@@ -0,0 +1,505 @@
+use bevy::{prelude::*, utils::HashMap, window::PrimaryWindow};
+
+use crate::{
+    camera::PlayerView,
+    game::{GameAssets, GameBoundaries, GameScore, GameState},
+    rating::{Medal, RunMedal},
+    save::{record_run, SaveData},
+    seed::GameSeed,
+    versus::LocalVersus,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum UiSprite {
+    Digit(u32),
+    Medal(Medal),
+    GetReady,
+    Tutorial,
+    GameOver,
+    Title,
+    PlayButton,
+    MenuButton,
+    OkButton,
+    PauseButton,
+    ResumeButton,
+}
+
+impl UiSprite {
+    fn all() -> impl Iterator<Item = UiSprite> {
+        (0..10)
+            .map(UiSprite::Digit)
+            .chain(Medal::ALL.map(UiSprite::Medal))
+            .chain([
+                UiSprite::GetReady,
+                UiSprite::Tutorial,
+                UiSprite::GameOver,
+                UiSprite::Title,
+                UiSprite::PlayButton,
+                UiSprite::MenuButton,
+                UiSprite::OkButton,
+                UiSprite::PauseButton,
+                UiSprite::ResumeButton,
+            ])
+    }
+
+    // location of the sprite in the spritesheet
+    pub fn rect(&self) -> Rect {
+        let (min, size) = match self {
+            UiSprite::Digit(0) => (Vec2::new(496.0, 60.0), Vec2::new(12.0, 18.0)),
+            UiSprite::Digit(1) => (Vec2::new(136.0, 455.0), Vec2::new(8.0, 18.0)),
+            UiSprite::Digit(digit) => {
+                let index = ((*digit).min(9) - 2) as f32;
+                let column = index % 4.0;
+                let row = (index / 4.0).floor();
+                (
+                    Vec2::new(292.0 + column * 14.0, 160.0 + row * 24.0),
+                    Vec2::new(12.0, 18.0),
+                )
+            }
+            UiSprite::Medal(Medal::Bronze) => (Vec2::new(112.0, 477.0), Vec2::splat(22.0)),
+            UiSprite::Medal(Medal::Silver) => (Vec2::new(112.0, 453.0), Vec2::splat(22.0)),
+            UiSprite::Medal(Medal::Gold) => (Vec2::new(121.0, 282.0), Vec2::splat(22.0)),
+            UiSprite::Medal(Medal::Platinum) => (Vec2::new(121.0, 258.0), Vec2::splat(22.0)),
+            UiSprite::GetReady => (Vec2::new(295.0, 59.0), Vec2::new(92.0, 25.0)),
+            UiSprite::Tutorial => (Vec2::new(292.0, 91.0), Vec2::new(57.0, 49.0)),
+            UiSprite::GameOver => (Vec2::new(395.0, 59.0), Vec2::new(96.0, 21.0)),
+            UiSprite::Title => (Vec2::new(351.0, 91.0), Vec2::new(89.0, 24.0)),
+            UiSprite::PlayButton => (Vec2::new(354.0, 118.0), Vec2::new(52.0, 29.0)),
+            UiSprite::MenuButton => (Vec2::new(462.0, 26.0), Vec2::new(40.0, 14.0)),
+            UiSprite::OkButton => (Vec2::new(462.0, 42.0), Vec2::new(40.0, 14.0)),
+            UiSprite::PauseButton => (Vec2::new(121.0, 306.0), Vec2::new(13.0, 14.0)),
+            UiSprite::ResumeButton => (Vec2::new(334.0, 142.0), Vec2::new(13.0, 14.0)),
+        };
+        Rect::from_corners(min, min + size)
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct UiAtlas {
+    pub handle: Handle<TextureAtlas>,
+    indices: HashMap<UiSprite, usize>,
+}
+
+impl UiAtlas {
+    // sizes are in world units, `UiScale` maps them onto the window
+    pub fn image(&self, sprite: UiSprite) -> AtlasImageBundle {
+        let size = sprite.rect().size();
+        AtlasImageBundle {
+            style: Style {
+                width: Val::Px(size.x),
+                height: Val::Px(size.y),
+                ..default()
+            },
+            texture_atlas: self.handle.clone(),
+            texture_atlas_image: UiTextureAtlasImage {
+                index: self.indices.get(&sprite).copied().unwrap_or_default(),
+                ..default()
+            },
+            ..default()
+        }
+    }
+
+    pub fn spawn_button(&self, parent: &mut ChildBuilder, sprite: UiSprite, bundle: impl Bundle) {
+        parent
+            .spawn((
+                ButtonBundle {
+                    background_color: Color::NONE.into(),
+                    ..default()
+                },
+                bundle,
+            ))
+            .with_children(|parent| {
+                parent.spawn(self.image(sprite));
+            });
+    }
+}
+
+pub fn label_style() -> TextStyle {
+    TextStyle {
+        font_size: 6.0,
+        color: Color::hex("#E86101").unwrap(),
+        ..default()
+    }
+}
+
+pub fn spawn_text_button(
+    parent: &mut ChildBuilder,
+    background: Handle<Image>,
+    label: impl Into<String>,
+    bundle: impl Bundle,
+) {
+    parent
+        .spawn((
+            ButtonBundle {
+                style: Style {
+                    padding: UiRect::axes(Val::Px(6.0), Val::Px(3.0)),
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                image: UiImage::new(background),
+                ..default()
+            },
+            bundle,
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(label, label_style()));
+        });
+}
+
+// full size column inside the hud root that gets despawned with its state
+pub fn screen_bundle() -> NodeBundle {
+    NodeBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            flex_direction: FlexDirection::Column,
+            justify_content: JustifyContent::SpaceEvenly,
+            align_items: AlignItems::Center,
+            ..default()
+        },
+        ..default()
+    }
+}
+
+#[derive(Component)]
+pub struct HudRoot;
+
+#[derive(Component, Default)]
+pub struct NumberDisplay(pub i32);
+
+#[derive(Component)]
+struct ScoreDisplay;
+
+#[derive(Component)]
+struct WaitingScreen;
+
+#[derive(Component)]
+struct GameOverScreen;
+
+#[derive(Component, Clone, Copy)]
+pub struct StateButton(pub GameState);
+
+pub struct HudPlugin;
+
+impl Plugin for HudPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<UiAtlas>()
+            .add_systems(OnExit(GameState::Loading), setup)
+            .add_systems(OnEnter(GameState::Waiting), spawn_waiting_screen)
+            .add_systems(OnExit(GameState::Waiting), despawn_screen::<WaitingScreen>)
+            .add_systems(
+                OnEnter(GameState::Dead),
+                spawn_game_over_screen
+                    .after(record_run)
+                    .run_if(not(resource_exists::<LocalVersus>())),
+            )
+            .add_systems(OnExit(GameState::Dead), despawn_screen::<GameOverScreen>)
+            .add_systems(
+                Update,
+                (update_layout, update_score, render_numbers, state_buttons)
+                    .run_if(not(in_state(GameState::Loading))),
+            );
+    }
+}
+
+fn setup(
+    mut commands: Commands,
+    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+    mut ui_atlas: ResMut<UiAtlas>,
+    game_assets: Res<GameAssets>,
+    images: Res<Assets<Image>>,
+) {
+    let Some(spritesheet) = images.get(&game_assets.spritesheet) else {
+        warn!("spritesheet did not resolve to an `Image` asset.");
+        return;
+    };
+
+    let mut texture_atlas =
+        TextureAtlas::new_empty(game_assets.spritesheet.clone(), spritesheet.size_f32());
+    for sprite in UiSprite::all() {
+        let index = texture_atlas.add_texture(sprite.rect());
+        ui_atlas.indices.insert(sprite, index);
+    }
+    ui_atlas.handle = texture_atlases.add(texture_atlas);
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    ..default()
+                },
+                ..default()
+            },
+            HudRoot,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                NodeBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        top: Val::Px(10.0),
+                        width: Val::Percent(100.0),
+                        justify_content: JustifyContent::Center,
+                        ..default()
+                    },
+                    visibility: Visibility::Hidden,
+                    ..default()
+                },
+                NumberDisplay::default(),
+                ScoreDisplay,
+            ));
+        });
+}
+
+fn spawn_waiting_screen(
+    mut commands: Commands,
+    root_query: Query<Entity, With<HudRoot>>,
+    ui_atlas: Res<UiAtlas>,
+) {
+    let Ok(root) = root_query.get_single() else {
+        return;
+    };
+
+    commands.entity(root).with_children(|parent| {
+        parent
+            .spawn((screen_bundle(), WaitingScreen))
+            .with_children(|parent| {
+                parent.spawn(ui_atlas.image(UiSprite::GetReady));
+                parent.spawn(ui_atlas.image(UiSprite::Tutorial));
+            });
+    });
+}
+
+#[allow(clippy::too_many_arguments)]
+fn spawn_game_over_screen(
+    mut commands: Commands,
+    root_query: Query<Entity, With<HudRoot>>,
+    ui_atlas: Res<UiAtlas>,
+    game_assets: Res<GameAssets>,
+    game_score: Res<GameScore>,
+    save_data: Res<SaveData>,
+    run_medal: Res<RunMedal>,
+    game_seed: Res<GameSeed>,
+) {
+    let Ok(root) = root_query.get_single() else {
+        return;
+    };
+
+    let label_style = label_style();
+    let row_style = Style {
+        width: Val::Percent(100.0),
+        justify_content: JustifyContent::SpaceBetween,
+        align_items: AlignItems::Center,
+        ..default()
+    };
+
+    commands.entity(root).with_children(|parent| {
+        parent
+            .spawn((screen_bundle(), GameOverScreen))
+            .with_children(|parent| {
+                parent.spawn(ui_atlas.image(UiSprite::GameOver));
+
+                parent
+                    .spawn(ImageBundle {
+                        style: Style {
+                            width: Val::Px(100.0),
+                            flex_direction: FlexDirection::Column,
+                            row_gap: Val::Px(4.0),
+                            padding: UiRect::axes(Val::Px(12.0), Val::Px(8.0)),
+                            ..default()
+                        },
+                        image: UiImage::new(game_assets.ui_background.clone()),
+                        ..default()
+                    })
+                    .with_children(|parent| {
+                        parent
+                            .spawn(NodeBundle {
+                                style: row_style.clone(),
+                                ..default()
+                            })
+                            .with_children(|parent| {
+                                parent
+                                    .spawn(TextBundle::from_section("SCORE", label_style.clone()));
+                                parent.spawn((NodeBundle::default(), NumberDisplay(**game_score)));
+                            });
+
+                        parent
+                            .spawn(NodeBundle {
+                                style: row_style.clone(),
+                                ..default()
+                            })
+                            .with_children(|parent| {
+                                parent.spawn(TextBundle::from_section("BEST", label_style.clone()));
+                                parent.spawn((
+                                    NodeBundle::default(),
+                                    NumberDisplay(save_data.best_score),
+                                ));
+                            });
+
+                        parent
+                            .spawn(NodeBundle {
+                                style: row_style.clone(),
+                                ..default()
+                            })
+                            .with_children(|parent| {
+                                parent
+                                    .spawn(TextBundle::from_section("MEDAL", label_style.clone()));
+                                match **run_medal {
+                                    Some(medal) => {
+                                        parent.spawn(ui_atlas.image(UiSprite::Medal(medal)));
+                                    }
+                                    None => {
+                                        parent.spawn(NodeBundle {
+                                            style: Style {
+                                                width: Val::Px(22.0),
+                                                height: Val::Px(22.0),
+                                                ..default()
+                                            },
+                                            ..default()
+                                        });
+                                    }
+                                }
+                            });
+
+                        parent
+                            .spawn(NodeBundle {
+                                style: row_style,
+                                ..default()
+                            })
+                            .with_children(|parent| {
+                                parent.spawn(TextBundle::from_section("SEED", label_style.clone()));
+                                parent.spawn(TextBundle::from_section(
+                                    game_seed.to_string(),
+                                    label_style,
+                                ));
+                            });
+                    });
+
+                parent
+                    .spawn(NodeBundle {
+                        style: Style {
+                            column_gap: Val::Px(8.0),
+                            align_items: AlignItems::Center,
+                            ..default()
+                        },
+                        ..default()
+                    })
+                    .with_children(|parent| {
+                        ui_atlas.spawn_button(
+                            parent,
+                            UiSprite::PlayButton,
+                            StateButton(GameState::Waiting),
+                        );
+                        ui_atlas.spawn_button(
+                            parent,
+                            UiSprite::MenuButton,
+                            StateButton(GameState::MainMenu),
+                        );
+                    });
+            });
+    });
+}
+
+pub fn despawn_screen<T: Component>(mut commands: Commands, query: Query<Entity, With<T>>) {
+    query.for_each(|entity| {
+        commands.entity(entity).despawn_recursive();
+    });
+}
+
+// split screen views don't cover the whole window, the hud follows the camera that does
+#[allow(clippy::type_complexity)]
+fn update_layout(
+    mut ui_scale: ResMut<UiScale>,
+    mut root_query: Query<&mut Style, With<HudRoot>>,
+    camera_query: Query<(&Transform, &OrthographicProjection), (With<Camera>, Without<PlayerView>)>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    game_boundaries: Res<GameBoundaries>,
+) {
+    let (Ok((camera_transform, projection)), Ok(window)) =
+        (camera_query.get_single(), window_query.get_single())
+    else {
+        return;
+    };
+    if projection.area.width() <= 0.0 {
+        return;
+    }
+
+    let scale = (window.width() / projection.area.width()) as f64;
+    if ui_scale.0 != scale {
+        ui_scale.0 = scale;
+    }
+
+    // keep the hud inside the part of the game area that is on screen
+    let view = Rect::from_corners(
+        projection.area.min + camera_transform.translation.xy(),
+        projection.area.max + camera_transform.translation.xy(),
+    );
+    let visible = view.intersect(**game_boundaries);
+    root_query.for_each_mut(|mut style| {
+        let left = Val::Px(visible.min.x - view.min.x);
+        let top = Val::Px(view.max.y - visible.max.y);
+        let width = Val::Px(visible.width());
+        let height = Val::Px(visible.height());
+        if style.left != left || style.top != top || style.width != width || style.height != height
+        {
+            style.left = left;
+            style.top = top;
+            style.width = width;
+            style.height = height;
+        }
+    });
+}
+
+fn update_score(
+    mut query: Query<(&mut NumberDisplay, &mut Visibility), With<ScoreDisplay>>,
+    game_score: Res<GameScore>,
+    game_state: Res<State<GameState>>,
+    local_versus: Option<Res<LocalVersus>>,
+) {
+    // versus shows a score per bird instead
+    let visible = local_versus.is_none()
+        && (*game_state == GameState::Playing
+            || *game_state == GameState::Paused
+            || *game_state == GameState::Stopped);
+    query.for_each_mut(|(mut number_display, mut visibility)| {
+        if number_display.0 != **game_score {
+            number_display.0 = **game_score;
+        }
+        visibility.set_if_neq(if visible {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        });
+    });
+}
+
+fn render_numbers(
+    mut commands: Commands,
+    query: Query<(Entity, &NumberDisplay), Changed<NumberDisplay>>,
+    ui_atlas: Res<UiAtlas>,
+) {
+    query.for_each(|(entity, number_display)| {
+        commands
+            .entity(entity)
+            .despawn_descendants()
+            .with_children(|parent| {
+                let digits = number_display.0.max(0).to_string();
+                for digit in digits.chars().filter_map(|c| c.to_digit(10)) {
+                    parent.spawn(ui_atlas.image(UiSprite::Digit(digit)));
+                }
+            });
+    });
+}
+
+fn state_buttons(
+    mut next_state: ResMut<NextState<GameState>>,
+    query: Query<(&Interaction, &StateButton), Changed<Interaction>>,
+) {
+    for (interaction, state_button) in query.iter() {
+        if *interaction == Interaction::Pressed {
+            next_state.set(state_button.0);
+        }
+    }
+}
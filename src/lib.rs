@@ -0,0 +1,28 @@
+pub mod anchor;
+pub mod animation;
+pub mod camera;
+pub mod cli;
+pub mod collider;
+pub mod debug;
+pub mod desync;
+pub mod difficulty;
+pub mod game;
+pub mod ghost;
+pub mod headless;
+pub mod hud;
+pub mod level;
+pub mod lobby;
+pub mod menu;
+pub mod networking;
+pub mod patterns;
+pub mod pause;
+pub mod physics;
+pub mod pipes;
+pub mod player;
+pub mod rating;
+pub mod replay;
+pub mod save;
+pub mod seed;
+pub mod tiling;
+pub mod tuning;
+pub mod versus;
@@ -0,0 +1,463 @@
+use std::{
+    io::{self, ErrorKind},
+    net::{Ipv4Addr, SocketAddr, UdpSocket},
+    time::Duration,
+};
+
+use bevy::{prelude::*, window::ReceivedCharacter};
+use flappy_lobby::protocol::{
+    LobbyError, Message, RoomCode, RoomState, DEFAULT_PORT as DEFAULT_LOBBY_PORT, ROOM_SIZE,
+};
+
+use crate::{
+    cli::Args,
+    game::{GameAssets, GameState},
+    hud::{despawn_screen, label_style, screen_bundle, spawn_text_button, HudRoot, StateButton},
+    menu::GameSettings,
+    networking::NetSession,
+    player::PlayerId,
+};
+
+// requests go over UDP, so they're repeated until the server answers
+const RESEND_INTERVAL: Duration = Duration::from_millis(250);
+// how long to wait for any answer before telling the player the server is unreachable
+const SERVER_TIMEOUT: Duration = Duration::from_secs(3);
+const MAX_PACKET_SIZE: usize = 1024;
+
+// talks to the lobby server from the online screen, becomes a `NetSession` once the race starts
+#[derive(Resource)]
+pub struct LobbyClient {
+    socket: UdpSocket,
+    server: SocketAddr,
+    relay: bool,
+    // `Create` or `Join` until the server puts us in a room
+    request: Option<Message>,
+    room: Option<RoomState>,
+    ready: bool,
+    error: Option<LobbyError>,
+    resend: Duration,
+    silence: Duration,
+}
+
+impl LobbyClient {
+    pub fn connect(server: SocketAddr, relay: bool) -> io::Result<Self> {
+        let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))?;
+        socket.set_nonblocking(true)?;
+        Ok(Self {
+            socket,
+            server,
+            relay,
+            request: None,
+            room: None,
+            ready: false,
+            error: None,
+            resend: Duration::ZERO,
+            silence: Duration::ZERO,
+        })
+    }
+
+    pub fn room(&self) -> Option<&RoomState> {
+        self.room.as_ref()
+    }
+
+    pub fn error(&self) -> Option<LobbyError> {
+        self.error
+    }
+
+    pub fn ready(&self) -> bool {
+        self.ready
+    }
+
+    pub fn unreachable(&self) -> bool {
+        self.request.is_some() && self.silence >= SERVER_TIMEOUT
+    }
+
+    pub fn create(&mut self, seed: Option<u64>) {
+        self.leave();
+        self.request = Some(Message::Create { seed });
+        self.resend = Duration::ZERO;
+    }
+
+    pub fn join(&mut self, code: RoomCode) {
+        self.leave();
+        self.request = Some(Message::Join { code });
+        self.resend = Duration::ZERO;
+    }
+
+    pub fn set_ready(&mut self, ready: bool) {
+        if self.room.is_some() {
+            self.ready = ready;
+            self.resend = Duration::ZERO;
+        }
+    }
+
+    pub fn leave(&mut self) {
+        if let Some(room) = self.room.take() {
+            self.send(&Message::Leave { code: room.code });
+        }
+        self.request = None;
+        self.ready = false;
+        self.error = None;
+        self.silence = Duration::ZERO;
+    }
+
+    fn update(&mut self, delta: Duration) {
+        self.silence += delta;
+        self.receive();
+
+        self.resend = self.resend.saturating_sub(delta);
+        if !self.resend.is_zero() {
+            return;
+        }
+        // the ready state doubles as a keepalive while in a room
+        let message = match &self.room {
+            Some(room) => Some(Message::Ready {
+                code: room.code,
+                ready: self.ready,
+            }),
+            None => self.request.clone(),
+        };
+        if let Some(message) = message {
+            self.send(&message);
+            self.resend = RESEND_INTERVAL;
+        }
+    }
+
+    fn receive(&mut self) {
+        let mut buffer = [0; MAX_PACKET_SIZE];
+        loop {
+            let (len, from) = match self.socket.recv_from(&mut buffer) {
+                Ok(received) => received,
+                Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                // e.g. nothing is listening on the server's port
+                Err(_) => break,
+            };
+            if from != self.server {
+                continue;
+            }
+
+            match Message::decode(&buffer[..len]) {
+                Some(Message::Room(room)) => {
+                    self.silence = Duration::ZERO;
+                    // answers to a room that was already left
+                    if self.request.is_none() && self.room.is_none() {
+                        continue;
+                    }
+                    self.request = None;
+                    self.error = None;
+                    self.room = Some(room);
+                }
+                Some(Message::Error(err)) => {
+                    self.silence = Duration::ZERO;
+                    self.request = None;
+                    self.room = None;
+                    self.ready = false;
+                    self.error = Some(err);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn send(&self, message: &Message) {
+        // repeated until it's answered anyway
+        let _ = self.socket.send_to(&message.encode(), self.server);
+    }
+
+    fn into_session(self, room: &RoomState, peer: SocketAddr) -> io::Result<NetSession> {
+        let player = PlayerId(room.player as usize);
+        let session = NetSession::new(self.socket, peer, player, room.seed)?;
+        Ok(session.with_relay(self.server, room.code, self.relay))
+    }
+}
+
+// letters typed so far while the join button is being edited
+#[derive(Resource, Default, Deref, DerefMut)]
+struct RoomEntry(Option<String>);
+
+#[derive(Component)]
+struct LobbyScreen;
+
+#[derive(Component)]
+struct LobbyStatus;
+
+#[derive(Component, Clone, Copy, PartialEq, Eq)]
+enum LobbyButton {
+    Create,
+    Join,
+    Ready,
+}
+
+pub struct LobbyPlugin;
+
+impl Plugin for LobbyPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RoomEntry>()
+            .add_systems(
+                OnEnter(GameState::Lobby),
+                (open_lobby, spawn_lobby_screen).chain(),
+            )
+            .add_systems(
+                OnExit(GameState::Lobby),
+                (despawn_screen::<LobbyScreen>, leave_lobby),
+            )
+            .add_systems(
+                Update,
+                (
+                    (lobby_buttons, type_room_code)
+                        .chain()
+                        .run_if(resource_exists::<LobbyClient>()),
+                    update_lobby,
+                    start_race,
+                    update_lobby_labels,
+                )
+                    .chain()
+                    .run_if(in_state(GameState::Lobby)),
+            );
+    }
+}
+
+fn open_lobby(mut commands: Commands, args: Option<Res<Args>>, client: Option<Res<LobbyClient>>) {
+    if client.is_some() {
+        return;
+    }
+
+    let server = args
+        .as_ref()
+        .and_then(|args| args.lobby)
+        .unwrap_or((Ipv4Addr::LOCALHOST, DEFAULT_LOBBY_PORT).into());
+    let relay = args.is_some_and(|args| args.relay);
+    match LobbyClient::connect(server, relay) {
+        Ok(client) => {
+            info!("using the lobby at {server}");
+            commands.insert_resource(client);
+        }
+        Err(err) => error!("failed to open lobby connection: {err}"),
+    }
+}
+
+fn spawn_lobby_screen(
+    mut commands: Commands,
+    root_query: Query<Entity, With<HudRoot>>,
+    game_assets: Res<GameAssets>,
+) {
+    let Ok(root) = root_query.get_single() else {
+        return;
+    };
+
+    commands.entity(root).with_children(|parent| {
+        parent
+            .spawn((screen_bundle(), LobbyScreen))
+            .with_children(|parent| {
+                parent.spawn(TextBundle::from_section("ONLINE", label_style()));
+                parent.spawn((
+                    TextBundle::from_section("", label_style())
+                        .with_text_alignment(TextAlignment::Center),
+                    LobbyStatus,
+                ));
+                for (label, button) in [
+                    ("CREATE ROOM", LobbyButton::Create),
+                    ("JOIN ROOM", LobbyButton::Join),
+                    ("READY: NO", LobbyButton::Ready),
+                ] {
+                    spawn_text_button(parent, game_assets.ui_background.clone(), label, button);
+                }
+                spawn_text_button(
+                    parent,
+                    game_assets.ui_background.clone(),
+                    "BACK",
+                    StateButton(GameState::MainMenu),
+                );
+            });
+    });
+}
+
+fn leave_lobby(
+    mut commands: Commands,
+    mut room_entry: ResMut<RoomEntry>,
+    client: Option<ResMut<LobbyClient>>,
+) {
+    **room_entry = None;
+    if let Some(mut client) = client {
+        client.leave();
+        commands.remove_resource::<LobbyClient>();
+    }
+}
+
+fn lobby_buttons(
+    mut client: ResMut<LobbyClient>,
+    mut room_entry: ResMut<RoomEntry>,
+    query: Query<(&Interaction, &LobbyButton), Changed<Interaction>>,
+    game_settings: Res<GameSettings>,
+) {
+    for (interaction, button) in query.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+
+        match button {
+            LobbyButton::Create => {
+                **room_entry = None;
+                client.create(game_settings.seed);
+            }
+            LobbyButton::Join => match room_entry.take() {
+                Some(letters) => join_room(&mut client, &letters),
+                None => **room_entry = Some(String::new()),
+            },
+            LobbyButton::Ready => {
+                let ready = !client.ready();
+                client.set_ready(ready);
+            }
+        }
+    }
+}
+
+fn type_room_code(
+    mut client: ResMut<LobbyClient>,
+    mut room_entry: ResMut<RoomEntry>,
+    mut char_events: EventReader<ReceivedCharacter>,
+    keyboard_input: Res<Input<KeyCode>>,
+) {
+    let Some(letters) = &mut **room_entry else {
+        char_events.clear();
+        return;
+    };
+
+    for event in char_events.read() {
+        if RoomCode::is_letter(event.char) && letters.len() < 4 {
+            letters.push(event.char.to_ascii_uppercase());
+        }
+    }
+
+    if keyboard_input.just_pressed(KeyCode::Back) {
+        letters.pop();
+    }
+
+    if keyboard_input.any_just_pressed([KeyCode::Return, KeyCode::NumpadEnter]) {
+        if let Some(letters) = room_entry.take() {
+            join_room(&mut client, &letters);
+        }
+    }
+}
+
+// an incomplete code just closes the entry
+fn join_room(client: &mut LobbyClient, letters: &str) {
+    if let Some(code) = RoomCode::parse(letters) {
+        client.join(code);
+    }
+}
+
+fn update_lobby(client: Option<ResMut<LobbyClient>>, time: Res<Time>) {
+    if let Some(mut client) = client {
+        client.update(time.delta());
+    }
+}
+
+// everyone in the room is ready, the online screen stays up until the peers connect
+fn start_race(world: &mut World) {
+    let Some((room, peer)) = world
+        .get_resource::<LobbyClient>()
+        .and_then(LobbyClient::room)
+        .and_then(|room| Some((room.clone(), room.peer?)))
+    else {
+        return;
+    };
+
+    let client = world.remove_resource::<LobbyClient>().unwrap();
+    match client.into_session(&room, peer) {
+        Ok(session) => {
+            info!(
+                "room {} started, waiting for {peer} as player {}",
+                room.code,
+                room.player + 1
+            );
+            world.insert_resource(session);
+        }
+        Err(err) => error!("failed to start network session: {err}"),
+    }
+}
+
+fn lobby_status(client: Option<&LobbyClient>, session: Option<&NetSession>) -> String {
+    if let Some(session) = session {
+        if let Some(mismatch) = session.refused() {
+            return mismatch.to_uppercase();
+        }
+        return if session.connected() {
+            "STARTING".into()
+        } else {
+            "CONNECTING...".into()
+        };
+    }
+    let Some(client) = client else {
+        return "NO CONNECTION".into();
+    };
+
+    if client.unreachable() {
+        return "NO ANSWER FROM\nTHE LOBBY".into();
+    }
+    if let Some(err) = client.error() {
+        return err.to_string().to_uppercase();
+    }
+    let Some(room) = client.room() else {
+        return match client.request {
+            Some(Message::Create { .. }) => "CREATING ROOM...".into(),
+            Some(Message::Join { code }) => format!("JOINING {code}..."),
+            _ => "CREATE A ROOM\nOR JOIN ONE".into(),
+        };
+    };
+
+    let mut status = format!("ROOM {}", room.code);
+    for (player, ready) in room.ready.iter().enumerate() {
+        let you = if player == room.player as usize {
+            " (YOU)"
+        } else {
+            ""
+        };
+        let ready = if *ready { "READY" } else { "WAITING" };
+        status.push_str(&format!("\nPLAYER {}{you}: {ready}", player + 1));
+    }
+    if room.ready.len() < ROOM_SIZE {
+        status.push_str("\nWAITING FOR A PLAYER");
+    }
+    status
+}
+
+fn update_lobby_labels(
+    mut text_query: Query<&mut Text>,
+    status_query: Query<Entity, With<LobbyStatus>>,
+    button_query: Query<(&LobbyButton, &Children)>,
+    client: Option<Res<LobbyClient>>,
+    session: Option<Res<NetSession>>,
+    room_entry: Res<RoomEntry>,
+) {
+    let client = client.as_deref();
+    let mut set = |entity: Entity, label: String| {
+        if let Ok(mut text) = text_query.get_mut(entity) {
+            if text.sections[0].value != label {
+                text.sections[0].value = label;
+            }
+        }
+    };
+
+    status_query.for_each(|entity| set(entity, lobby_status(client, session.as_deref())));
+    button_query.for_each(|(button, children)| {
+        let label = match button {
+            LobbyButton::Create => return,
+            LobbyButton::Join => match &**room_entry {
+                Some(letters) => format!("JOIN: {letters}_"),
+                None => "JOIN ROOM".into(),
+            },
+            LobbyButton::Ready => format!(
+                "READY: {}",
+                if client.is_some_and(LobbyClient::ready) {
+                    "YES"
+                } else {
+                    "NO"
+                }
+            ),
+        };
+        for child in children.iter() {
+            set(*child, label.clone());
+        }
+    });
+}
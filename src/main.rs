@@ -1,18 +1,24 @@
-mod anchor;
-mod animation;
-mod camera;
-mod game;
-mod level;
-mod physics;
-mod pipes;
-mod player;
-mod tiling;
-
 use bevy::prelude::*;
 use bevy_framepace::FramepacePlugin;
-use game::GamePlugins;
+use flappy_bird::{cli::Args, game::GamePlugins, headless};
 
 fn main() {
+    let args = Args::from_env().unwrap_or_else(|err| {
+        eprintln!("{err}");
+        std::process::exit(2);
+    });
+
+    if args.headless {
+        match headless::app(args) {
+            Ok(mut app) => app.run(),
+            Err(err) => {
+                eprintln!("{err}");
+                std::process::exit(2);
+            }
+        }
+        return;
+    }
+
     App::new()
         .add_plugins((
             DefaultPlugins
@@ -25,10 +31,16 @@ fn main() {
                     }),
                     ..default()
                 })
-                .set(ImagePlugin::default_nearest()),
+                .set(ImagePlugin::default_nearest())
+                // so the tuning file can be edited while playing
+                .set(AssetPlugin {
+                    watch_for_changes_override: Some(true),
+                    ..default()
+                }),
             FramepacePlugin,
             GamePlugins,
         ))
+        .insert_resource(args)
         // .insert_resource(FramepaceSettings::default().with_limiter(Limiter::from_framerate(60.0)))
         .run();
 }
@@ -0,0 +1,445 @@
+use bevy::{
+    audio::AudioSink, input::common_conditions::input_just_pressed, prelude::*,
+    window::ReceivedCharacter,
+};
+
+use crate::{
+    difficulty::Difficulty,
+    game::{GameAssets, GameState},
+    hud::{
+        despawn_screen, label_style, screen_bundle, spawn_text_button, HudRoot, StateButton,
+        UiAtlas, UiSprite,
+    },
+    versus::{VersusButton, MAX_LOCAL_PLAYERS},
+};
+
+#[derive(Resource)]
+pub struct GameSettings {
+    pub sound: bool,
+    pub ghost: bool,
+    // `None` rolls a new seed for every run
+    pub seed: Option<u64>,
+    // birds in a party round
+    pub party_size: usize,
+}
+
+impl Default for GameSettings {
+    fn default() -> Self {
+        Self {
+            sound: true,
+            ghost: true,
+            seed: None,
+            party_size: 2,
+        }
+    }
+}
+
+// digits typed so far while the seed button is being edited
+#[derive(Resource, Default, Deref, DerefMut)]
+struct SeedEntry(Option<String>);
+
+#[derive(Component)]
+struct MainMenuScreen;
+
+#[derive(Component)]
+struct SettingsScreen;
+
+#[derive(Component)]
+struct SoundToggle;
+
+#[derive(Component)]
+struct GhostToggle;
+
+#[derive(Component)]
+struct SeedInput;
+
+#[derive(Component)]
+struct PartySizeToggle;
+
+#[derive(Component)]
+struct DifficultyToggle;
+
+pub struct MenuPlugin;
+
+impl Plugin for MenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GameSettings>()
+            .init_resource::<SeedEntry>()
+            .add_systems(OnEnter(GameState::MainMenu), spawn_main_menu)
+            .add_systems(
+                OnExit(GameState::MainMenu),
+                despawn_screen::<MainMenuScreen>,
+            )
+            .add_systems(OnEnter(GameState::Settings), spawn_settings)
+            .add_systems(
+                OnExit(GameState::Settings),
+                (despawn_screen::<SettingsScreen>, commit_seed_entry),
+            )
+            .add_systems(
+                Update,
+                (
+                    start_game
+                        .run_if(in_state(GameState::MainMenu))
+                        .run_if(input_just_pressed(KeyCode::Space)),
+                    (
+                        toggle_sound,
+                        toggle_ghost,
+                        toggle_party_size,
+                        toggle_difficulty,
+                    )
+                        .run_if(in_state(GameState::Settings)),
+                    (edit_seed, type_seed)
+                        .chain()
+                        .run_if(in_state(GameState::Settings)),
+                    update_sound_label,
+                    update_ghost_label,
+                    update_seed_label,
+                    update_party_size_label,
+                    update_difficulty_label,
+                    apply_sound_settings,
+                ),
+            );
+    }
+}
+
+fn spawn_main_menu(
+    mut commands: Commands,
+    root_query: Query<Entity, With<HudRoot>>,
+    ui_atlas: Res<UiAtlas>,
+    game_assets: Res<GameAssets>,
+) {
+    let Ok(root) = root_query.get_single() else {
+        return;
+    };
+
+    commands.entity(root).with_children(|parent| {
+        parent
+            .spawn((screen_bundle(), MainMenuScreen))
+            .with_children(|parent| {
+                parent.spawn(ui_atlas.image(UiSprite::Title));
+                ui_atlas.spawn_button(
+                    parent,
+                    UiSprite::PlayButton,
+                    StateButton(GameState::Waiting),
+                );
+                spawn_text_button(
+                    parent,
+                    game_assets.ui_background.clone(),
+                    "VERSUS",
+                    VersusButton::SplitScreen,
+                );
+                spawn_text_button(
+                    parent,
+                    game_assets.ui_background.clone(),
+                    "PARTY",
+                    VersusButton::Party,
+                );
+                spawn_text_button(
+                    parent,
+                    game_assets.ui_background.clone(),
+                    "ONLINE",
+                    StateButton(GameState::Lobby),
+                );
+                spawn_text_button(
+                    parent,
+                    game_assets.ui_background.clone(),
+                    "SETTINGS",
+                    StateButton(GameState::Settings),
+                );
+            });
+    });
+}
+
+fn spawn_settings(
+    mut commands: Commands,
+    root_query: Query<Entity, With<HudRoot>>,
+    ui_atlas: Res<UiAtlas>,
+    game_assets: Res<GameAssets>,
+    game_settings: Res<GameSettings>,
+    seed_entry: Res<SeedEntry>,
+    difficulty: Res<Difficulty>,
+) {
+    let Ok(root) = root_query.get_single() else {
+        return;
+    };
+
+    commands.entity(root).with_children(|parent| {
+        parent
+            .spawn((screen_bundle(), SettingsScreen))
+            .with_children(|parent| {
+                parent.spawn(TextBundle::from_section("SETTINGS", label_style()));
+                spawn_text_button(
+                    parent,
+                    game_assets.ui_background.clone(),
+                    sound_label(&game_settings),
+                    SoundToggle,
+                );
+                spawn_text_button(
+                    parent,
+                    game_assets.ui_background.clone(),
+                    ghost_label(&game_settings),
+                    GhostToggle,
+                );
+                spawn_text_button(
+                    parent,
+                    game_assets.ui_background.clone(),
+                    seed_label(&game_settings, &seed_entry),
+                    SeedInput,
+                );
+                spawn_text_button(
+                    parent,
+                    game_assets.ui_background.clone(),
+                    difficulty_label(*difficulty),
+                    DifficultyToggle,
+                );
+                spawn_text_button(
+                    parent,
+                    game_assets.ui_background.clone(),
+                    party_size_label(&game_settings),
+                    PartySizeToggle,
+                );
+                ui_atlas.spawn_button(parent, UiSprite::OkButton, StateButton(GameState::MainMenu));
+            });
+    });
+}
+
+fn sound_label(game_settings: &GameSettings) -> String {
+    format!("SOUND: {}", if game_settings.sound { "ON" } else { "OFF" })
+}
+
+fn ghost_label(game_settings: &GameSettings) -> String {
+    format!("GHOST: {}", if game_settings.ghost { "ON" } else { "OFF" })
+}
+
+fn seed_label(game_settings: &GameSettings, seed_entry: &SeedEntry) -> String {
+    match (&**seed_entry, game_settings.seed) {
+        (Some(digits), _) => format!("SEED: {digits}_"),
+        (None, Some(seed)) => format!("SEED: {seed}"),
+        (None, None) => "SEED: RANDOM".into(),
+    }
+}
+
+fn party_size_label(game_settings: &GameSettings) -> String {
+    format!("PARTY: {} BIRDS", game_settings.party_size)
+}
+
+fn difficulty_label(difficulty: Difficulty) -> String {
+    format!("DIFFICULTY: {}", difficulty.to_string().to_uppercase())
+}
+
+fn start_game(mut next_state: ResMut<NextState<GameState>>) {
+    next_state.set(GameState::Waiting);
+}
+
+fn toggle_sound(
+    mut game_settings: ResMut<GameSettings>,
+    query: Query<&Interaction, (Changed<Interaction>, With<SoundToggle>)>,
+) {
+    for interaction in query.iter() {
+        if *interaction == Interaction::Pressed {
+            game_settings.sound = !game_settings.sound;
+        }
+    }
+}
+
+fn toggle_ghost(
+    mut game_settings: ResMut<GameSettings>,
+    query: Query<&Interaction, (Changed<Interaction>, With<GhostToggle>)>,
+) {
+    for interaction in query.iter() {
+        if *interaction == Interaction::Pressed {
+            game_settings.ghost = !game_settings.ghost;
+        }
+    }
+}
+
+// cycles through every size that needs at least two birds
+fn toggle_party_size(
+    mut game_settings: ResMut<GameSettings>,
+    query: Query<&Interaction, (Changed<Interaction>, With<PartySizeToggle>)>,
+) {
+    for interaction in query.iter() {
+        if *interaction == Interaction::Pressed {
+            game_settings.party_size = if game_settings.party_size >= MAX_LOCAL_PLAYERS {
+                2
+            } else {
+                game_settings.party_size + 1
+            };
+        }
+    }
+}
+
+fn toggle_difficulty(
+    mut difficulty: ResMut<Difficulty>,
+    query: Query<&Interaction, (Changed<Interaction>, With<DifficultyToggle>)>,
+) {
+    for interaction in query.iter() {
+        if *interaction == Interaction::Pressed {
+            *difficulty = difficulty.next();
+        }
+    }
+}
+
+fn edit_seed(
+    mut seed_entry: ResMut<SeedEntry>,
+    mut game_settings: ResMut<GameSettings>,
+    query: Query<&Interaction, (Changed<Interaction>, With<SeedInput>)>,
+) {
+    for interaction in query.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+
+        if seed_entry.is_some() {
+            commit_seed(&mut seed_entry, &mut game_settings);
+        } else {
+            **seed_entry = Some(
+                game_settings
+                    .seed
+                    .map(|seed| seed.to_string())
+                    .unwrap_or_default(),
+            );
+        }
+    }
+}
+
+fn type_seed(
+    mut seed_entry: ResMut<SeedEntry>,
+    mut game_settings: ResMut<GameSettings>,
+    mut char_events: EventReader<ReceivedCharacter>,
+    keyboard_input: Res<Input<KeyCode>>,
+) {
+    let Some(digits) = &mut **seed_entry else {
+        char_events.clear();
+        return;
+    };
+
+    for event in char_events.read() {
+        if event.char.is_ascii_digit() {
+            digits.push(event.char);
+            // drop digits that would overflow the seed
+            if digits.parse::<u64>().is_err() {
+                digits.pop();
+            }
+        }
+    }
+
+    if keyboard_input.just_pressed(KeyCode::Back) {
+        digits.pop();
+    }
+
+    if keyboard_input.any_just_pressed([KeyCode::Return, KeyCode::NumpadEnter]) {
+        commit_seed(&mut seed_entry, &mut game_settings);
+    }
+}
+
+fn commit_seed_entry(mut seed_entry: ResMut<SeedEntry>, mut game_settings: ResMut<GameSettings>) {
+    commit_seed(&mut seed_entry, &mut game_settings);
+}
+
+// an empty entry switches back to random seeds
+fn commit_seed(seed_entry: &mut SeedEntry, game_settings: &mut GameSettings) {
+    if let Some(digits) = seed_entry.take() {
+        game_settings.seed = digits.parse().ok();
+    }
+}
+
+fn update_sound_label(
+    mut text_query: Query<&mut Text>,
+    toggle_query: Query<&Children, With<SoundToggle>>,
+    game_settings: Res<GameSettings>,
+) {
+    if !game_settings.is_changed() {
+        return;
+    }
+
+    toggle_query.for_each(|children| {
+        let mut iter = text_query.iter_many_mut(children);
+        while let Some(mut text) = iter.fetch_next() {
+            text.sections[0].value = sound_label(&game_settings);
+        }
+    });
+}
+
+fn update_ghost_label(
+    mut text_query: Query<&mut Text>,
+    toggle_query: Query<&Children, With<GhostToggle>>,
+    game_settings: Res<GameSettings>,
+) {
+    if !game_settings.is_changed() {
+        return;
+    }
+
+    toggle_query.for_each(|children| {
+        let mut iter = text_query.iter_many_mut(children);
+        while let Some(mut text) = iter.fetch_next() {
+            text.sections[0].value = ghost_label(&game_settings);
+        }
+    });
+}
+
+fn update_seed_label(
+    mut text_query: Query<&mut Text>,
+    input_query: Query<&Children, With<SeedInput>>,
+    game_settings: Res<GameSettings>,
+    seed_entry: Res<SeedEntry>,
+) {
+    if !game_settings.is_changed() && !seed_entry.is_changed() {
+        return;
+    }
+
+    input_query.for_each(|children| {
+        let mut iter = text_query.iter_many_mut(children);
+        while let Some(mut text) = iter.fetch_next() {
+            text.sections[0].value = seed_label(&game_settings, &seed_entry);
+        }
+    });
+}
+
+fn update_party_size_label(
+    mut text_query: Query<&mut Text>,
+    toggle_query: Query<&Children, With<PartySizeToggle>>,
+    game_settings: Res<GameSettings>,
+) {
+    if !game_settings.is_changed() {
+        return;
+    }
+
+    toggle_query.for_each(|children| {
+        let mut iter = text_query.iter_many_mut(children);
+        while let Some(mut text) = iter.fetch_next() {
+            text.sections[0].value = party_size_label(&game_settings);
+        }
+    });
+}
+
+fn update_difficulty_label(
+    mut text_query: Query<&mut Text>,
+    toggle_query: Query<&Children, With<DifficultyToggle>>,
+    difficulty: Res<Difficulty>,
+) {
+    if !difficulty.is_changed() {
+        return;
+    }
+
+    toggle_query.for_each(|children| {
+        let mut iter = text_query.iter_many_mut(children);
+        while let Some(mut text) = iter.fetch_next() {
+            text.sections[0].value = difficulty_label(*difficulty);
+        }
+    });
+}
+
+// sounds are fire and forget, so mute them as soon as they start playing
+fn apply_sound_settings(
+    query: Query<&AudioSink, Added<AudioSink>>,
+    game_settings: Res<GameSettings>,
+) {
+    if game_settings.sound {
+        return;
+    }
+
+    query.for_each(|sink| {
+        sink.set_volume(0.0);
+    });
+}
@@ -1,4 +1,38 @@
-use bevy::prelude::*;
+use std::{
+    collections::VecDeque,
+    io::{self, ErrorKind, Read, Write},
+    net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream, UdpSocket},
+    path::PathBuf,
+    thread,
+    time::Duration,
+};
+
+use bevy::{ecs::system::CommandQueue, prelude::*, utils::HashMap};
+use flappy_lobby::protocol::{Message as LobbyMessage, RoomCode};
+
+use crate::{
+    cli::Args,
+    desync::{checksum, write_report, BirdCheck, CheckedState},
+    difficulty::Difficulty,
+    game::{DistanceTraveled, GameAssets, GameScore, GameSet, GameState, GameTick},
+    headless,
+    patterns::PipePatterns,
+    physics::{Contacts, GravityScale, InterpolatedTransform, TickRate, Velocity},
+    pipes::{spawn_pipes, PipeMotion, PipeShape, PipeSpawner, Pipes},
+    player::{
+        flap_input, gather_inputs, spawn_player, DeadPlayer, DeathCause, DeathTimer, FlapInputs,
+        LocalPlayer, Player, PlayerAnimations, PlayerAtlas, PlayerId, PlayerScore, QueuedFlap,
+        RunDeath,
+    },
+    replay::{
+        apply_playback, ByteReader, GameConfig, ReplayConfig, ReplayPlayback, RunTick,
+        REPLAY_VERSION,
+    },
+    save::SavePath,
+    seed::{reseed, GameRng, GameSeed},
+    tuning::Tuning,
+    versus::LocalVersus,
+};
 
 pub const NUM_PLAYERS: usize = 2;
 pub const FPS: usize = 60;
@@ -6,8 +40,1547 @@ pub const MAX_PREDICTION: usize = 12;
 pub const INPUT_DELAY: usize = 2;
 pub const CHECK_DISTANCE: usize = 2;
 
+pub const DEFAULT_PORT: u16 = 7000;
+pub const DEFAULT_SPECTATE_DELAY: Duration = Duration::from_millis(500);
+
+const PACKET_MAGIC: &[u8; 4] = b"FBNP";
+const PACKET_VERSION: u8 = 2;
+const MAX_PACKET_SIZE: usize = 1024;
+const MAX_PACKET_INPUTS: usize = u8::MAX as usize;
+// every frame that can still be rolled back to, plus the one being predicted
+const SNAPSHOT_COUNT: usize = MAX_PREDICTION + 2;
+// ticks without hearing from the peer before the round is played out alone
+const PEER_TIMEOUT: u32 = FPS as u32 * 5;
+const REMOTE_TINT: Color = Color::rgb(1.0, 0.55, 0.55);
+// enough checks to cover a peer that's a whole prediction window behind
+const CHECK_HISTORY: usize = MAX_PREDICTION * 4 / CHECK_DISTANCE;
+// only pipes around the birds are compared, how far ahead they exist depends on the window size
+const CHECKED_PIPE_RANGE: f32 = 32.0;
+// ticks without reaching the peer directly before packets go through the lobby server instead
+const RELAY_FALLBACK: u32 = FPS as u32 * 2;
+
+const SPECTATE_MAGIC: &[u8; 4] = b"FBSP";
+const SPECTATE_VERSION: u8 = 6;
+// the message type and the size of the rest, which is the seed and config since patterns can make
+// the config any length
+const START_HEADER_SIZE: usize = 1 + 4;
+// the message type, then the state and flap
+const TICK_MESSAGE_SIZE: usize = 3;
+// how many ticks a spectator that fell behind its delay plays per update to catch up
+const MAX_CATCH_UP: u32 = 8;
+// what both peers have to be playing with before they race, the guest takes on the host's difficulty
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct SessionConfig {
+    difficulty: Difficulty,
+    // checksums rather than the files themselves, so they fit any packet
+    tuning: u64,
+    patterns: u64,
+}
+
+impl SessionConfig {
+    fn capture(world: &World) -> Self {
+        let mut tuning = Vec::new();
+        world.resource::<Tuning>().encode(&mut tuning);
+        let mut patterns = Vec::new();
+        world.resource::<PipePatterns>().encode(&mut patterns);
+        Self {
+            difficulty: *world.resource::<Difficulty>(),
+            tuning: checksum(&tuning),
+            patterns: checksum(&patterns),
+        }
+    }
+
+    // why a peer playing with `remote` can't race this one
+    fn mismatch(&self, remote: &Self) -> Option<&'static str> {
+        if self.tuning != remote.tuning {
+            Some("tuning doesn't match the peer's")
+        } else if self.patterns != remote.patterns {
+            Some("pipe patterns don't match the peer's")
+        } else {
+            None
+        }
+    }
+
+    fn encode(&self, bytes: &mut Vec<u8>) {
+        bytes.push(self.difficulty.to_byte());
+        bytes.extend_from_slice(&self.tuning.to_le_bytes());
+        bytes.extend_from_slice(&self.patterns.to_le_bytes());
+    }
+
+    fn decode(reader: &mut ByteReader) -> Option<Self> {
+        Some(Self {
+            difficulty: Difficulty::from_byte(reader.take::<1>().ok()?[0])?,
+            tuning: u64::from_le_bytes(reader.take().ok()?),
+            patterns: u64::from_le_bytes(reader.take().ok()?),
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Packet {
+    // sent until the peer answers, the host's seed is the one both peers play
+    Hello {
+        seed: u64,
+        connected: bool,
+        config: SessionConfig,
+    },
+    // every local input the peer hasn't acknowledged yet, starting at frame `start`
+    Inputs {
+        ack: u32,
+        start: u32,
+        flaps: Vec<bool>,
+    },
+    // the newest confirmed frame that was checked
+    Checksum {
+        frame: u32,
+        checksum: u64,
+    },
+    // sent after a desync so both sides can report what diverged
+    State(CheckedState),
+}
+
+impl Packet {
+    fn encode(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(PACKET_MAGIC);
+        bytes.push(PACKET_VERSION);
+        match self {
+            Packet::Hello {
+                seed,
+                connected,
+                config,
+            } => {
+                bytes.push(0);
+                bytes.extend_from_slice(&seed.to_le_bytes());
+                bytes.push(*connected as u8);
+                config.encode(&mut bytes);
+            }
+            Packet::Inputs { ack, start, flaps } => {
+                bytes.push(1);
+                bytes.extend_from_slice(&ack.to_le_bytes());
+                bytes.extend_from_slice(&start.to_le_bytes());
+                bytes.push(flaps.len() as u8);
+                bytes.extend(flaps.iter().map(|flap| *flap as u8));
+            }
+            Packet::Checksum { frame, checksum } => {
+                bytes.push(2);
+                bytes.extend_from_slice(&frame.to_le_bytes());
+                bytes.extend_from_slice(&checksum.to_le_bytes());
+            }
+            Packet::State(state) => {
+                bytes.push(3);
+                state.encode(&mut bytes);
+            }
+        }
+        bytes
+    }
+
+    // anything malformed is dropped like a lost packet
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        let mut reader = ByteReader(bytes);
+        if reader.take::<4>().ok()? != *PACKET_MAGIC
+            || reader.take::<1>().ok()?[0] != PACKET_VERSION
+        {
+            return None;
+        }
+
+        match reader.take::<1>().ok()?[0] {
+            0 => Some(Packet::Hello {
+                seed: u64::from_le_bytes(reader.take().ok()?),
+                connected: reader.take::<1>().ok()?[0] != 0,
+                config: SessionConfig::decode(&mut reader)?,
+            }),
+            1 => {
+                let ack = u32::from_le_bytes(reader.take().ok()?);
+                let start = u32::from_le_bytes(reader.take().ok()?);
+                let len = reader.take::<1>().ok()?[0];
+                let mut flaps = Vec::with_capacity(len as usize);
+                for _ in 0..len {
+                    flaps.push(reader.take::<1>().ok()?[0] != 0);
+                }
+                Some(Packet::Inputs { ack, start, flaps })
+            }
+            2 => Some(Packet::Checksum {
+                frame: u32::from_le_bytes(reader.take().ok()?),
+                checksum: u64::from_le_bytes(reader.take().ok()?),
+            }),
+            3 => CheckedState::decode(&mut reader).map(Packet::State),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone)]
+struct BirdState {
+    player_id: PlayerId,
+    transform: Transform,
+    velocity: Velocity,
+    gravity_scale: GravityScale,
+    interpolated: InterpolatedTransform,
+    score: PlayerScore,
+}
+
+#[derive(Clone)]
+struct PipesState {
+    pipes: Pipes,
+    motion: Option<PipeMotion>,
+    transform: Transform,
+    interpolated: InterpolatedTransform,
+    // in spawn order, to carry what they were touching over to their respawned selves
+    children: Vec<Entity>,
+}
+
+// everything a tick reads that isn't rebuilt from something else, taken right before the tick
+#[derive(Clone)]
+struct Snapshot {
+    frame: u32,
+    birds: Vec<BirdState>,
+    pipes: Vec<PipesState>,
+    spawners: Vec<PipeSpawner>,
+    distance_traveled: f32,
+    game_score: i32,
+    game_rng: GameRng,
+    run_tick: u32,
+    run_death: Option<DeathCause>,
+    dead_player: Option<PlayerId>,
+    death_timer: DeathTimer,
+    pending_state: Option<GameState>,
+    contacts: Contacts,
+}
+
+impl Snapshot {
+    fn capture(world: &mut World, frame: u32, pending_state: Option<GameState>) -> Self {
+        let birds = world
+            .query::<(
+                &PlayerId,
+                &Transform,
+                &Velocity,
+                &GravityScale,
+                &InterpolatedTransform,
+                &PlayerScore,
+            )>()
+            .iter(world)
+            .map(
+                |(player_id, transform, velocity, gravity_scale, interpolated, score)| BirdState {
+                    player_id: *player_id,
+                    transform: *transform,
+                    velocity: *velocity,
+                    gravity_scale: *gravity_scale,
+                    interpolated: interpolated.clone(),
+                    score: *score,
+                },
+            )
+            .collect();
+
+        let pipes = world
+            .query::<(
+                &Pipes,
+                Option<&PipeMotion>,
+                &Transform,
+                &InterpolatedTransform,
+                &Children,
+            )>()
+            .iter(world)
+            .map(
+                |(pipes, motion, transform, interpolated, children)| PipesState {
+                    pipes: pipes.clone(),
+                    motion: motion.copied(),
+                    transform: *transform,
+                    interpolated: interpolated.clone(),
+                    children: children.to_vec(),
+                },
+            )
+            .collect();
+
+        let spawners = world.query::<&PipeSpawner>().iter(world).cloned().collect();
+
+        Self {
+            frame,
+            birds,
+            pipes,
+            spawners,
+            distance_traveled: **world.resource::<DistanceTraveled>(),
+            game_score: **world.resource::<GameScore>(),
+            game_rng: world.resource::<GameRng>().clone(),
+            run_tick: **world.resource::<RunTick>(),
+            run_death: **world.resource::<RunDeath>(),
+            dead_player: **world.resource::<DeadPlayer>(),
+            death_timer: world.resource::<DeathTimer>().clone(),
+            pending_state,
+            contacts: world.resource::<Contacts>().clone(),
+        }
+    }
+
+    fn checked_state(&self) -> CheckedState {
+        let mut birds = self
+            .birds
+            .iter()
+            .map(|bird| BirdCheck {
+                player_id: bird.player_id,
+                translation: bird.interpolated.current().translation.xy(),
+                velocity: *bird.velocity,
+            })
+            .collect::<Vec<_>>();
+        birds.sort_by_key(|bird| *bird.player_id);
+
+        let mut pipes = self
+            .pipes
+            .iter()
+            .map(|state| state.pipes.spawn_position)
+            .filter(|position| (position.x - self.distance_traveled).abs() <= CHECKED_PIPE_RANGE)
+            .collect::<Vec<_>>();
+        pipes.sort_by(|a, b| a.x.total_cmp(&b.x));
+
+        CheckedState {
+            frame: self.frame,
+            birds,
+            pipes,
+            score: self.game_score,
+            distance: self.distance_traveled,
+        }
+    }
+
+    fn restore(&self, world: &mut World) {
+        let mut bird_query = world.query::<(
+            &PlayerId,
+            &mut Transform,
+            &mut Velocity,
+            &mut GravityScale,
+            &mut InterpolatedTransform,
+            &mut PlayerScore,
+        )>();
+        for (
+            player_id,
+            mut transform,
+            mut velocity,
+            mut gravity_scale,
+            mut interpolated,
+            mut score,
+        ) in bird_query.iter_mut(world)
+        {
+            let Some(bird) = self.birds.iter().find(|bird| bird.player_id == *player_id) else {
+                continue;
+            };
+            *transform = bird.transform;
+            *velocity = bird.velocity;
+            *gravity_scale = bird.gravity_scale;
+            *interpolated = bird.interpolated.clone();
+            *score = bird.score;
+        }
+
+        let mut spawner_query = world.query::<&mut PipeSpawner>();
+        for (mut spawner, state) in spawner_query.iter_mut(world).zip(self.spawners.iter()) {
+            *spawner = state.clone();
+        }
+
+        // pipes come and go between frames, so they're rebuilt rather than patched
+        let entities = world
+            .query_filtered::<Entity, With<Pipes>>()
+            .iter(world)
+            .collect::<Vec<_>>();
+        for entity in entities {
+            world.entity_mut(entity).despawn_recursive();
+        }
+        let mut queue = CommandQueue::default();
+        let mut respawned = Vec::new();
+        {
+            let mut commands = Commands::new(&mut queue, world);
+            let game_assets = world.resource::<GameAssets>();
+            let pipe_shape = world.resource::<PipeShape>();
+            for state in self.pipes.iter() {
+                let entity = spawn_pipes(
+                    &mut commands,
+                    state.pipes.clone(),
+                    state.motion,
+                    state.transform,
+                    game_assets,
+                    pipe_shape,
+                );
+                commands.entity(entity).insert(state.interpolated.clone());
+                respawned.push(entity);
+            }
+        }
+        queue.apply(world);
+
+        // a bird already in a gap when the snapshot was taken doesn't score it again
+        let mut entities = HashMap::new();
+        for (state, entity) in self.pipes.iter().zip(respawned) {
+            let children = world
+                .get::<Children>(entity)
+                .map_or(&[][..], |children| children);
+            entities.extend(state.children.iter().copied().zip(children.iter().copied()));
+        }
+        *world.resource_mut::<Contacts>() = self.contacts.remapped(&entities);
+
+        **world.resource_mut::<DistanceTraveled>() = self.distance_traveled;
+        **world.resource_mut::<GameScore>() = self.game_score;
+        *world.resource_mut::<GameRng>() = self.game_rng.clone();
+        **world.resource_mut::<RunTick>() = self.run_tick;
+        **world.resource_mut::<RunDeath>() = self.run_death;
+        **world.resource_mut::<DeadPlayer>() = self.dead_player;
+        *world.resource_mut::<DeathTimer>() = self.death_timer.clone();
+    }
+}
+
+// the lobby server a session was matched through
+struct Relay {
+    server: SocketAddr,
+    code: RoomCode,
+    active: bool,
+}
+
+// set while `advance` replays ticks that already ran once, whose sounds and shakes were already had
+#[derive(Resource, Default, Deref, DerefMut)]
+pub struct Resimulating(pub bool);
+
+// the other half of a `--loopback` session, started once the host knows what it's playing with
+#[derive(Resource)]
+struct LoopbackPeer {
+    session: NetSession,
+    script: Option<PathBuf>,
+}
+
+// a two player race against a peer over UDP, rolling back whenever a prediction was wrong
+#[derive(Resource)]
+pub struct NetSession {
+    socket: UdpSocket,
+    peer: SocketAddr,
+    relay: Option<Relay>,
+    player: PlayerId,
+    seed: u64,
+    // taken once the game has loaded, until then there's nothing to tell the peer
+    config: Option<SessionConfig>,
+    // why the peer was turned away, if it was
+    refused: Option<&'static str>,
+    connected: bool,
+    reply_hello: bool,
+    finished: bool,
+    silent_ticks: u32,
+    // next frame to simulate, frame 0 is the first tick spent in `Waiting`
+    frame: u32,
+    local_inputs: Vec<bool>,
+    // only confirmed inputs, anything past the end is predicted as not flapping
+    remote_inputs: Vec<bool>,
+    peer_ack: u32,
+    rollback_frame: Option<u32>,
+    // transitions are held back until the tick that requested them is confirmed
+    pending_state: Option<GameState>,
+    snapshots: Vec<Option<Snapshot>>,
+    rollbacks: u32,
+    // next frame to check, every `CHECK_DISTANCE` frames once its inputs are confirmed
+    checked_frame: u32,
+    local_checks: VecDeque<(CheckedState, u64)>,
+    remote_checks: VecDeque<(u32, u64)>,
+    desync: Option<CheckedState>,
+    remote_desync: Option<CheckedState>,
+    reply_state: bool,
+    desync_reported: bool,
+}
+
+impl NetSession {
+    // the guest's seed is replaced by the host's once they connect
+    pub fn new(
+        socket: UdpSocket,
+        peer: SocketAddr,
+        player: PlayerId,
+        seed: u64,
+    ) -> io::Result<Self> {
+        socket.set_nonblocking(true)?;
+        Ok(Self {
+            socket,
+            peer,
+            relay: None,
+            player,
+            seed,
+            config: None,
+            refused: None,
+            connected: false,
+            reply_hello: false,
+            finished: false,
+            silent_ticks: 0,
+            frame: 0,
+            // nothing can be pressed in time for the first few frames
+            local_inputs: vec![false; INPUT_DELAY],
+            remote_inputs: vec![false; INPUT_DELAY],
+            peer_ack: INPUT_DELAY as u32,
+            rollback_frame: None,
+            pending_state: None,
+            snapshots: vec![None; SNAPSHOT_COUNT],
+            rollbacks: 0,
+            checked_frame: 0,
+            local_checks: VecDeque::new(),
+            remote_checks: VecDeque::new(),
+            desync: None,
+            remote_desync: None,
+            reply_state: false,
+            desync_reported: false,
+        })
+    }
+
+    pub fn bind(
+        address: SocketAddr,
+        peer: SocketAddr,
+        player: PlayerId,
+        seed: u64,
+    ) -> io::Result<Self> {
+        Self::new(UdpSocket::bind(address)?, peer, player, seed)
+    }
+
+    // host and guest sessions on local ports, connected to each other
+    pub fn loopback(seed: u64) -> io::Result<(Self, Self)> {
+        let host_socket = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0))?;
+        let guest_socket = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0))?;
+        let host_address = host_socket.local_addr()?;
+        let guest_address = guest_socket.local_addr()?;
+        Ok((
+            Self::new(host_socket, guest_address, PlayerId(0), seed)?,
+            Self::new(guest_socket, host_address, PlayerId(1), 0)?,
+        ))
+    }
+
+    // packets go through the lobby server when the peer can't be reached directly, or always
+    pub fn with_relay(mut self, server: SocketAddr, code: RoomCode, always: bool) -> Self {
+        self.relay = Some(Relay {
+            server,
+            code,
+            active: always,
+        });
+        self
+    }
+
+    pub fn player(&self) -> PlayerId {
+        self.player
+    }
+
+    pub fn relayed(&self) -> bool {
+        self.relay.as_ref().is_some_and(|relay| relay.active)
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn connected(&self) -> bool {
+        self.connected
+    }
+
+    pub fn refused(&self) -> Option<&'static str> {
+        self.refused
+    }
+
+    pub fn frame(&self) -> u32 {
+        self.frame
+    }
+
+    pub fn rollbacks(&self) -> u32 {
+        self.rollbacks
+    }
+
+    // first frame the peers were found to disagree on
+    pub fn desync_frame(&self) -> Option<u32> {
+        self.desync.as_ref().map(|state| state.frame)
+    }
+
+    fn remote_player(&self) -> PlayerId {
+        PlayerId((*self.player + 1) % NUM_PLAYERS)
+    }
+
+    fn is_host(&self) -> bool {
+        *self.player == 0
+    }
+
+    fn receive(&mut self) {
+        let mut buffer = [0; MAX_PACKET_SIZE];
+        loop {
+            let (len, from) = match self.socket.recv_from(&mut buffer) {
+                Ok(received) => received,
+                Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                // e.g. the peer's port isn't open yet
+                Err(_) => break,
+            };
+            let packet = if from == self.peer {
+                Packet::decode(&buffer[..len])
+            } else if let Some(relay) = self.relay.as_mut().filter(|relay| relay.server == from) {
+                match LobbyMessage::decode(&buffer[..len]) {
+                    Some(LobbyMessage::Relayed { payload }) => {
+                        // answer the way the peer is talking
+                        relay.active = true;
+                        Packet::decode(&payload)
+                    }
+                    _ => None,
+                }
+            } else {
+                continue;
+            };
+            let Some(packet) = packet else {
+                continue;
+            };
+
+            self.silent_ticks = 0;
+            self.handle(packet);
+        }
+    }
+
+    fn handle(&mut self, packet: Packet) {
+        match packet {
+            Packet::Hello {
+                seed,
+                connected,
+                config,
+            } => {
+                let Some(local) = self.config else {
+                    return;
+                };
+                if let Some(mismatch) = local.mismatch(&config) {
+                    if self.refused.is_none() {
+                        error!("refusing to race {}: {mismatch}", self.peer);
+                        self.refused = Some(mismatch);
+                    }
+                    return;
+                }
+                if !self.connected {
+                    if !self.is_host() {
+                        self.seed = seed;
+                        self.config = Some(SessionConfig {
+                            difficulty: config.difficulty,
+                            ..local
+                        });
+                    }
+                    info!("connected to {} as player {}", self.peer, *self.player);
+                    self.connected = true;
+                }
+                self.reply_hello |= !connected;
+            }
+            Packet::Inputs { ack, start, flaps } => {
+                if !self.connected {
+                    return;
+                }
+                self.peer_ack = self.peer_ack.max(ack);
+                for (frame, flap) in (start..).zip(flaps) {
+                    if frame as usize != self.remote_inputs.len() {
+                        continue;
+                    }
+                    self.remote_inputs.push(flap);
+                    // predicted as not flapping when it was simulated
+                    if flap && frame < self.frame {
+                        self.rollback_frame = Some(
+                            self.rollback_frame
+                                .map_or(frame, |rollback| rollback.min(frame)),
+                        );
+                    }
+                }
+            }
+            Packet::Checksum { frame, checksum } => {
+                if self
+                    .remote_checks
+                    .back()
+                    .map_or(true, |(last, _)| *last < frame)
+                {
+                    self.remote_checks.push_back((frame, checksum));
+                    if self.remote_checks.len() > CHECK_HISTORY {
+                        self.remote_checks.pop_front();
+                    }
+                }
+            }
+            Packet::State(remote) => {
+                self.reply_state = true;
+                // both sides settle on the earliest frame either of them caught
+                let Some((local, _)) = self
+                    .local_checks
+                    .iter()
+                    .find(|(local, _)| local.frame == remote.frame)
+                else {
+                    return;
+                };
+                if self
+                    .desync
+                    .as_ref()
+                    .map_or(true, |desync| desync.frame >= remote.frame)
+                {
+                    if self.desync.is_none() {
+                        error!("peers desynced at frame {}", remote.frame);
+                    }
+                    self.desync = Some(local.clone());
+                    self.remote_desync = Some(remote);
+                }
+            }
+        }
+    }
+
+    fn check_frames(&mut self) {
+        while self.checked_frame < self.frame
+            && self.checked_frame as usize <= self.remote_inputs.len()
+        {
+            if let Some(snapshot) = self.snapshot(self.checked_frame) {
+                let state = snapshot.checked_state();
+                let checksum = state.checksum();
+                self.local_checks.push_back((state, checksum));
+                if self.local_checks.len() > CHECK_HISTORY {
+                    self.local_checks.pop_front();
+                }
+            }
+            self.checked_frame += CHECK_DISTANCE as u32;
+        }
+
+        if self.desync.is_some() {
+            return;
+        }
+        let mismatch = self.remote_checks.iter().find_map(|(frame, checksum)| {
+            self.local_checks
+                .iter()
+                .find(|(local, local_checksum)| local.frame == *frame && local_checksum != checksum)
+        });
+        if let Some((local, _)) = mismatch {
+            error!("peers desynced at frame {}", local.frame);
+            self.desync = Some(local.clone());
+        }
+    }
+
+    fn send(&mut self) {
+        let mut packets = Vec::new();
+        if let Some(config) = self.config {
+            if !self.connected || std::mem::take(&mut self.reply_hello) {
+                packets.push(Packet::Hello {
+                    seed: self.seed,
+                    connected: self.connected,
+                    config,
+                });
+            }
+        }
+        if self.connected {
+            let flaps = self
+                .local_inputs
+                .get(self.peer_ack as usize..)
+                .unwrap_or_default();
+            packets.push(Packet::Inputs {
+                ack: self.remote_inputs.len() as u32,
+                start: self.peer_ack,
+                flaps: flaps.iter().copied().take(MAX_PACKET_INPUTS).collect(),
+            });
+            if let Some((state, checksum)) = self.local_checks.back() {
+                packets.push(Packet::Checksum {
+                    frame: state.frame,
+                    checksum: *checksum,
+                });
+            }
+        }
+        if let Some(desync) = &self.desync {
+            // until the peer's side has arrived, or again whenever the peer asks with its own
+            if self.remote_desync.is_none() || std::mem::take(&mut self.reply_state) {
+                packets.push(Packet::State(desync.clone()));
+            }
+        }
+
+        for packet in packets {
+            // lost packets are resent with the next frame anyway
+            let _ = match &self.relay {
+                Some(relay) if relay.active => {
+                    let message = LobbyMessage::Relay {
+                        code: relay.code,
+                        payload: packet.encode(),
+                    };
+                    self.socket.send_to(&message.encode(), relay.server)
+                }
+                _ => self.socket.send_to(&packet.encode(), self.peer),
+            };
+        }
+    }
+
+    fn fall_back_to_relay(&mut self) {
+        if self.connected || self.silent_ticks <= RELAY_FALLBACK {
+            return;
+        }
+        if let Some(relay) = self.relay.as_mut().filter(|relay| !relay.active) {
+            warn!(
+                "can't reach {}, relaying through {}",
+                self.peer, relay.server
+            );
+            relay.active = true;
+        }
+    }
+
+    fn can_simulate(&self) -> bool {
+        let confirmed = self.remote_inputs.len() as u32;
+        self.frame < confirmed + MAX_PREDICTION as u32
+            && (self.pending_state.is_none() || self.frame < confirmed)
+    }
+
+    fn inputs(&self, frame: u32) -> Vec<PlayerId> {
+        let mut flapping = Vec::new();
+        if self.local_inputs[frame as usize] {
+            flapping.push(self.player);
+        }
+        if self.remote_inputs.get(frame as usize) == Some(&true) {
+            flapping.push(self.remote_player());
+        }
+        flapping
+    }
+
+    fn store(&mut self, snapshot: Snapshot) {
+        let index = snapshot.frame as usize % SNAPSHOT_COUNT;
+        self.snapshots[index] = Some(snapshot);
+    }
+
+    fn snapshot(&self, frame: u32) -> Option<&Snapshot> {
+        self.snapshots[frame as usize % SNAPSHOT_COUNT]
+            .as_ref()
+            .filter(|snapshot| snapshot.frame == frame)
+    }
+}
+
+// what a broadcasting game streams to its spectators over TCP, after the magic and version
+#[derive(Debug, Clone, PartialEq)]
+enum SpectatorMessage {
+    // a run entered `Waiting`, everything after it plays out in that run
+    Start {
+        seed: u64,
+        config: Box<ReplayConfig>,
+    },
+    // one `GameTick`, the state it ran in and whether the bird flapped
+    Tick {
+        state: GameState,
+        flap: bool,
+    },
+}
+
+impl SpectatorMessage {
+    fn encode(&self, bytes: &mut Vec<u8>) {
+        match self {
+            SpectatorMessage::Start { seed, config } => {
+                let mut body = seed.to_le_bytes().to_vec();
+                config.encode(&mut body);
+                bytes.push(0);
+                bytes.extend_from_slice(&(body.len() as u32).to_le_bytes());
+                bytes.extend_from_slice(&body);
+            }
+            SpectatorMessage::Tick { state, flap } => {
+                bytes.push(1);
+                bytes.push(state_to_byte(*state));
+                bytes.push(*flap as u8);
+            }
+        }
+    }
+
+    // the message and its size, or `None` until all of it has arrived
+    fn decode(bytes: &[u8]) -> io::Result<Option<(Self, usize)>> {
+        let invalid = |err: String| io::Error::new(ErrorKind::InvalidData, err);
+        let size = match bytes.first() {
+            None => return Ok(None),
+            Some(0) if bytes.len() < START_HEADER_SIZE => return Ok(None),
+            Some(0) => {
+                let len = u32::from_le_bytes(bytes[1..START_HEADER_SIZE].try_into().unwrap());
+                START_HEADER_SIZE + len as usize
+            }
+            Some(1) => TICK_MESSAGE_SIZE,
+            Some(kind) => return Err(invalid(format!("unknown message type {kind}"))),
+        };
+        if bytes.len() < size {
+            return Ok(None);
+        }
+
+        let header = if bytes[0] == 0 { START_HEADER_SIZE } else { 1 };
+        let mut reader = ByteReader(&bytes[header..size]);
+        let message = match bytes[0] {
+            0 => SpectatorMessage::Start {
+                seed: u64::from_le_bytes(reader.take().map_err(|err| invalid(err.to_string()))?),
+                config: Box::new(
+                    ReplayConfig::decode(&mut reader).map_err(|err| invalid(err.to_string()))?,
+                ),
+            },
+            _ => {
+                let [state, flap] = reader.take().map_err(|err| invalid(err.to_string()))?;
+                SpectatorMessage::Tick {
+                    state: state_from_byte(state)
+                        .ok_or(invalid(format!("unknown state {state}")))?,
+                    flap: flap != 0,
+                }
+            }
+        };
+        Ok(Some((message, size)))
+    }
+}
+
+// new states go at the end so the ones already sent keep their numbers
+fn state_to_byte(state: GameState) -> u8 {
+    match state {
+        GameState::Loading => 0,
+        GameState::MainMenu => 1,
+        GameState::Settings => 2,
+        GameState::Waiting => 3,
+        GameState::Playing => 4,
+        GameState::Paused => 5,
+        GameState::Stopped => 6,
+        GameState::Dead => 7,
+        GameState::Lobby => 8,
+    }
+}
+
+fn state_from_byte(byte: u8) -> Option<GameState> {
+    Some(match byte {
+        0 => GameState::Loading,
+        1 => GameState::MainMenu,
+        2 => GameState::Settings,
+        3 => GameState::Waiting,
+        4 => GameState::Playing,
+        5 => GameState::Paused,
+        6 => GameState::Stopped,
+        7 => GameState::Dead,
+        8 => GameState::Lobby,
+        _ => return None,
+    })
+}
+
+struct Watcher {
+    stream: TcpStream,
+    address: SocketAddr,
+    unsent: Vec<u8>,
+}
+
+// streams the local bird's inputs to anyone connecting with `--spectate`
+#[derive(Resource)]
+pub struct Broadcast {
+    listener: TcpListener,
+    watchers: Vec<Watcher>,
+    // everything since the current run started, so spectators can join halfway through
+    run: Vec<u8>,
+}
+
+impl Broadcast {
+    pub fn bind(address: SocketAddr) -> io::Result<Self> {
+        let listener = TcpListener::bind(address)?;
+        listener.set_nonblocking(true)?;
+        Ok(Self {
+            listener,
+            watchers: Vec::new(),
+            run: Vec::new(),
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    pub fn spectators(&self) -> usize {
+        self.watchers.len()
+    }
+
+    fn accept(&mut self) {
+        loop {
+            let (stream, address) = match self.listener.accept() {
+                Ok(accepted) => accepted,
+                Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                Err(err) => {
+                    warn!("failed to accept spectator: {err}");
+                    break;
+                }
+            };
+            if let Err(err) = stream
+                .set_nonblocking(true)
+                .and_then(|_| stream.set_nodelay(true))
+            {
+                warn!("failed to accept spectator {address}: {err}");
+                continue;
+            }
+
+            info!("{address} is spectating");
+            let mut unsent = SPECTATE_MAGIC.to_vec();
+            unsent.push(SPECTATE_VERSION);
+            unsent.push(REPLAY_VERSION);
+            unsent.extend_from_slice(&self.run);
+            self.watchers.push(Watcher {
+                stream,
+                address,
+                unsent,
+            });
+        }
+    }
+
+    fn publish(&mut self, message: SpectatorMessage) {
+        if matches!(message, SpectatorMessage::Start { .. }) {
+            self.run.clear();
+        }
+        let mut bytes = Vec::new();
+        message.encode(&mut bytes);
+        self.run.extend_from_slice(&bytes);
+        for watcher in self.watchers.iter_mut() {
+            watcher.unsent.extend_from_slice(&bytes);
+        }
+    }
+
+    fn flush(&mut self) {
+        self.watchers.retain_mut(|watcher| {
+            while !watcher.unsent.is_empty() {
+                match watcher.stream.write(&watcher.unsent) {
+                    Ok(0) => break,
+                    Ok(len) => {
+                        watcher.unsent.drain(..len);
+                    }
+                    Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                    Err(_) => {
+                        info!("{} stopped spectating", watcher.address);
+                        return false;
+                    }
+                }
+            }
+            true
+        });
+    }
+}
+
+// plays back a broadcast as it arrives, `delay` behind the game being watched
+#[derive(Resource)]
+pub struct Spectator {
+    // gone once the broadcast has ended
+    stream: Option<TcpStream>,
+    received: Vec<u8>,
+    verified: bool,
+    messages: VecDeque<SpectatorMessage>,
+    buffered_ticks: u32,
+    delay: Duration,
+    // transitions requested by the last tick played, local input can't change the state
+    pending_state: Option<GameState>,
+}
+
+impl Spectator {
+    pub fn new(stream: TcpStream, delay: Duration) -> io::Result<Self> {
+        stream.set_nonblocking(true)?;
+        Ok(Self {
+            stream: Some(stream),
+            received: Vec::new(),
+            verified: false,
+            messages: VecDeque::new(),
+            buffered_ticks: 0,
+            delay,
+            pending_state: None,
+        })
+    }
+
+    pub fn connect(address: SocketAddr, delay: Duration) -> io::Result<Self> {
+        Self::new(TcpStream::connect(address)?, delay)
+    }
+
+    pub fn connected(&self) -> bool {
+        self.stream.is_some()
+    }
+
+    // ticks received but not played yet
+    pub fn buffered_ticks(&self) -> u32 {
+        self.buffered_ticks
+    }
+
+    fn receive(&mut self) {
+        let Some(stream) = &mut self.stream else {
+            return;
+        };
+        let mut buffer = [0; MAX_PACKET_SIZE];
+        let result = loop {
+            match stream.read(&mut buffer) {
+                Ok(0) => break Err(None),
+                Ok(len) => self.received.extend_from_slice(&buffer[..len]),
+                Err(err) if err.kind() == ErrorKind::WouldBlock => break Ok(()),
+                Err(err) => break Err(Some(err)),
+            }
+        }
+        .and_then(|_| self.parse().map_err(Some));
+
+        match result {
+            Ok(()) => {}
+            Err(None) => {
+                info!("broadcast ended");
+                self.stream = None;
+            }
+            Err(Some(err)) => {
+                warn!("stopped spectating: {err}");
+                self.stream = None;
+            }
+        }
+    }
+
+    fn parse(&mut self) -> io::Result<()> {
+        if !self.verified {
+            // the start message carries a replay config, so its version has to match as well
+            let header = SPECTATE_MAGIC.len() + 2;
+            if self.received.len() < header {
+                return Ok(());
+            }
+            if self.received[..SPECTATE_MAGIC.len()] != *SPECTATE_MAGIC {
+                return Err(io::Error::new(ErrorKind::InvalidData, "not a broadcast"));
+            }
+            let version = self.received[SPECTATE_MAGIC.len()];
+            if version != SPECTATE_VERSION {
+                return Err(io::Error::new(
+                    ErrorKind::InvalidData,
+                    format!(
+                        "unsupported broadcast version {version} (current is {SPECTATE_VERSION})"
+                    ),
+                ));
+            }
+            let replay_version = self.received[SPECTATE_MAGIC.len() + 1];
+            if replay_version != REPLAY_VERSION {
+                return Err(io::Error::new(
+                    ErrorKind::InvalidData,
+                    format!(
+                        "unsupported run config version {replay_version} (current is {REPLAY_VERSION})"
+                    ),
+                ));
+            }
+            self.received.drain(..header);
+            self.verified = true;
+        }
+
+        let mut parsed = 0;
+        while let Some((message, size)) = SpectatorMessage::decode(&self.received[parsed..])? {
+            if matches!(message, SpectatorMessage::Tick { .. }) {
+                self.buffered_ticks += 1;
+            }
+            self.messages.push_back(message);
+            parsed += size;
+        }
+        self.received.drain(..parsed);
+        Ok(())
+    }
+
+    fn delay_ticks(&self, timestep: Duration) -> u32 {
+        (self.delay.as_secs_f64() / timestep.as_secs_f64()).round() as u32
+    }
+}
+
 pub struct NetworkingPlugin;
 
 impl Plugin for NetworkingPlugin {
-    fn build(&self, app: &mut App) {}
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Resimulating>()
+            .add_systems(Startup, open_session)
+            .add_systems(
+                FixedUpdate,
+                (drive_session, report_desync)
+                    .chain()
+                    .run_if(resource_exists::<NetSession>()),
+            )
+            .add_systems(
+                OnEnter(GameState::Waiting),
+                start_round
+                    .after(reseed)
+                    .after(apply_playback)
+                    .run_if(resource_exists::<NetSession>()),
+            )
+            .add_systems(
+                OnEnter(GameState::Dead),
+                end_round.run_if(resource_exists::<NetSession>()),
+            )
+            .add_systems(
+                OnExit(GameState::Dead),
+                leave_session.run_if(resource_exists::<NetSession>()),
+            )
+            .add_systems(Startup, (open_broadcast, start_spectating))
+            .add_systems(
+                OnEnter(GameState::Waiting),
+                publish_start
+                    .after(reseed)
+                    .after(apply_playback)
+                    .run_if(resource_exists::<Broadcast>())
+                    .run_if(not(resource_exists::<LocalVersus>())),
+            )
+            .add_systems(
+                OnEnter(GameState::MainMenu),
+                clear_broadcast.run_if(resource_exists::<Broadcast>()),
+            )
+            .add_systems(
+                GameTick,
+                publish_tick
+                    .after(gather_inputs)
+                    .before(flap_input)
+                    .in_set(GameSet::Input)
+                    .run_if(resource_exists::<Broadcast>())
+                    .run_if(not(resource_exists::<LocalVersus>()))
+                    .run_if(not(in_state(GameState::Loading))),
+            )
+            .add_systems(
+                FixedUpdate,
+                drive_spectator
+                    .run_if(resource_exists::<Spectator>())
+                    .run_if(not(in_state(GameState::Loading))),
+            )
+            .add_systems(
+                Last,
+                (
+                    serve_spectators.run_if(resource_exists::<Broadcast>()),
+                    hold_spectated_state
+                        .run_if(resource_exists::<Spectator>())
+                        .run_if(not(in_state(GameState::Loading))),
+                ),
+            );
+    }
+}
+
+fn open_session(mut commands: Commands, args: Option<Res<Args>>, session: Option<Res<NetSession>>) {
+    let Some(args) = args else {
+        return;
+    };
+    // e.g. the loopback peer, which is handed its session directly
+    if session.is_some() {
+        return;
+    }
+
+    let seed = args.seed.unwrap_or_else(|| rand::random::<u32>() as u64);
+    let session = if args.loopback {
+        NetSession::loopback(seed).map(|(host, guest)| {
+            commands.insert_resource(LoopbackPeer {
+                session: guest,
+                script: args.script.clone(),
+            });
+            host
+        })
+    } else if let Some(peer) = args.peer {
+        let address = args
+            .bind
+            .unwrap_or((Ipv4Addr::UNSPECIFIED, DEFAULT_PORT).into());
+        NetSession::bind(address, peer, PlayerId(args.player), seed)
+    } else {
+        return;
+    };
+
+    match session {
+        Ok(session) => {
+            info!("waiting for {} as player {}", session.peer, *session.player);
+            commands.insert_resource(session);
+        }
+        Err(err) => error!("failed to start network session: {err}"),
+    }
+}
+
+// the second player is a headless game on another thread, flapping from `--script` with whatever
+// the host loaded
+fn spawn_loopback_peer(
+    peer: LoopbackPeer,
+    tuning: Tuning,
+    patterns: PipePatterns,
+) -> io::Result<()> {
+    let LoopbackPeer { session, script } = peer;
+    let guest_args = Args {
+        headless: true,
+        script,
+        peer: Some(session.peer),
+        player: *session.player,
+        ..default()
+    };
+
+    thread::Builder::new()
+        .name("loopback peer".into())
+        .spawn(move || match headless::app(guest_args) {
+            Ok(mut app) => {
+                app.insert_resource(tuning)
+                    .insert_resource(patterns)
+                    .insert_resource(session)
+                    .run();
+            }
+            Err(err) => error!("failed to start loopback peer: {err}"),
+        })?;
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+fn start_round(
+    mut commands: Commands,
+    mut game_seed: ResMut<GameSeed>,
+    mut game_rng: ResMut<GameRng>,
+    mut local_player: ResMut<LocalPlayer>,
+    mut fixed_time: ResMut<Time<Fixed>>,
+    mut difficulty: ResMut<Difficulty>,
+    mut query: Query<(&PlayerId, &mut TextureAtlasSprite), With<Player>>,
+    session: Res<NetSession>,
+    player_atlas: Res<PlayerAtlas>,
+    player_animations: Res<PlayerAnimations>,
+) {
+    **game_seed = session.seed;
+    *game_rng = GameRng::from_seed(session.seed);
+    **local_player = session.player;
+    fixed_time.set_timestep_hz(FPS as f64);
+    if let Some(config) = session.config {
+        *difficulty = config.difficulty;
+    }
+
+    let tint = |player_id: PlayerId| {
+        if player_id == session.player {
+            Color::WHITE
+        } else {
+            REMOTE_TINT
+        }
+    };
+
+    let mut spawned = [false; NUM_PLAYERS];
+    query.for_each_mut(|(player_id, mut sprite)| {
+        if let Some(spawned) = spawned.get_mut(**player_id) {
+            *spawned = true;
+        }
+        sprite.color = tint(*player_id);
+    });
+
+    for (id, _) in spawned.iter().enumerate().filter(|(_, spawned)| !**spawned) {
+        let player_id = PlayerId(id);
+        let entity = spawn_player(&mut commands, &player_atlas, player_id);
+        commands.entity(entity).insert((
+            TextureAtlasSprite {
+                index: player_atlas.index,
+                color: tint(player_id),
+                ..default()
+            },
+            player_animations.idle.clone(),
+        ));
+    }
+}
+
+fn end_round(mut session: ResMut<NetSession>, dead_player: Res<DeadPlayer>) {
+    session.finished = true;
+    match **dead_player {
+        Some(player_id) if player_id == session.player => {
+            info!("player {} won", *session.remote_player())
+        }
+        Some(_) => info!("player {} won", *session.player),
+        None => {}
+    }
+}
+
+fn leave_session(
+    mut commands: Commands,
+    mut local_player: ResMut<LocalPlayer>,
+    mut fixed_time: ResMut<Time<Fixed>>,
+    mut query: Query<(Entity, &PlayerId, &mut TextureAtlasSprite), With<Player>>,
+    tick_rate: Res<TickRate>,
+) {
+    commands.remove_resource::<NetSession>();
+    **local_player = PlayerId::default();
+    fixed_time.set_timestep_hz(**tick_rate);
+    query.for_each_mut(|(entity, player_id, mut sprite)| {
+        if *player_id == PlayerId::default() {
+            sprite.color = Color::WHITE;
+        } else {
+            commands.entity(entity).despawn_recursive();
+        }
+    });
+}
+
+// runs in place of the usual `GameTick`, so outside of a round the game just ticks along
+fn drive_session(world: &mut World) {
+    let game_state = *world.resource::<State<GameState>>().get();
+    if game_state != GameState::Loading && world.resource::<NetSession>().config.is_none() {
+        let config = SessionConfig::capture(world);
+        world.resource_mut::<NetSession>().config = Some(config);
+        if let Some(peer) = world.remove_resource::<LoopbackPeer>() {
+            let tuning = *world.resource::<Tuning>();
+            let patterns = world.resource::<PipePatterns>().clone();
+            if let Err(err) = spawn_loopback_peer(peer, tuning, patterns) {
+                error!("failed to start loopback peer: {err}");
+            }
+        }
+    }
+
+    world.resource_mut::<NetSession>().receive();
+    let session = world.resource::<NetSession>();
+    let in_round = matches!(
+        game_state,
+        GameState::Waiting | GameState::Playing | GameState::Stopped | GameState::Dead
+    ) && !session.finished;
+
+    if !in_round {
+        if matches!(game_state, GameState::MainMenu | GameState::Lobby) && session.connected {
+            world
+                .resource_mut::<NextState<GameState>>()
+                .set(GameState::Waiting);
+        } else {
+            world.run_schedule(GameTick);
+        }
+    } else if session.connected {
+        advance(world);
+        world.resource_mut::<NetSession>().check_frames();
+    }
+
+    let mut session = world.resource_mut::<NetSession>();
+    session.send();
+    session.silent_ticks += 1;
+    session.fall_back_to_relay();
+    if session.connected && !session.finished && session.silent_ticks > PEER_TIMEOUT {
+        warn!("lost connection to {}", session.peer);
+        session.finished = true;
+    }
+}
+
+fn report_desync(mut session: ResMut<NetSession>, save_path: Res<SavePath>) {
+    if session.desync_reported {
+        return;
+    }
+    let (Some(local), Some(remote)) = (&session.desync, &session.remote_desync) else {
+        return;
+    };
+
+    match write_report(&save_path, session.player, local, remote) {
+        Ok(path) => error!("desync diagnostics written to {path:?}"),
+        Err(err) => warn!("failed to write desync diagnostics: {err}"),
+    }
+    session.desync_reported = true;
+}
+
+// resimulates from the oldest misprediction, then tries to move one frame further
+fn advance(world: &mut World) {
+    let mut session = world.resource_mut::<NetSession>();
+    let simulated = session.frame;
+    let target = session.frame + 1;
+    if let Some(frame) = session.rollback_frame.take() {
+        match session.snapshot(frame).cloned() {
+            Some(snapshot) => {
+                session.frame = frame;
+                session.pending_state = snapshot.pending_state;
+                session.rollbacks += 1;
+                snapshot.restore(world);
+            }
+            None => warn!("can't roll back to frame {frame}, peers may have desynced"),
+        }
+    }
+
+    loop {
+        let session = world.resource::<NetSession>();
+        if session.frame >= target || session.finished || !session.can_simulate() {
+            break;
+        }
+        let resimulating = session.frame < simulated;
+        **world.resource_mut::<Resimulating>() = resimulating;
+        simulate(world);
+    }
+    **world.resource_mut::<Resimulating>() = false;
+}
+
+fn simulate(world: &mut World) {
+    let session = world.resource::<NetSession>();
+    let frame = session.frame;
+    let pending_state = session.pending_state;
+    let sample_input = session.local_inputs.len() <= frame as usize + INPUT_DELAY;
+    let snapshot = Snapshot::capture(world, frame, pending_state);
+
+    let flap = sample_input && std::mem::take(&mut **world.resource_mut::<QueuedFlap>());
+    let mut session = world.resource_mut::<NetSession>();
+    if sample_input {
+        session.local_inputs.push(flap);
+    }
+    session.store(snapshot);
+    session.pending_state = None;
+    let inputs = session.inputs(frame);
+
+    **world.resource_mut::<FlapInputs>() = inputs;
+    world.resource_mut::<NextState<GameState>>().0 = pending_state;
+    world.run_schedule(GameTick);
+
+    let pending_state = world.resource_mut::<NextState<GameState>>().0.take();
+    let mut session = world.resource_mut::<NetSession>();
+    session.pending_state = pending_state;
+    session.frame += 1;
+}
+
+fn open_broadcast(mut commands: Commands, args: Option<Res<Args>>) {
+    let Some(address) = args.and_then(|args| args.broadcast) else {
+        return;
+    };
+    match Broadcast::bind(address) {
+        Ok(broadcast) => {
+            info!("broadcasting on {address}");
+            commands.insert_resource(broadcast);
+        }
+        Err(err) => error!("failed to broadcast on {address}: {err}"),
+    }
+}
+
+fn start_spectating(mut commands: Commands, args: Option<Res<Args>>) {
+    let Some(args) = args else {
+        return;
+    };
+    let Some(address) = args.spectate else {
+        return;
+    };
+    let delay = args
+        .delay
+        .map_or(DEFAULT_SPECTATE_DELAY, Duration::from_secs_f32);
+    match Spectator::connect(address, delay) {
+        Ok(spectator) => {
+            info!("spectating {address} with a {delay:?} delay");
+            commands.insert_resource(spectator);
+        }
+        Err(err) => error!("failed to spectate {address}: {err}"),
+    }
+}
+
+fn publish_start(
+    mut broadcast: ResMut<Broadcast>,
+    game_seed: Res<GameSeed>,
+    game_config: GameConfig,
+) {
+    broadcast.publish(SpectatorMessage::Start {
+        seed: **game_seed,
+        config: Box::new(ReplayConfig::capture(&game_config)),
+    });
+}
+
+// nothing before it matters to someone joining from the menu
+fn clear_broadcast(mut broadcast: ResMut<Broadcast>) {
+    broadcast.run.clear();
+}
+
+fn publish_tick(
+    mut broadcast: ResMut<Broadcast>,
+    game_state: Res<State<GameState>>,
+    flap_inputs: Res<FlapInputs>,
+    local_player: Res<LocalPlayer>,
+) {
+    broadcast.publish(SpectatorMessage::Tick {
+        state: *game_state.get(),
+        flap: flap_inputs.contains(&local_player),
+    });
+}
+
+fn serve_spectators(mut broadcast: ResMut<Broadcast>) {
+    broadcast.accept();
+    broadcast.flush();
+}
+
+// runs in place of the usual `GameTick`, a tick is only played once there's one to play
+fn drive_spectator(world: &mut World) {
+    world.resource_mut::<Spectator>().receive();
+
+    let timestep = world.resource::<Time<Fixed>>().timestep();
+    for played in 0..MAX_CATCH_UP {
+        let spectator = world.resource::<Spectator>();
+        let delay = spectator.delay_ticks(timestep);
+        // once the broadcast is over the rest plays out in real time
+        let ready = spectator.buffered_ticks > delay
+            || (!spectator.connected() && played == 0 && spectator.buffered_ticks > 0);
+        if !ready {
+            break;
+        }
+
+        loop {
+            match world.resource_mut::<Spectator>().messages.pop_front() {
+                Some(SpectatorMessage::Start { seed, config }) => {
+                    world.insert_resource(ReplayPlayback::live(seed, *config));
+                }
+                Some(SpectatorMessage::Tick { state, flap }) => {
+                    world.resource_mut::<Spectator>().buffered_ticks -= 1;
+                    play_tick(world, state, flap);
+                    break;
+                }
+                None => break,
+            }
+        }
+    }
+}
+
+fn play_tick(world: &mut World, state: GameState, flap: bool) {
+    world.resource_mut::<NextState<GameState>>().set(state);
+    **world.resource_mut::<QueuedFlap>() = flap;
+    world.run_schedule(GameTick);
+
+    let pending_state = world.resource::<NextState<GameState>>().0;
+    world.resource_mut::<Spectator>().pending_state = pending_state;
+}
+
+// whatever was clicked or pressed locally is replaced by what the watched game did
+fn hold_spectated_state(mut next_state: ResMut<NextState<GameState>>, spectator: Res<Spectator>) {
+    next_state.0 = spectator.pending_state;
 }
@@ -0,0 +1,264 @@
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext},
+    prelude::*,
+    utils::BoxedFuture,
+};
+use rand::Rng;
+use serde::Deserialize;
+
+use crate::{
+    game::GameState,
+    networking::NetSession,
+    pipes::MotionKind,
+    replay::{ByteReader, ReplayError},
+    seed::{reseed, GameRng},
+    tuning::{TuningError, TuningStatus},
+};
+
+// a hand made run of pairs the spawner plays back to back, read from `assets/patterns`
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct PipePattern {
+    // how likely the pattern is at the start and at the end of the difficulty ramp, next to a
+    // single random pair which always has a weight of 1
+    pub weight: (f32, f32),
+    pub steps: Vec<PatternStep>,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct PatternStep {
+    // 0 puts the gap as low as it can go and 1 as high
+    pub height: f32,
+    // multipliers on top of what the difficulty asks for at that point
+    #[serde(default = "one")]
+    pub spacing: f32,
+    #[serde(default = "one")]
+    pub gap: f32,
+    #[serde(default)]
+    pub motion: Option<MotionKind>,
+}
+
+fn one() -> f32 {
+    1.0
+}
+
+impl PipePattern {
+    pub fn parse(contents: &str) -> Result<Self, TuningError> {
+        let pattern: Self = ron::from_str(contents)?;
+        pattern.validate()?;
+        Ok(pattern)
+    }
+
+    pub fn validate(&self) -> Result<(), TuningError> {
+        let invalid = |reason: String| Err(TuningError::Invalid(reason));
+        let (start, end) = self.weight;
+        if !start.is_finite() || !end.is_finite() || start < 0.0 || end < 0.0 {
+            return invalid(format!("weight can't be negative, got ({start}, {end})"));
+        }
+        if self.steps.is_empty() {
+            return invalid("a pattern needs at least one step".to_string());
+        }
+        for (index, step) in self.steps.iter().enumerate() {
+            if !(0.0..=1.0).contains(&step.height) {
+                return invalid(format!(
+                    "step {index}: height must be between 0 and 1, got {}",
+                    step.height
+                ));
+            }
+            if !step.spacing.is_finite() || step.spacing <= 0.0 {
+                return invalid(format!(
+                    "step {index}: spacing must be positive, got {}",
+                    step.spacing
+                ));
+            }
+            // the tuning only makes sure the widest gap of a curve fits
+            if !(step.gap > 0.0 && step.gap <= 1.0) {
+                return invalid(format!(
+                    "step {index}: gap must be above 0 and at most 1, got {}",
+                    step.gap
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn weight(&self, progress: f32) -> f32 {
+        let (start, end) = self.weight;
+        start + (end - start) * progress
+    }
+}
+
+// every valid pattern file, sorted by path so every game picks from the same list
+#[derive(Resource, Default, Debug, Clone, PartialEq, Deref, DerefMut)]
+pub struct PipePatterns(pub Vec<PipePattern>);
+
+impl PipePatterns {
+    // `None` means a single random pair
+    pub fn pick(&self, rng: &mut GameRng, progress: f32) -> Option<&PipePattern> {
+        if self.is_empty() {
+            return None;
+        }
+        let total = 1.0
+            + self
+                .iter()
+                .map(|pattern| pattern.weight(progress))
+                .sum::<f32>();
+        let mut roll = rng.gen_range(0.0..total);
+        for pattern in self.iter() {
+            roll -= pattern.weight(progress);
+            if roll < 0.0 {
+                return Some(pattern);
+            }
+        }
+        None
+    }
+
+    pub fn encode(&self, bytes: &mut Vec<u8>) {
+        bytes.extend_from_slice(&(self.len() as u16).to_le_bytes());
+        for pattern in self.iter() {
+            bytes.extend_from_slice(&pattern.weight.0.to_le_bytes());
+            bytes.extend_from_slice(&pattern.weight.1.to_le_bytes());
+            bytes.extend_from_slice(&(pattern.steps.len() as u16).to_le_bytes());
+            for step in pattern.steps.iter() {
+                for value in [step.height, step.spacing, step.gap] {
+                    bytes.extend_from_slice(&value.to_le_bytes());
+                }
+                bytes.push(step.motion.map_or(0, |kind| kind.to_byte() + 1));
+            }
+        }
+    }
+
+    pub fn decode(reader: &mut ByteReader) -> Result<Self, ReplayError> {
+        let len = u16::from_le_bytes(reader.take()?);
+        let mut patterns = Vec::new();
+        for _ in 0..len {
+            let weight = (
+                f32::from_le_bytes(reader.take()?),
+                f32::from_le_bytes(reader.take()?),
+            );
+            let len = u16::from_le_bytes(reader.take()?);
+            let mut steps = Vec::new();
+            for _ in 0..len {
+                let mut value = || reader.take().map(f32::from_le_bytes);
+                let (height, spacing, gap) = (value()?, value()?, value()?);
+                let motion = match reader.take::<1>()?[0] {
+                    0 => None,
+                    byte => Some(
+                        MotionKind::from_byte(byte - 1).ok_or(ReplayError::InvalidMotion(byte))?,
+                    ),
+                };
+                steps.push(PatternStep {
+                    height,
+                    spacing,
+                    gap,
+                    motion,
+                });
+            }
+            patterns.push(PipePattern { weight, steps });
+        }
+        Ok(Self(patterns))
+    }
+}
+
+// like the tuning, a broken pattern still loads so it can be reported and fixed in place
+#[derive(Asset, TypePath)]
+pub struct PatternFile(Result<PipePattern, String>);
+
+#[derive(Default)]
+struct PatternLoader;
+
+impl AssetLoader for PatternLoader {
+    type Asset = PatternFile;
+    type Settings = ();
+    type Error = std::io::Error;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<PatternFile, std::io::Error>> {
+        Box::pin(async move {
+            let mut contents = String::new();
+            reader.read_to_string(&mut contents).await?;
+            Ok(PatternFile(
+                PipePattern::parse(&contents).map_err(|err| err.to_string()),
+            ))
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["pattern.ron"]
+    }
+}
+
+// reloaded patterns wait for the next run, the same way the tuning does
+#[derive(Resource, Default, Deref, DerefMut)]
+struct PendingPatterns(Option<PipePatterns>);
+
+pub struct PatternsPlugin;
+
+impl Plugin for PatternsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PipePatterns>()
+            .init_resource::<PendingPatterns>()
+            .init_asset::<PatternFile>()
+            .init_asset_loader::<PatternLoader>()
+            .add_systems(PreUpdate, collect_patterns)
+            .add_systems(OnExit(GameState::Loading), apply_pending_patterns)
+            .add_systems(
+                OnEnter(GameState::Waiting),
+                apply_pending_patterns
+                    .before(reseed)
+                    .run_if(not(resource_exists::<NetSession>())),
+            );
+    }
+}
+
+fn collect_patterns(
+    mut events: EventReader<AssetEvent<PatternFile>>,
+    mut pending: ResMut<PendingPatterns>,
+    patterns: Res<PipePatterns>,
+    mut status: ResMut<TuningStatus>,
+    pattern_files: Res<Assets<PatternFile>>,
+    asset_server: Res<AssetServer>,
+) {
+    if events.read().count() == 0 {
+        return;
+    }
+
+    let mut files = pattern_files
+        .iter()
+        .map(|(id, file)| {
+            let path = asset_server
+                .get_path(id)
+                .map_or_else(|| format!("{id:?}"), |path| path.to_string());
+            (path, file)
+        })
+        .collect::<Vec<_>>();
+    files.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    status.retain(|path, _| !path.ends_with(".pattern.ron"));
+    let mut loaded = Vec::new();
+    for (path, PatternFile(result)) in files {
+        match result {
+            Ok(pattern) => loaded.push(pattern.clone()),
+            Err(err) => {
+                error!("{path}: {err}");
+                status.insert(path, err.clone());
+            }
+        }
+    }
+    **pending = Some(PipePatterns(loaded)).filter(|loaded| *loaded != *patterns);
+}
+
+fn apply_pending_patterns(
+    mut pending: ResMut<PendingPatterns>,
+    mut patterns: ResMut<PipePatterns>,
+) {
+    if let Some(loaded) = pending.take() {
+        info!("{} pipe patterns loaded", loaded.len());
+        *patterns = loaded;
+    }
+}
@@ -0,0 +1,192 @@
+use bevy::{prelude::*, window::WindowFocused};
+
+use crate::{
+    game::GameState,
+    hud::{
+        despawn_screen, label_style, screen_bundle, HudRoot, NumberDisplay, StateButton, UiAtlas,
+        UiSprite,
+    },
+    networking::NetSession,
+};
+
+const COUNTDOWN_SECONDS: f32 = 3.0;
+
+#[derive(Resource, Default)]
+pub struct ResumeCountdown(Option<Timer>);
+
+#[derive(Component)]
+struct PauseButton;
+
+#[derive(Component)]
+struct PauseScreen;
+
+#[derive(Component)]
+struct ResumeButton;
+
+#[derive(Component)]
+struct CountdownDisplay;
+
+pub struct PausePlugin;
+
+impl Plugin for PausePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ResumeCountdown>()
+            .add_systems(
+                OnEnter(GameState::Playing),
+                spawn_pause_button.run_if(not(resource_exists::<NetSession>())),
+            )
+            .add_systems(OnExit(GameState::Playing), despawn_screen::<PauseButton>)
+            .add_systems(OnEnter(GameState::Paused), spawn_pause_screen)
+            .add_systems(
+                OnExit(GameState::Paused),
+                (despawn_screen::<PauseScreen>, reset_countdown),
+            )
+            .add_systems(
+                Update,
+                (
+                    // a network game can't stop for one player
+                    (pause_input, pause_on_focus_loss)
+                        .run_if(in_state(GameState::Playing))
+                        .run_if(not(resource_exists::<NetSession>())),
+                    (resume_input, tick_countdown)
+                        .chain()
+                        .run_if(in_state(GameState::Paused)),
+                ),
+            );
+    }
+}
+
+fn pause_key_pressed(keyboard_input: &Input<KeyCode>) -> bool {
+    keyboard_input.any_just_pressed([KeyCode::Escape, KeyCode::P])
+}
+
+fn spawn_pause_button(
+    mut commands: Commands,
+    root_query: Query<Entity, With<HudRoot>>,
+    ui_atlas: Res<UiAtlas>,
+) {
+    let Ok(root) = root_query.get_single() else {
+        return;
+    };
+
+    commands.entity(root).with_children(|parent| {
+        parent
+            .spawn((
+                NodeBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        left: Val::Px(4.0),
+                        top: Val::Px(4.0),
+                        ..default()
+                    },
+                    ..default()
+                },
+                PauseButton,
+            ))
+            .with_children(|parent| {
+                ui_atlas.spawn_button(
+                    parent,
+                    UiSprite::PauseButton,
+                    StateButton(GameState::Paused),
+                );
+            });
+    });
+}
+
+fn spawn_pause_screen(
+    mut commands: Commands,
+    root_query: Query<Entity, With<HudRoot>>,
+    ui_atlas: Res<UiAtlas>,
+) {
+    let Ok(root) = root_query.get_single() else {
+        return;
+    };
+
+    commands.entity(root).with_children(|parent| {
+        parent
+            .spawn((screen_bundle(), PauseScreen))
+            .with_children(|parent| {
+                parent.spawn(TextBundle::from_section("PAUSED", label_style()));
+                parent.spawn((
+                    NodeBundle {
+                        visibility: Visibility::Hidden,
+                        ..default()
+                    },
+                    NumberDisplay(COUNTDOWN_SECONDS as i32),
+                    CountdownDisplay,
+                ));
+                ui_atlas.spawn_button(parent, UiSprite::ResumeButton, ResumeButton);
+                ui_atlas.spawn_button(
+                    parent,
+                    UiSprite::MenuButton,
+                    StateButton(GameState::MainMenu),
+                );
+            });
+    });
+}
+
+fn pause_input(mut next_state: ResMut<NextState<GameState>>, keyboard_input: Res<Input<KeyCode>>) {
+    if pause_key_pressed(&keyboard_input) {
+        next_state.set(GameState::Paused);
+    }
+}
+
+fn pause_on_focus_loss(
+    mut next_state: ResMut<NextState<GameState>>,
+    mut focus_events: EventReader<WindowFocused>,
+) {
+    for event in focus_events.read() {
+        if !event.focused {
+            next_state.set(GameState::Paused);
+        }
+    }
+}
+
+fn resume_input(
+    mut resume_countdown: ResMut<ResumeCountdown>,
+    mut display_query: Query<&mut Visibility, With<CountdownDisplay>>,
+    button_query: Query<&Interaction, (Changed<Interaction>, With<ResumeButton>)>,
+    keyboard_input: Res<Input<KeyCode>>,
+) {
+    if resume_countdown.0.is_some() {
+        return;
+    }
+
+    let resume_pressed = button_query
+        .iter()
+        .any(|interaction| *interaction == Interaction::Pressed);
+    if resume_pressed || pause_key_pressed(&keyboard_input) {
+        resume_countdown.0 = Some(Timer::from_seconds(COUNTDOWN_SECONDS, TimerMode::Once));
+        display_query.for_each_mut(|mut visibility| {
+            *visibility = Visibility::Inherited;
+        });
+    }
+}
+
+fn tick_countdown(
+    mut resume_countdown: ResMut<ResumeCountdown>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut display_query: Query<&mut NumberDisplay, With<CountdownDisplay>>,
+    time: Res<Time<Real>>,
+) {
+    let Some(timer) = &mut resume_countdown.0 else {
+        return;
+    };
+
+    timer.tick(time.delta());
+    if timer.finished() {
+        next_state.set(GameState::Playing);
+        return;
+    }
+
+    let seconds_left = timer.remaining_secs().ceil() as i32;
+    display_query.for_each_mut(|mut number_display| {
+        if number_display.0 != seconds_left {
+            number_display.0 = seconds_left;
+        }
+    });
+}
+
+fn reset_countdown(mut resume_countdown: ResMut<ResumeCountdown>) {
+    resume_countdown.0 = None;
+}
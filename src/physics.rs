@@ -1,11 +1,39 @@
-use std::ops::{Deref, DerefMut};
+use std::{
+    collections::BTreeMap,
+    ops::{Deref, DerefMut},
+};
 
 use bevy::{
     prelude::*,
     sprite::collide_aabb::{collide, Collision},
+    transform::{
+        systems::{propagate_transforms, sync_simple_transforms},
+        TransformSystem,
+    },
+    utils::HashMap,
+};
+
+use crate::{
+    cli::Args,
+    collider::{contact, Collider, CollisionLayers, Contact, Sensor, Shape},
+    game::GameTick,
 };
 
-#[derive(Resource)]
+pub const DEFAULT_TICK_RATE: f64 = 60.0;
+// most checks a pair that can't be swept as boxes gets per tick
+const MAX_SWEEP_STEPS: u32 = 64;
+
+// what solo runs tick at, picked with `--tick-rate`, network races and replays bring their own
+#[derive(Resource, Clone, Copy, Debug, Deref, DerefMut)]
+pub struct TickRate(pub f64);
+
+impl Default for TickRate {
+    fn default() -> Self {
+        Self(DEFAULT_TICK_RATE)
+    }
+}
+
+#[derive(Resource, Default)]
 pub struct Gravity(Vec2);
 
 impl From<Vec2> for Gravity {
@@ -28,7 +56,7 @@ impl DerefMut for Gravity {
     }
 }
 
-#[derive(Component, Deref, DerefMut)]
+#[derive(Component, Clone, Copy, Deref, DerefMut)]
 pub struct GravityScale(pub f32);
 
 impl Default for GravityScale {
@@ -37,7 +65,7 @@ impl Default for GravityScale {
     }
 }
 
-#[derive(Component, Default, Deref, DerefMut)]
+#[derive(Component, Clone, Copy, Default, Deref, DerefMut)]
 pub struct Velocity(pub Vec2);
 
 impl From<Vec2> for Velocity {
@@ -46,22 +74,145 @@ impl From<Vec2> for Velocity {
     }
 }
 
-#[derive(Component)]
-pub struct Collider {
-    pub size: Vec2,
+// sent every tick two solid colliders touch, in whichever order they were found
+#[derive(Event, Clone, Copy, Debug)]
+pub struct CollisionEvent {
+    pub entity_a: Entity,
+    pub entity_b: Entity,
+    pub collision: Collision,
+    // how far into the tick they first touched, 0 if they were already overlapping when it started
+    pub time_of_impact: f32,
+    // where they touched, and which way and how far `entity_a` has to move to be clear of `entity_b`
+    pub contact: Vec2,
+    pub normal: Vec2,
+    pub depth: f32,
 }
 
-impl From<Vec2> for Collider {
-    fn from(value: Vec2) -> Self {
-        Self { size: value }
+impl CollisionEvent {
+    // the event as `entity` would see it, with `entity` as `entity_a`
+    pub fn seen_from(&self, entity: Entity) -> Option<Self> {
+        if self.entity_a == entity {
+            return Some(*self);
+        }
+        if self.entity_b != entity {
+            return None;
+        }
+        Some(Self {
+            entity_a: self.entity_b,
+            entity_b: self.entity_a,
+            collision: match self.collision {
+                Collision::Inside => Collision::Inside,
+                _ => normal_side(-self.normal),
+            },
+            normal: -self.normal,
+            ..*self
+        })
     }
 }
 
-#[derive(Event)]
-pub struct CollisionEvent {
+// sent once when a pair starts touching, sensors included
+#[derive(Event, Clone, Copy, Debug)]
+pub struct CollisionStarted {
     pub entity_a: Entity,
     pub entity_b: Entity,
-    pub collision: Collision,
+    pub sensor: bool,
+}
+
+// sent once when a pair stops touching, also when either of them was despawned
+#[derive(Event, Clone, Copy, Debug)]
+pub struct CollisionEnded {
+    pub entity_a: Entity,
+    pub entity_b: Entity,
+    pub sensor: bool,
+}
+
+impl CollisionStarted {
+    pub fn other(&self, entity: Entity) -> Option<Entity> {
+        other(self.entity_a, self.entity_b, entity)
+    }
+}
+
+impl CollisionEnded {
+    pub fn other(&self, entity: Entity) -> Option<Entity> {
+        other(self.entity_a, self.entity_b, entity)
+    }
+}
+
+fn other(entity_a: Entity, entity_b: Entity, entity: Entity) -> Option<Entity> {
+    if entity_a == entity {
+        Some(entity_b)
+    } else if entity_b == entity {
+        Some(entity_a)
+    } else {
+        None
+    }
+}
+
+// pairs that touched last tick, lower entity first, and whether either of them is a sensor
+#[derive(Resource, Default, Clone, Debug, Deref, DerefMut)]
+pub struct Contacts(BTreeMap<(Entity, Entity), bool>);
+
+impl Contacts {
+    // the same contacts after some of the entities were respawned under new ids
+    pub fn remapped(&self, entities: &HashMap<Entity, Entity>) -> Self {
+        let remap = |entity: Entity| entities.get(&entity).copied().unwrap_or(entity);
+        Self(
+            self.iter()
+                .map(|(&(a, b), &sensor)| (ordered(remap(a), remap(b)), sensor))
+                .collect(),
+        )
+    }
+}
+
+fn ordered(a: Entity, b: Entity) -> (Entity, Entity) {
+    (a.min(b), a.max(b))
+}
+
+// where every collider was when the tick started, so anything moving fast enough to skip past a thin
+// collider in one tick is still caught. only colliders in here have been through a transform
+// propagation, anything spawned since joins in on the next tick
+#[derive(Resource, Default, Deref, DerefMut)]
+struct StartPositions(HashMap<Entity, Vec2>);
+
+// smooths rendering between fixed ticks, the simulation itself only ever sees `current`
+#[derive(Component, Clone, Default)]
+pub struct InterpolatedTransform {
+    previous: Transform,
+    current: Transform,
+    rendered: Transform,
+}
+
+impl InterpolatedTransform {
+    // for moves that shouldn't be smoothed, even ones that happen to land where the last frame was drawn
+    pub fn teleport(&mut self, transform: &Transform) {
+        self.previous = *transform;
+        self.current = *transform;
+        self.rendered = *transform;
+    }
+
+    // where the simulation has it, rather than where it was last drawn
+    pub fn current(&self) -> &Transform {
+        &self.current
+    }
+
+    // anything that moved the transform outside of the fixed timestep is treated as a teleport
+    fn sync_external(&mut self, transform: &Transform) -> bool {
+        if transform.translation == self.rendered.translation
+            && transform.rotation == self.rendered.rotation
+        {
+            return false;
+        }
+        self.teleport(transform);
+        true
+    }
+}
+
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum PhysicsSet {
+    Restore,
+    Step,
+    Collide,
+    Store,
 }
 
 pub struct PhysicsPlugin;
@@ -69,17 +220,67 @@ pub struct PhysicsPlugin;
 impl Plugin for PhysicsPlugin {
     fn build(&self, app: &mut App) {
         app.add_event::<CollisionEvent>()
-            .insert_resource(Gravity::from(Vec2::new(0.0, -100.0)))
-            .add_systems(PostUpdate, check_collisions)
-            .add_systems(Update, (apply_gravity, apply_velocity));
+            .add_event::<CollisionStarted>()
+            .add_event::<CollisionEnded>()
+            .init_resource::<Gravity>()
+            .init_resource::<Contacts>()
+            .init_resource::<StartPositions>()
+            .init_resource::<TickRate>()
+            .insert_resource(Time::<Fixed>::from_hz(DEFAULT_TICK_RATE))
+            .add_systems(Startup, apply_args)
+            .configure_sets(
+                GameTick,
+                (
+                    PhysicsSet::Restore,
+                    PhysicsSet::Step,
+                    PhysicsSet::Collide,
+                    PhysicsSet::Store,
+                )
+                    .chain(),
+            )
+            .add_systems(
+                GameTick,
+                (
+                    restore_transforms,
+                    sync_simple_transforms,
+                    propagate_transforms,
+                    record_start_positions,
+                )
+                    .chain()
+                    .in_set(PhysicsSet::Restore),
+            )
+            .add_systems(
+                GameTick,
+                (apply_gravity, apply_velocity)
+                    .chain()
+                    .in_set(PhysicsSet::Step),
+            )
+            .add_systems(
+                GameTick,
+                (
+                    sync_simple_transforms,
+                    propagate_transforms,
+                    check_collisions,
+                )
+                    .chain()
+                    .in_set(PhysicsSet::Collide),
+            )
+            .add_systems(GameTick, store_transforms.in_set(PhysicsSet::Store))
+            .add_systems(
+                PostUpdate,
+                interpolate_transforms.before(TransformSystem::TransformPropagate),
+            );
     }
 }
 
-pub struct PhysicsDebugPlugin;
-
-impl Plugin for PhysicsDebugPlugin {
-    fn build(&self, app: &mut App) {
-        app.add_systems(Update, draw_debug);
+fn apply_args(
+    mut tick_rate: ResMut<TickRate>,
+    mut fixed_time: ResMut<Time<Fixed>>,
+    args: Option<Res<Args>>,
+) {
+    if let Some(picked) = args.and_then(|args| args.tick_rate) {
+        **tick_rate = picked;
+        fixed_time.set_timestep_hz(picked);
     }
 }
 
@@ -103,41 +304,277 @@ fn apply_velocity(mut query: Query<(&mut Transform, &Velocity)>, time: Res<Time>
     });
 }
 
-fn check_collisions(
-    mut collision_events: EventWriter<CollisionEvent>,
-    collider_query: Query<(Entity, &Collider, &GlobalTransform)>,
+fn restore_transforms(mut query: Query<(&mut Transform, &mut InterpolatedTransform)>) {
+    query.for_each_mut(|(mut transform, mut interpolated)| {
+        if !interpolated.sync_external(&transform) {
+            transform.translation = interpolated.current.translation;
+            transform.rotation = interpolated.current.rotation;
+        }
+    });
+}
+
+fn store_transforms(mut query: Query<(&Transform, &mut InterpolatedTransform)>) {
+    query.for_each_mut(|(transform, mut interpolated)| {
+        interpolated.previous = interpolated.current;
+        interpolated.current = *transform;
+        interpolated.rendered = *transform;
+    });
+}
+
+fn interpolate_transforms(
+    mut query: Query<(&mut Transform, &mut InterpolatedTransform)>,
+    fixed_time: Res<Time<Fixed>>,
+) {
+    let alpha = fixed_time.overstep_percentage();
+    query.for_each_mut(|(mut transform, mut interpolated)| {
+        if interpolated.sync_external(&transform) {
+            return;
+        }
+        transform.translation = interpolated
+            .previous
+            .translation
+            .lerp(interpolated.current.translation, alpha);
+        transform.rotation = interpolated
+            .previous
+            .rotation
+            .slerp(interpolated.current.rotation, alpha);
+        interpolated.rendered = *transform;
+    });
+}
+
+fn record_start_positions(
+    mut start_positions: ResMut<StartPositions>,
+    query: Query<(Entity, &GlobalTransform), With<Collider>>,
 ) {
-    let colliders = collider_query.iter().collect::<Vec<_>>();
-    for (i, (entity_a, collider_a, transform_a)) in colliders.iter().enumerate() {
-        for (entity_b, collider_b, transform_b) in colliders.iter().skip(i + 1) {
-            // workaround for PostUpdate
-            if transform_a.translation() == Vec3::ZERO || transform_b.translation() == Vec3::ZERO {
-                continue;
+    start_positions.clear();
+    query.for_each(|(entity, transform)| {
+        start_positions.insert(entity, transform.translation().xy());
+    });
+}
+
+// the earliest point in the tick where the boxes touch, both moving in a straight line between
+// their start and end positions, and the side of `b` that was touched
+fn sweep_aabb(
+    a: (Vec2, Vec2),
+    a_size: Vec2,
+    b: (Vec2, Vec2),
+    b_size: Vec2,
+) -> Option<(f32, Collision)> {
+    let half = (a_size + b_size) * 0.5;
+    let offset = a.0 - b.0;
+    let motion = (a.1 - a.0) - (b.1 - b.0);
+
+    let (mut enter, mut exit, mut side) = (0.0f32, 1.0f32, None);
+    for (axis, sides) in [
+        (0, [Collision::Left, Collision::Right]),
+        (1, [Collision::Bottom, Collision::Top]),
+    ] {
+        if motion[axis] == 0.0 {
+            if offset[axis].abs() >= half[axis] {
+                return None;
             }
+            continue;
+        }
+        let near = (-half[axis] - offset[axis]) / motion[axis];
+        let far = (half[axis] - offset[axis]) / motion[axis];
+        if near.min(far) >= enter {
+            enter = near.min(far);
+            side = Some(sides[(motion[axis] < 0.0) as usize]);
+        }
+        exit = exit.min(near.max(far));
+    }
+    if enter >= exit {
+        return None;
+    }
+
+    match side {
+        Some(side) => Some((enter, side)),
+        // already overlapping when the tick started
+        None => collide(a.0.extend(0.0), a_size, b.0.extend(0.0), b_size).map(|side| (0.0, side)),
+    }
+}
 
-            if let Some(collision) = collide(
-                transform_a.translation(),
-                collider_a.size,
-                transform_b.translation(),
-                collider_b.size,
-            ) {
-                collision_events.send(CollisionEvent {
-                    entity_a: *entity_a,
-                    entity_b: *entity_b,
-                    collision,
-                });
+fn side_normal(side: Collision) -> Vec2 {
+    match side {
+        Collision::Left => Vec2::NEG_X,
+        Collision::Right => Vec2::X,
+        Collision::Top => Vec2::Y,
+        Collision::Bottom => Vec2::NEG_Y,
+        Collision::Inside => Vec2::ZERO,
+    }
+}
+
+fn normal_side(normal: Vec2) -> Collision {
+    if normal.x.abs() > normal.y.abs() {
+        if normal.x < 0.0 {
+            Collision::Left
+        } else {
+            Collision::Right
+        }
+    } else if normal.y > 0.0 {
+        Collision::Top
+    } else {
+        Collision::Bottom
+    }
+}
+
+// the bounding boxes are swept first, anything that isn't a box lined up with the axes is then
+// checked at points along the way, close enough together that the smaller shape can't skip a step
+fn sweep(a: &Shape, a_motion: Vec2, b: &Shape, b_motion: Vec2) -> Option<(f32, Contact)> {
+    let (a_bounds, b_bounds) = (a.aabb(), b.aabb());
+    let (enter, side) = sweep_aabb(
+        (a_bounds.center() - a_motion, a_bounds.center()),
+        a_bounds.size(),
+        (b_bounds.center() - b_motion, b_bounds.center()),
+        b_bounds.size(),
+    )?;
+    let at = |t: f32| {
+        (
+            a.translated(-a_motion * (1.0 - t)),
+            b.translated(-b_motion * (1.0 - t)),
+        )
+    };
+
+    if a.is_aabb() && b.is_aabb() {
+        let (a, b) = at(enter);
+        let touching = Contact {
+            point: b.aabb().intersect(a.aabb()).center(),
+            normal: side_normal(side),
+            depth: 0.0,
+        };
+        if enter > 0.0 {
+            return Some((enter, touching));
+        }
+        return Some((0.0, contact(&a, &b).unwrap_or(touching)));
+    }
+
+    let smallest = [a_bounds.half_size(), b_bounds.half_size()]
+        .into_iter()
+        .reduce(Vec2::min)
+        .unwrap()
+        .min_element()
+        .max(0.5);
+    let distance = (a_motion - b_motion).length() * (1.0 - enter);
+    let steps = ((distance / smallest).ceil() as u32).clamp(1, MAX_SWEEP_STEPS);
+    (0..=steps).find_map(|step| {
+        let t = enter + (1.0 - enter) * step as f32 / steps as f32;
+        let (a, b) = at(t);
+        contact(&a, &b).map(|contact| (t, contact))
+    })
+}
+
+// sort and sweep along whichever axis the bounds are spread out more on, every pair whose bounds
+// overlap comes out once with the lower index first, in the order a loop over every pair would find them
+fn broad_phase(bounds: &[Rect]) -> Vec<(usize, usize)> {
+    let spread = |axis: usize| {
+        let (min, max) = bounds
+            .iter()
+            .fold((f32::MAX, f32::MIN), |(min, max), rect| {
+                let center = rect.center()[axis];
+                (min.min(center), max.max(center))
+            });
+        max - min
+    };
+    let (axis, other) = if spread(0) >= spread(1) {
+        (0, 1)
+    } else {
+        (1, 0)
+    };
+
+    let mut order = (0..bounds.len()).collect::<Vec<_>>();
+    order.sort_unstable_by(|&a, &b| bounds[a].min[axis].total_cmp(&bounds[b].min[axis]));
+    let mut active: Vec<usize> = Vec::new();
+    let mut pairs = Vec::new();
+    for i in order {
+        let rect = bounds[i];
+        active.retain(|&j| bounds[j].max[axis] >= rect.min[axis]);
+        for &j in active.iter() {
+            if bounds[j].min[other] <= rect.max[other] && rect.min[other] <= bounds[j].max[other] {
+                pairs.push((i.min(j), i.max(j)));
             }
         }
+        active.push(i);
     }
+    pairs.sort_unstable();
+    pairs
 }
 
-fn draw_debug(mut gizmos: Gizmos, query: Query<(&GlobalTransform, &Collider)>) {
-    query.for_each(|(transform, collider)| {
-        gizmos.rect_2d(
-            transform.translation().xy(),
-            0.0,
-            collider.size,
-            Color::MIDNIGHT_BLUE,
-        );
-    });
+#[allow(clippy::type_complexity)]
+fn check_collisions(
+    mut collision_events: EventWriter<CollisionEvent>,
+    mut started_events: EventWriter<CollisionStarted>,
+    mut ended_events: EventWriter<CollisionEnded>,
+    collider_query: Query<(
+        Entity,
+        &Collider,
+        &GlobalTransform,
+        Option<&CollisionLayers>,
+        Has<Sensor>,
+    )>,
+    start_positions: Res<StartPositions>,
+    mut contacts: ResMut<Contacts>,
+) {
+    let colliders = collider_query
+        .iter()
+        .filter_map(|(entity, collider, transform, layers, sensor)| {
+            let start = start_positions.get(&entity)?;
+            let motion = transform.translation().xy() - *start;
+            let layers = layers.copied().unwrap_or_default();
+            Some((entity, collider.shape(transform), motion, layers, sensor))
+        })
+        .collect::<Vec<_>>();
+    // everything each collider passes through over the tick
+    let bounds = colliders
+        .iter()
+        .map(|(_, shape, motion, _, _)| {
+            let end = shape.aabb();
+            end.union(Rect::from_corners(end.min - *motion, end.max - *motion))
+        })
+        .collect::<Vec<_>>();
+
+    let mut touching = Contacts::default();
+    for (a, b) in broad_phase(&bounds) {
+        let (entity_a, shape_a, motion_a, layers_a, sensor_a) = &colliders[a];
+        let (entity_b, shape_b, motion_b, layers_b, sensor_b) = &colliders[b];
+        if !layers_a.interacts_with(layers_b) {
+            continue;
+        }
+        let Some((time_of_impact, contact)) = sweep(shape_a, *motion_a, shape_b, *motion_b) else {
+            continue;
+        };
+
+        let sensor = *sensor_a || *sensor_b;
+        touching.insert(ordered(*entity_a, *entity_b), sensor);
+        if !sensor {
+            collision_events.send(CollisionEvent {
+                entity_a: *entity_a,
+                entity_b: *entity_b,
+                collision: normal_side(contact.normal),
+                time_of_impact,
+                contact: contact.point,
+                normal: contact.normal,
+                depth: contact.depth,
+            });
+        }
+    }
+
+    for (&(entity_a, entity_b), &sensor) in touching.iter() {
+        if !contacts.contains_key(&(entity_a, entity_b)) {
+            started_events.send(CollisionStarted {
+                entity_a,
+                entity_b,
+                sensor,
+            });
+        }
+    }
+    for (&(entity_a, entity_b), &sensor) in contacts.iter() {
+        if !touching.contains_key(&(entity_a, entity_b)) {
+            ended_events.send(CollisionEnded {
+                entity_a,
+                entity_b,
+                sensor,
+            });
+        }
+    }
+    *contacts = touching;
 }
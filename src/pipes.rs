@@ -1,40 +1,168 @@
+use std::{collections::VecDeque, f32::consts::TAU};
+
 use bevy::prelude::*;
 use rand::Rng;
+use serde::Deserialize;
 
 use crate::{
-    game::{DistanceTraveled, GameAssets, GameBoundaries, GameState},
-    physics::Collider,
+    collider::{Collider, CollisionLayers, Layer, Sensor},
+    difficulty::Difficulty,
+    game::{
+        update_distance, DistanceTraveled, GameAssets, GameBoundaries, GameSet, GameState, GameTick,
+    },
+    patterns::{PatternStep, PipePatterns},
+    physics::InterpolatedTransform,
+    seed::GameRng,
+    tuning::Tuning,
 };
 
+// room kept between a gap and the ground or the top of the game area
+const GAP_MARGIN_BOTTOM: f32 = 14.0;
+const GAP_MARGIN_TOP: f32 = 16.0 * 2.0;
+pub const GAP_MARGIN: f32 = GAP_MARGIN_BOTTOM + GAP_MARGIN_TOP;
+
+const OSCILLATE_AMPLITUDE: f32 = 14.0;
+const OSCILLATE_WAVELENGTH: f32 = 120.0;
+// share of the gap that closes at the narrowest point
+const PULSE_AMOUNT: f32 = 0.35;
+const PULSE_WAVELENGTH: f32 = 90.0;
+const DRIFT_OFFSET: f32 = 32.0;
+// drifting pairs get to their new height by the time they reach the birds
+const DRIFT_LENGTH: f32 = 150.0;
+
 #[derive(Event, Default)]
 pub struct PipeSpawnEvent {
     position: Vec2,
     gap_spacing: f32,
+    motion: Option<PipeMotion>,
 }
 
-#[derive(Component)]
+// pipes are placed at fixed distances along the track so the window size can't change a run
+#[derive(Component, Clone)]
 pub struct PipeSpawner {
-    distance_spacing: f32,
-    next_position: Vec2,
+    lead_distance: f32,
+    next_distance: Option<f32>,
+    // what's left of the pattern being played, random pairs are picked once it's empty
+    pattern: VecDeque<PatternStep>,
 }
 
 impl Default for PipeSpawner {
     fn default() -> Self {
         Self {
-            distance_spacing: 75.0,
-            next_position: Vec2::ZERO,
+            lead_distance: 110.0,
+            next_distance: None,
+            pattern: VecDeque::new(),
         }
     }
 }
 
-#[derive(Component, Default)]
+#[derive(Component, Clone, Default)]
 pub struct Pipes {
-    spawn_position: Vec2,
+    pub spawn_position: Vec2,
+    pub gap_spacing: f32,
+}
+
+// how a pair moves besides scrolling, worked out from where it is on screen so a pair that is
+// respawned after a rollback picks up exactly where it was
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub enum PipeMotion {
+    // the gap bobs up and down
+    Oscillate { amplitude: f32 },
+    // the gap narrows and opens up again
+    Pulse { amount: f32 },
+    // the whole pair slides up or down on its way in
+    Drift { offset: f32 },
+}
+
+impl PipeMotion {
+    // wherever a pair moves to has to be as reachable as where it started
+    fn new(
+        kind: MotionKind,
+        height: &mut f32,
+        lowest: f32,
+        highest: f32,
+        gap_spacing: f32,
+    ) -> Self {
+        match kind {
+            MotionKind::Oscillate => {
+                let amplitude = OSCILLATE_AMPLITUDE.min((highest - lowest) * 0.5);
+                *height = height.clamp(lowest + amplitude, highest - amplitude);
+                PipeMotion::Oscillate { amplitude }
+            }
+            MotionKind::Pulse => PipeMotion::Pulse {
+                amount: gap_spacing * PULSE_AMOUNT,
+            },
+            MotionKind::DriftUp | MotionKind::DriftDown => {
+                let offset = if kind == MotionKind::DriftUp {
+                    DRIFT_OFFSET
+                } else {
+                    -DRIFT_OFFSET
+                };
+                PipeMotion::Drift {
+                    offset: (*height + offset).clamp(lowest, highest) - *height,
+                }
+            }
+        }
+    }
+
+    // height of the gap relative to where it was spawned
+    fn offset(&self, x: f32) -> f32 {
+        match *self {
+            PipeMotion::Oscillate { amplitude } => {
+                amplitude * (TAU * x / OSCILLATE_WAVELENGTH).sin()
+            }
+            PipeMotion::Pulse { .. } => 0.0,
+            PipeMotion::Drift { offset } => offset * (1.0 - x / DRIFT_LENGTH).clamp(0.0, 1.0),
+        }
+    }
+
+    fn gap_spacing(&self, gap_spacing: f32, x: f32) -> f32 {
+        match *self {
+            PipeMotion::Pulse { amount } => {
+                gap_spacing - amount * 0.5 * (1.0 - (TAU * x / PULSE_WAVELENGTH).cos())
+            }
+            _ => gap_spacing,
+        }
+    }
+}
+
+// the kinds of motion a pattern can ask for, sized to fit wherever the pair ends up
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MotionKind {
+    Oscillate,
+    Pulse,
+    DriftUp,
+    DriftDown,
+}
+
+impl MotionKind {
+    pub const ALL: [MotionKind; 4] = [
+        MotionKind::Oscillate,
+        MotionKind::Pulse,
+        MotionKind::DriftUp,
+        MotionKind::DriftDown,
+    ];
+
+    pub fn to_byte(self) -> u8 {
+        Self::ALL.iter().position(|kind| *kind == self).unwrap() as u8
+    }
+
+    pub fn from_byte(byte: u8) -> Option<Self> {
+        Self::ALL.get(byte as usize).copied()
+    }
 }
 
 #[derive(Component)]
 pub struct Pipe;
 
+// pipes collide with the pipe sprite itself rather than its bounds, worked out once since pipes
+// are respawned all the time when rolling back
+#[derive(Resource)]
+pub struct PipeShape {
+    size: Vec2,
+    collider: Collider,
+}
+
 #[derive(Component)]
 pub struct PipeArea;
 
@@ -45,16 +173,33 @@ impl Plugin for PipesPlugin {
         app.add_event::<PipeSpawnEvent>()
             .add_systems(OnExit(GameState::Loading), setup)
             .add_systems(
-                Update,
-                (spawner, handle_spawning, handle_despawning, move_pipes)
+                GameTick,
+                (
+                    spawner,
+                    handle_spawning,
+                    handle_despawning,
+                    move_pipes,
+                    animate_gaps,
+                )
+                    .chain()
+                    .after(update_distance)
+                    .in_set(GameSet::Simulation)
                     .run_if(in_state(GameState::Playing)),
             )
+            .add_systems(OnEnter(GameState::MainMenu), restart)
             .add_systems(OnEnter(GameState::Waiting), restart);
     }
 }
 
-fn setup(mut commands: Commands) {
+fn setup(mut commands: Commands, game_assets: Res<GameAssets>, images: Res<Assets<Image>>) {
     commands.spawn((SpatialBundle::default(), PipeSpawner::default()));
+
+    let image = images.get(&game_assets.pipe_image).unwrap();
+    let size = image.size_f32();
+    commands.insert_resource(PipeShape {
+        size,
+        collider: Collider::mask(image, 128).unwrap_or(Collider::Box { size }),
+    });
 }
 
 fn restart(
@@ -67,31 +212,70 @@ fn restart(
     });
 
     spawner_query.for_each_mut(|mut spawner| {
-        spawner.next_position = Vec2::ZERO;
+        spawner.next_distance = None;
+        spawner.pattern.clear();
     });
 }
 
+#[allow(clippy::too_many_arguments)]
 fn spawner(
     mut event_writer: EventWriter<PipeSpawnEvent>,
     mut query: Query<(&mut PipeSpawner, &mut Transform)>,
+    mut rng: ResMut<GameRng>,
     game_boundaries: Res<GameBoundaries>,
     distance_traveled: Res<DistanceTraveled>,
+    tuning: Res<Tuning>,
+    difficulty: Res<Difficulty>,
+    patterns: Res<PipePatterns>,
 ) {
     query.for_each_mut(|(mut spawner, mut transform)| {
-        if **distance_traveled >= spawner.next_position.x {
-            let mut rng = rand::thread_rng();
-            let spacing = 48.0;
-            transform.translation = Vec3::X * game_boundaries.max.x
-                + Vec3::Y
-                    * rng.gen_range(
-                        game_boundaries.min.y + spacing * 0.5 + 14.0
-                            ..=game_boundaries.max.y - spacing * 0.5 - 16.0 * 2.0,
-                    );
+        let lead_distance = spawner.lead_distance;
+        let next_distance = *spawner
+            .next_distance
+            .get_or_insert(**distance_traveled + lead_distance);
+        if next_distance - **distance_traveled <= game_boundaries.max.x {
+            // each pair is as hard as the point of the track it sits at
+            let level = tuning.level(*difficulty, next_distance);
+            if spawner.pattern.is_empty() {
+                if let Some(pattern) = patterns.pick(&mut rng, level.progress) {
+                    spawner.pattern.extend(pattern.steps.iter().copied());
+                }
+            }
+            let step = spawner.pattern.pop_front();
+
+            let spacing = tuning.gap_spacing * level.gap * step.map_or(1.0, |step| step.gap);
+            let lowest = game_boundaries.min.y + spacing * 0.5 + GAP_MARGIN_BOTTOM;
+            let highest = game_boundaries.max.y - spacing * 0.5 - GAP_MARGIN_TOP;
+            let (mut height, kind) = match step {
+                Some(step) => (lowest + (highest - lowest) * step.height, step.motion),
+                None => {
+                    let middle = (lowest + highest) * 0.5;
+                    let spread = (highest - lowest) * 0.5 * level.gap_variance;
+                    let height = rng.gen_range(middle - spread..=middle + spread);
+                    let kind =
+                        rng.gen_bool(level.moving as f64)
+                            .then(|| match rng.gen_range(0..3) {
+                                0 => MotionKind::Oscillate,
+                                1 => MotionKind::Pulse,
+                                _ if rng.gen_bool(0.5) => MotionKind::DriftUp,
+                                _ => MotionKind::DriftDown,
+                            });
+                    (height, kind)
+                }
+            };
+            let motion =
+                kind.map(|kind| PipeMotion::new(kind, &mut height, lowest, highest, spacing));
+
+            transform.translation =
+                Vec3::X * (next_distance - **distance_traveled) + Vec3::Y * height;
             event_writer.send(PipeSpawnEvent {
                 position: transform.translation.xy(),
                 gap_spacing: spacing,
+                motion,
             });
-            spawner.next_position.x = **distance_traveled + spawner.distance_spacing;
+            let spacing_scale = step.map_or(1.0, |step| step.spacing);
+            spawner.next_distance =
+                Some(next_distance + tuning.pipe_spacing * level.spacing * spacing_scale);
         }
     });
 }
@@ -101,59 +285,83 @@ fn handle_spawning(
     mut event_reader: EventReader<PipeSpawnEvent>,
     distance_traveled: Res<DistanceTraveled>,
     game_assets: Res<GameAssets>,
-    images: Res<Assets<Image>>,
+    pipe_shape: Res<PipeShape>,
 ) {
     for event in event_reader.read() {
-        let image = images.get(game_assets.pipe_image.clone()).unwrap();
-        let pipe_offset = Vec3::Y * (event.gap_spacing * 0.5 + image.size_f32().y * 0.5);
-        commands
-            .spawn((
-                SpatialBundle {
-                    transform: Transform::from_translation(event.position.extend(-10.0)),
+        spawn_pipes(
+            &mut commands,
+            Pipes {
+                spawn_position: event.position + Vec2::X * **distance_traveled,
+                gap_spacing: event.gap_spacing,
+            },
+            event.motion,
+            Transform::from_translation(event.position.extend(-10.0)),
+            &game_assets,
+            &pipe_shape,
+        );
+    }
+}
+
+// also used to respawn pipes when rolling back
+pub fn spawn_pipes(
+    commands: &mut Commands,
+    pipes: Pipes,
+    motion: Option<PipeMotion>,
+    transform: Transform,
+    game_assets: &GameAssets,
+    pipe_shape: &PipeShape,
+) -> Entity {
+    let pipe_offset = Vec3::Y * (pipes.gap_spacing * 0.5 + pipe_shape.size.y * 0.5);
+    let gap_spacing = pipes.gap_spacing;
+    let mut entity = commands.spawn((
+        SpatialBundle {
+            transform,
+            ..default()
+        },
+        pipes,
+        InterpolatedTransform::default(),
+    ));
+    if let Some(motion) = motion {
+        entity.insert(motion);
+    }
+    entity
+        .with_children(|parent| {
+            // bottom pipe
+            parent.spawn((
+                SpriteBundle {
+                    texture: game_assets.pipe_image.clone(),
+                    transform: Transform::from_translation(-pipe_offset),
                     ..default()
                 },
-                Pipes {
-                    spawn_position: event.position + Vec2::X * **distance_traveled,
+                pipe_shape.collider.clone(),
+                CollisionLayers::new([Layer::Pipe], [Layer::Bird]),
+                Pipe,
+            ));
+
+            // top pipe
+            parent.spawn((
+                SpriteBundle {
+                    texture: game_assets.pipe_image.clone(),
+                    transform: Transform::from_scale(Vec3::new(1.0, -1.0, 1.0))
+                        .with_translation(pipe_offset),
                     ..default()
                 },
-            ))
-            .with_children(|parent| {
-                // bottom pipe
-                parent.spawn((
-                    SpriteBundle {
-                        texture: game_assets.pipe_image.clone(),
-                        transform: Transform::from_translation(-pipe_offset),
-                        ..default()
-                    },
-                    Collider {
-                        size: image.size_f32(),
-                    },
-                    Pipe,
-                ));
-
-                // top pipe
-                parent.spawn((
-                    SpriteBundle {
-                        texture: game_assets.pipe_image.clone(),
-                        transform: Transform::from_scale(Vec3::new(1.0, -1.0, 1.0))
-                            .with_translation(pipe_offset),
-                        ..default()
-                    },
-                    Collider {
-                        size: image.size_f32(),
-                    },
-                    Pipe,
-                ));
-
-                parent.spawn((
-                    SpatialBundle::default(),
-                    Collider {
-                        size: Vec2::new(1.0, event.gap_spacing - 1.0),
-                    },
-                    PipeArea,
-                ));
-            });
-    }
+                pipe_shape.collider.clone(),
+                CollisionLayers::new([Layer::Pipe], [Layer::Bird]),
+                Pipe,
+            ));
+
+            parent.spawn((
+                SpatialBundle::default(),
+                Collider::Box {
+                    size: Vec2::new(1.0, gap_spacing - 1.0),
+                },
+                CollisionLayers::new([Layer::Score], [Layer::Bird]),
+                Sensor,
+                PipeArea,
+            ));
+        })
+        .id()
 }
 
 fn handle_despawning(
@@ -169,11 +377,38 @@ fn handle_despawning(
 }
 
 fn move_pipes(
-    mut query: Query<(&mut Transform, &Pipes)>,
+    mut query: Query<(&mut Transform, &Pipes, Option<&PipeMotion>)>,
     distance_traveled: Res<DistanceTraveled>,
 ) {
-    query.for_each_mut(|(mut transform, pipes)| {
+    query.for_each_mut(|(mut transform, pipes, motion)| {
         let distance = pipes.spawn_position.x - **distance_traveled;
         transform.translation.x = distance;
+        if let Some(motion) = motion {
+            transform.translation.y = pipes.spawn_position.y + motion.offset(distance);
+        }
+    });
+}
+
+// gaps that open and close move their pipes apart and resize the area between them
+fn animate_gaps(
+    query: Query<(&Transform, &Pipes, &PipeMotion, &Children)>,
+    mut pipe_query: Query<&mut Transform, (With<Pipe>, Without<Pipes>)>,
+    mut area_query: Query<&mut Collider, With<PipeArea>>,
+    pipe_shape: Res<PipeShape>,
+) {
+    query.for_each(|(transform, pipes, motion, children)| {
+        let gap_spacing = motion.gap_spacing(pipes.gap_spacing, transform.translation.x);
+        let pipe_offset = gap_spacing * 0.5 + pipe_shape.size.y * 0.5;
+        for child in children.iter() {
+            if let Ok(mut pipe_transform) = pipe_query.get_mut(*child) {
+                // the top pipe is the flipped one
+                pipe_transform.translation.y = -pipe_transform.scale.y * pipe_offset;
+            }
+            if let Ok(mut collider) = area_query.get_mut(*child) {
+                if let Collider::Box { size } = &mut *collider {
+                    size.y = gap_spacing - 1.0;
+                }
+            }
+        }
     });
 }
@@ -3,7 +3,7 @@ use std::time::Duration;
 use benimator::{Frame, FrameRate};
 use bevy::{
     audio::{PlaybackMode, VolumeLevel},
-    input::common_conditions::input_just_pressed,
+    input::{common_conditions::input_just_pressed, InputSystem},
     prelude::*,
     sprite::collide_aabb::Collision,
 };
@@ -11,16 +11,88 @@ use bevy_camera_shake::Shake2d;
 
 use crate::{
     animation::{Animation, AnimationState},
-    game::{GameAssets, GameBoundaries, GameScore, GameState},
-    physics::{Collider, CollisionEvent, GravityScale, Velocity},
+    camera::PlayerView,
+    collider::{Collider, CollisionLayers, Layer},
+    game::{GameAssets, GameBoundaries, GameScore, GameSet, GameState, GameTick},
+    networking::{NetSession, Resimulating},
+    physics::{CollisionEvent, CollisionStarted, GravityScale, InterpolatedTransform, Velocity},
     pipes::{Pipe, PipeArea},
+    replay::{ReplayPlayback, RunTick},
+    tuning::Tuning,
 };
 
+// how long a bird hangs in the pipe it hit before falling
+const FALL_DELAY: Duration = Duration::from_secs(1);
+
 #[derive(Component)]
 pub struct Player;
 
+// birds are numbered in spawn order, which every peer of a network session agrees on
+#[derive(Component, Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Deref)]
+pub struct PlayerId(pub usize);
+
+// the bird that the keyboard and camera belong to
+#[derive(Resource, Default, Deref, DerefMut)]
+pub struct LocalPlayer(pub PlayerId);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeathCause {
+    Ground,
+    Pipe,
+}
+
+// what ended the current run, if anything has yet
+#[derive(Resource, Default, Deref, DerefMut)]
+pub struct RunDeath(pub Option<DeathCause>);
+
+// the bird that ended the run, i.e. the loser of a versus race
+#[derive(Resource, Default, Deref, DerefMut)]
+pub struct DeadPlayer(pub Option<PlayerId>);
+
+// birds crash on their own and the round goes on until every one of them is down
+#[derive(Resource, Default, Deref, DerefMut)]
+pub struct LastBirdStanding(pub bool);
+
+// only used while `LastBirdStanding`, otherwise the first crash ends the round
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct Crashed {
+    pub cause: DeathCause,
+    pub tick: u32,
+}
+
+// pipes passed by this bird whenever there's more than one, `GameScore` counts them otherwise
+#[derive(Component, Clone, Copy, Default)]
+pub struct PlayerScore {
+    pub score: i32,
+}
+
+// flaps are read every frame but only applied on the next fixed tick
+#[derive(Resource, Default, Deref, DerefMut)]
+pub struct QueuedFlap(pub bool);
+
+// birds flapping on the current tick, filled from `QueuedFlap` or by a network session
+#[derive(Resource, Default, Deref, DerefMut)]
+pub struct FlapInputs(pub Vec<PlayerId>);
+
+// time between hitting a pipe and falling, part of the simulation so it can be rolled back
+#[derive(Resource, Clone, Deref, DerefMut)]
+pub struct DeathTimer(Timer);
+
+impl Default for DeathTimer {
+    fn default() -> Self {
+        let mut timer = Timer::new(FALL_DELAY, TimerMode::Once);
+        timer.pause();
+        Self(timer)
+    }
+}
+
+// shared with anything else that draws a bird
 #[derive(Resource)]
-pub struct FlapForce(pub f32);
+pub struct PlayerAtlas {
+    pub handle: Handle<TextureAtlas>,
+    pub index: usize,
+    pub size: Vec2,
+}
 
 #[derive(Resource, Default)]
 pub struct PlayerAnimations {
@@ -33,28 +105,57 @@ pub struct PlayerPlugin;
 
 impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut App) {
-        app.insert_resource(FlapForce(150.0))
-            .insert_resource(PlayerAnimations::default())
+        app.insert_resource(PlayerAnimations::default())
+            .init_resource::<LocalPlayer>()
+            .init_resource::<QueuedFlap>()
+            .init_resource::<FlapInputs>()
+            .init_resource::<DeathTimer>()
+            .init_resource::<RunDeath>()
+            .init_resource::<DeadPlayer>()
+            .init_resource::<LastBirdStanding>()
             .add_systems(Startup, setup_animations)
             .add_systems(OnExit(GameState::Loading), setup)
+            .add_systems(OnEnter(GameState::MainMenu), restart)
             .add_systems(OnEnter(GameState::Waiting), restart)
             .add_systems(
-                Update,
+                PreUpdate,
+                queue_flap
+                    .after(InputSystem)
+                    .run_if(not(resource_exists::<ReplayPlayback>()))
+                    .run_if(in_state(GameState::Waiting).or_else(in_state(GameState::Playing))),
+            )
+            .add_systems(
+                GameTick,
                 (
-                    handle_death,
-                    auto_flap.run_if(in_state(GameState::Waiting)),
-                    flap_input
-                        .run_if(can_flap)
-                        .run_if(not(in_state(GameState::Stopped)))
-                        .run_if(not(in_state(GameState::Dead))),
-                    animate_velocity,
-                    trigger_restart
-                        .run_if(in_state(GameState::Dead))
-                        .run_if(input_just_pressed(KeyCode::Space)),
-                    collisions,
+                    auto_flap.run_if(
+                        in_state(GameState::Waiting)
+                            .or_else(in_state(GameState::MainMenu))
+                            .or_else(in_state(GameState::Settings))
+                            .or_else(in_state(GameState::Lobby)),
+                    ),
+                    (
+                        gather_inputs.run_if(not(resource_exists::<NetSession>())),
+                        flap_input,
+                    )
+                        .chain()
+                        .run_if(in_state(GameState::Waiting).or_else(in_state(GameState::Playing))),
                 )
                     .chain()
-                    .run_if(not(in_state(GameState::Loading))),
+                    .in_set(GameSet::Input),
+            )
+            .add_systems(
+                GameTick,
+                (handle_death, animate_velocity, collisions)
+                    .chain()
+                    .in_set(GameSet::Response)
+                    .run_if(not(in_state(GameState::Loading)))
+                    .run_if(not(in_state(GameState::Paused))),
+            )
+            .add_systems(
+                Update,
+                trigger_restart
+                    .run_if(in_state(GameState::Dead))
+                    .run_if(input_just_pressed(KeyCode::Space)),
             );
     }
 }
@@ -114,46 +215,105 @@ fn setup(
     let vendor_handle = game_assets.player_sprite_folder.get(0).unwrap();
     let vendor_index = texture_atlas.get_texture_index(vendor_handle).unwrap();
     let vendor_texture = textures.get(vendor_handle).unwrap();
-    let texture_atlas_handle = texture_atlases.add(texture_atlas);
-
-    commands.spawn((
-        SpriteSheetBundle {
-            sprite: TextureAtlasSprite::new(vendor_index),
-            texture_atlas: texture_atlas_handle,
-            transform: Transform::from_translation(Vec3::Z * 500.0),
-            ..default()
-        },
-        // AnimationIndices { first: 0, last: 2 },
-        // AnimationTimer(Timer::from_seconds(0.1, TimerMode::Repeating)),
-        AnimationState::default(),
-        Velocity::default(),
-        Collider {
-            size: Vec2::new(
-                vendor_texture.size_f32().y * 0.9,
-                vendor_texture.size_f32().y * 0.8,
-            ),
-        },
-        GravityScale::default(),
-        Player,
-    ));
+    let player_atlas = PlayerAtlas {
+        handle: texture_atlases.add(texture_atlas),
+        index: vendor_index,
+        size: vendor_texture.size_f32(),
+    };
+
+    spawn_player(&mut commands, &player_atlas, PlayerId(0));
+    commands.insert_resource(player_atlas);
 }
 
+pub fn spawn_player(
+    commands: &mut Commands,
+    player_atlas: &PlayerAtlas,
+    player_id: PlayerId,
+) -> Entity {
+    commands
+        .spawn((
+            SpriteSheetBundle {
+                sprite: TextureAtlasSprite::new(player_atlas.index),
+                texture_atlas: player_atlas.handle.clone(),
+                transform: Transform::from_translation(Vec3::Z * 500.0),
+                ..default()
+            },
+            // AnimationIndices { first: 0, last: 2 },
+            // AnimationTimer(Timer::from_seconds(0.1, TimerMode::Repeating)),
+            AnimationState::default(),
+            Velocity::default(),
+            // rounded and turning with the sprite, as wide and tall as the old box around the bird
+            Collider::Capsule {
+                half_length: player_atlas.size.y * 0.05,
+                radius: player_atlas.size.y * 0.4,
+            },
+            // birds fly through each other
+            CollisionLayers::new([Layer::Bird], [Layer::Pipe, Layer::Score]),
+            GravityScale::default(),
+            InterpolatedTransform::default(),
+            PlayerScore::default(),
+            Player,
+            player_id,
+        ))
+        .id()
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
 fn handle_death(
     mut commands: Commands,
-    mut query: Query<(&mut Velocity, &mut GravityScale, &Transform), With<Player>>,
-    mut death_timer: Local<Timer>,
+    mut query: Query<
+        (
+            &mut Velocity,
+            &mut GravityScale,
+            &Transform,
+            Option<&Crashed>,
+        ),
+        With<Player>,
+    >,
+    mut death_timer: ResMut<DeathTimer>,
     game_boundaries: Res<GameBoundaries>,
     game_assets: Res<GameAssets>,
     game_state: Res<State<GameState>>,
+    last_bird_standing: Res<LastBirdStanding>,
+    resimulating: Res<Resimulating>,
+    run_tick: Res<RunTick>,
     time: Res<Time>,
 ) {
-    if death_timer.duration() == Duration::from_secs_f32(0.0) {
-        death_timer.set_duration(Duration::from_secs_f32(1.0));
+    // every bird that hit a pipe falls on its own time while the others keep flying
+    if **last_bird_standing {
+        query.for_each_mut(|(mut velocity, mut gravity_scale, transform, crashed)| {
+            let Some(crashed) = crashed else {
+                return;
+            };
+            if crashed.cause != DeathCause::Pipe
+                || **gravity_scale != 0.0
+                || transform.translation.y == game_boundaries.min.y
+            {
+                return;
+            }
+
+            if time.delta() * (**run_tick - crashed.tick) < FALL_DELAY {
+                **velocity = Vec2::ZERO;
+                return;
+            }
+            **gravity_scale = 1.0;
+
+            if !**resimulating {
+                commands.spawn(AudioSourceBundle {
+                    source: game_assets.fall_audio.clone(),
+                    settings: PlaybackSettings {
+                        mode: PlaybackMode::Remove,
+                        volume: bevy::audio::Volume::Absolute(VolumeLevel::new(0.1)),
+                        ..default()
+                    },
+                });
+            }
+        });
+        return;
     }
 
     if *game_state != GameState::Stopped && *game_state != GameState::Dead {
         if !death_timer.paused() {
-            println!("RESET TIMER");
             death_timer.pause();
             death_timer.reset();
         }
@@ -161,10 +321,9 @@ fn handle_death(
     }
 
     if death_timer.paused() || death_timer.percent() == 0.0 {
-        println!("JUST DIED");
         death_timer.unpause();
         if death_timer.percent() == 0.0 {
-            query.for_each_mut(|(mut velocity, mut gravity_scale, _)| {
+            query.for_each_mut(|(mut velocity, mut gravity_scale, _, _)| {
                 **velocity = Vec2::ZERO;
                 **gravity_scale = 0.0;
             });
@@ -175,93 +334,150 @@ fn handle_death(
     death_timer.tick(time.delta());
 
     if death_timer.just_finished() {
-        query.for_each_mut(|(_, mut gravity_scale, transform)| {
+        query.for_each_mut(|(_, mut gravity_scale, transform, _)| {
             if transform.translation.y != game_boundaries.min.y {
                 **gravity_scale = 1.0;
 
-                commands.spawn(AudioSourceBundle {
-                    source: game_assets.fall_audio.clone(),
-                    settings: PlaybackSettings {
-                        mode: PlaybackMode::Remove,
-                        volume: bevy::audio::Volume::Absolute(VolumeLevel::new(0.1)),
-                        ..default()
-                    },
-                });
+                if !**resimulating {
+                    commands.spawn(AudioSourceBundle {
+                        source: game_assets.fall_audio.clone(),
+                        settings: PlaybackSettings {
+                            mode: PlaybackMode::Remove,
+                            volume: bevy::audio::Volume::Absolute(VolumeLevel::new(0.1)),
+                            ..default()
+                        },
+                    });
+                }
             }
         });
     }
 }
 
-fn restart(
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub fn restart(
     mut commands: Commands,
-    mut query: Query<(&mut Transform, &mut GravityScale, Entity), With<Player>>,
+    mut query: Query<
+        (
+            &mut Transform,
+            &mut Velocity,
+            &mut GravityScale,
+            &mut InterpolatedTransform,
+            &mut PlayerScore,
+            Entity,
+        ),
+        With<Player>,
+    >,
+    mut queued_flap: ResMut<QueuedFlap>,
+    mut flap_inputs: ResMut<FlapInputs>,
+    mut death_timer: ResMut<DeathTimer>,
+    mut run_death: ResMut<RunDeath>,
+    mut dead_player: ResMut<DeadPlayer>,
     player_animations: Res<PlayerAnimations>,
 ) {
-    query.for_each_mut(|(mut transform, mut gravity_scale, entity)| {
-        transform.translation.x = 0.0;
-        transform.translation.y = 0.0;
-        transform.rotation = Quat::IDENTITY;
-        **gravity_scale = 1.0;
-
-        commands
-            .entity(entity)
-            .insert(player_animations.flap.clone());
-    });
+    **queued_flap = false;
+    flap_inputs.clear();
+    *death_timer = DeathTimer::default();
+    **run_death = None;
+    **dead_player = None;
+
+    query.for_each_mut(
+        |(mut transform, mut velocity, mut gravity_scale, mut interpolated, mut score, entity)| {
+            **velocity = Vec2::ZERO;
+            transform.translation.x = 0.0;
+            transform.translation.y = 0.0;
+            transform.rotation = Quat::IDENTITY;
+            interpolated.teleport(&transform);
+            **gravity_scale = 1.0;
+            *score = PlayerScore::default();
+
+            commands
+                .entity(entity)
+                .remove::<Crashed>()
+                .insert(player_animations.flap.clone());
+        },
+    );
 }
 
 fn trigger_restart(mut next_state: ResMut<NextState<GameState>>) {
     next_state.set(GameState::Waiting);
 }
 
-fn can_flap(
-    query: Query<(&Transform, &Collider), With<Player>>,
-    game_boundaries: Res<GameBoundaries>,
-) -> bool {
-    for (transform, collider) in query.iter() {
-        if transform.translation.y + collider.size.y * 0.5 > game_boundaries.max.y {
-            return false;
-        }
+fn queue_flap(mut queued_flap: ResMut<QueuedFlap>, keyboard_input: Res<Input<KeyCode>>) {
+    if keyboard_input.just_pressed(KeyCode::Space) {
+        **queued_flap = true;
     }
-    return true;
 }
 
-fn flap_input(
+pub fn gather_inputs(
+    mut queued_flap: ResMut<QueuedFlap>,
+    mut flap_inputs: ResMut<FlapInputs>,
+    local_player: Res<LocalPlayer>,
+) {
+    if std::mem::take(&mut **queued_flap) {
+        flap_inputs.push(**local_player);
+    }
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub fn flap_input(
     mut commands: Commands,
-    mut query: Query<(&mut Velocity, &mut AnimationState), With<Player>>,
+    mut query: Query<
+        (
+            &mut Velocity,
+            &mut AnimationState,
+            &Transform,
+            &Collider,
+            &PlayerId,
+        ),
+        (With<Player>, Without<Crashed>),
+    >,
     mut next_state: ResMut<NextState<GameState>>,
+    mut flap_inputs: ResMut<FlapInputs>,
     game_assets: Res<GameAssets>,
     game_state: Res<State<GameState>>,
-    keyboard_input: Res<Input<KeyCode>>,
-    flap_force: Res<FlapForce>,
+    game_boundaries: Res<GameBoundaries>,
+    resimulating: Res<Resimulating>,
+    tuning: Res<Tuning>,
 ) {
-    query.for_each_mut(|(mut velocity, mut animation_state)| {
-        if keyboard_input.just_pressed(KeyCode::Space) {
-            velocity.y = flap_force.0;
-            animation_state.0.reset();
-
-            commands.spawn(AudioSourceBundle {
-                source: game_assets.flap_audio.clone(),
-                settings: PlaybackSettings {
-                    mode: PlaybackMode::Remove,
-                    volume: bevy::audio::Volume::Absolute(VolumeLevel::new(0.1)),
-                    ..default()
-                },
-            });
+    let flapping = std::mem::take(&mut **flap_inputs);
+    if flapping.is_empty() {
+        return;
+    }
 
-            if *game_state == GameState::Waiting {
-                next_state.set(GameState::Playing);
+    query.for_each_mut(
+        |(mut velocity, mut animation_state, transform, collider, player_id)| {
+            if !flapping.contains(player_id) {
+                return;
             }
-        }
-    });
+
+            // can't flap while above the game area
+            if transform.translation.y + collider.half_size().y <= game_boundaries.max.y {
+                velocity.y = tuning.flap_force;
+                animation_state.0.reset();
+
+                if !**resimulating {
+                    commands.spawn(AudioSourceBundle {
+                        source: game_assets.flap_audio.clone(),
+                        settings: PlaybackSettings {
+                            mode: PlaybackMode::Remove,
+                            volume: bevy::audio::Volume::Absolute(VolumeLevel::new(0.1)),
+                            ..default()
+                        },
+                    });
+                }
+
+                if *game_state == GameState::Waiting {
+                    next_state.set(GameState::Playing);
+                }
+            }
+        },
+    );
 }
 
-fn auto_flap(
-    mut query: Query<(&mut Velocity, &Transform), With<Player>>,
-    flap_force: Res<FlapForce>,
-) {
+fn auto_flap(mut query: Query<(&mut Velocity, &Transform), With<Player>>, tuning: Res<Tuning>) {
     query.for_each_mut(|(mut velocity, transform)| {
         if transform.translation.y < -0.0 && velocity.y < 0.0 {
-            velocity.y = flap_force.0 * 0.5;
+            velocity.y = tuning.flap_force * 0.5;
         }
     });
 }
@@ -271,7 +487,11 @@ fn animate_velocity(
     game_state: Res<State<GameState>>,
     time: Res<Time>,
 ) {
-    if *game_state == GameState::Waiting {
+    if *game_state == GameState::Waiting
+        || *game_state == GameState::MainMenu
+        || *game_state == GameState::Settings
+        || *game_state == GameState::Lobby
+    {
         return;
     }
 
@@ -291,6 +511,7 @@ fn animate_velocity(
     });
 }
 
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
 fn collisions(
     mut commands: Commands,
     mut query: Query<
@@ -298,92 +519,131 @@ fn collisions(
             &mut Transform,
             &mut Velocity,
             &mut GravityScale,
-            &Collider,
+            &mut PlayerScore,
+            &PlayerId,
+            Option<&Crashed>,
             Entity,
         ),
         With<Player>,
     >,
     mut next_state: ResMut<NextState<GameState>>,
-    mut collision_events: EventReader<CollisionEvent>,
+    (mut collision_events, mut started_events): (
+        EventReader<CollisionEvent>,
+        EventReader<CollisionStarted>,
+    ),
     mut game_score: ResMut<GameScore>,
-    mut shake_query: Query<&mut Shake2d>,
-    game_assets: Res<GameAssets>,
-    pipe_query: Query<(&GlobalTransform, &Collider), With<Pipe>>,
+    mut run_death: ResMut<RunDeath>,
+    mut dead_player: ResMut<DeadPlayer>,
+    mut shake_query: Query<(&mut Shake2d, Option<&PlayerView>)>,
+    (game_assets, resimulating): (Res<GameAssets>, Res<Resimulating>),
+    pipe_query: Query<(), With<Pipe>>,
     pipe_area_query: Query<Entity, (With<PipeArea>, Without<Pipe>)>,
     game_state: Res<State<GameState>>,
     game_boundaries: Res<GameBoundaries>,
+    last_bird_standing: Res<LastBirdStanding>,
+    run_tick: Res<RunTick>,
+    time: Res<Time>,
 ) {
+    // every bird has to see every event, not just the first one to read them
+    let collision_events = collision_events.read().collect::<Vec<_>>();
+    let started_events = started_events.read().collect::<Vec<_>>();
+    let racing = query.iter().count() > 1;
     query.for_each_mut(
-        |(mut transform, mut velocity, mut gravity_scale, collider, entity)| {
+        |(
+            mut transform,
+            mut velocity,
+            mut gravity_scale,
+            mut score,
+            player_id,
+            crashed,
+            entity,
+        )| {
+            // only the views following this bird shake, and only the first time a tick runs
+            let mut shake = |trauma: f32| {
+                if **resimulating {
+                    return;
+                }
+                shake_query.for_each_mut(|(mut shake, player_view)| {
+                    if player_view.map_or(true, |player_view| **player_view == *player_id) {
+                        shake.trauma = trauma;
+                    }
+                });
+            };
+            let crash = |commands: &mut Commands, cause: DeathCause| {
+                commands.entity(entity).insert(Crashed {
+                    cause,
+                    tick: **run_tick,
+                });
+            };
+
             // ground collision
-            let mut shake = shake_query.single_mut();
             if transform.translation.y < game_boundaries.min.y {
                 if *game_state != GameState::Dead {
-                    println!("IMPACT VELOCITY: {:?}", **velocity);
                     let velocity_multiplier = ((500.0 - velocity.length()) / 300.0).clamp(0.5, 1.5);
-                    println!("VALUE: {}", velocity_multiplier);
                     **velocity = Vec2::ZERO;
                     **gravity_scale = 0.0;
                     transform.translation.y = game_boundaries.min.y;
 
-                    shake.trauma = (1.0 - (velocity_multiplier - 0.5)) * 0.4;
+                    shake((1.0 - (velocity_multiplier - 0.5)) * 0.4);
                     commands.entity(entity).remove::<Handle<Animation>>();
 
-                    commands.spawn(AudioSourceBundle {
-                        source: game_assets.hit_audio.clone(),
-                        settings: PlaybackSettings {
-                            mode: PlaybackMode::Remove,
-                            volume: bevy::audio::Volume::Absolute(VolumeLevel::new(0.1)),
-                            speed: velocity_multiplier,
-                            ..default()
-                        },
-                    });
+                    if !**resimulating {
+                        commands.spawn(AudioSourceBundle {
+                            source: game_assets.hit_audio.clone(),
+                            settings: PlaybackSettings {
+                                mode: PlaybackMode::Remove,
+                                volume: bevy::audio::Volume::Absolute(VolumeLevel::new(0.1)),
+                                speed: velocity_multiplier,
+                                ..default()
+                            },
+                        });
+                    }
 
-                    next_state.set(GameState::Dead);
+                    run_death.get_or_insert(DeathCause::Ground);
+                    dead_player.get_or_insert(*player_id);
+                    if !**last_bird_standing {
+                        next_state.set(GameState::Dead);
+                    } else if crashed.is_none() {
+                        crash(&mut commands, DeathCause::Ground);
+                    }
                 }
             }
 
-            // pipe collison
-            for event in collision_events.read() {
-                if event.entity_a == entity {
-                    if let Ok((pipe_transform, pipe_collider)) = pipe_query.get(event.entity_b) {
-                        if *game_state != GameState::Stopped && *game_state != GameState::Dead {
-                            shake.trauma = 0.25;
-                            commands.entity(entity).remove::<Handle<Animation>>();
-
-                            commands.spawn(AudioSourceBundle {
-                                source: game_assets.hit_audio.clone(),
-                                settings: PlaybackSettings {
-                                    mode: PlaybackMode::Remove,
-                                    volume: bevy::audio::Volume::Absolute(VolumeLevel::new(0.1)),
-                                    ..default()
-                                },
-                            });
-
-                            next_state.set(GameState::Stopped);
-                        }
+            // crashed birds fall through everything else
+            if crashed.is_some() {
+                return;
+            }
 
-                        let overlap_y = (collider.size.y / 2.0 + pipe_collider.size.y / 2.0)
-                            - (pipe_transform.translation().y - transform.translation.y).abs();
-                        let translation_vector = match event.collision {
-                            Collision::Top => Vec3::new(0.0, overlap_y, 0.0),
-                            Collision::Bottom => Vec3::new(0.0, -overlap_y, 0.0),
-                            Collision::Inside => Vec3::new(0.0, overlap_y, 0.0),
-                            _ => Vec3::ZERO,
-                        };
-                        println!("{:?}", event.collision);
-                        transform.translation += translation_vector;
-                        println!("TRANSLATED: {translation_vector}");
+            // pipe collison
+            for event in collision_events.iter() {
+                let Some(event) = event.seen_from(entity) else {
+                    continue;
+                };
+                if pipe_query.contains(event.entity_b)
+                    && *game_state != GameState::Stopped
+                    && *game_state != GameState::Dead
+                {
+                    // a bird fast enough to go past the lip within a tick is put back where
+                    // it hit, and falls through everything after that
+                    if event.time_of_impact > 0.0 {
+                        let overshoot = **velocity * time.delta_seconds();
+                        transform.translation -=
+                            (overshoot * (1.0 - event.time_of_impact)).extend(0.0);
                     }
+                    let translation_vector = match event.collision {
+                        Collision::Top | Collision::Bottom => {
+                            Vec3::Y * event.normal.y * event.depth
+                        }
+                        _ => Vec3::ZERO,
+                    };
+                    transform.translation += translation_vector;
 
-                    // score collision
-                    if pipe_area_query.contains(event.entity_b) {
-                        **game_score += 1;
-
-                        commands.entity(event.entity_b).despawn();
+                    shake(0.25);
+                    commands.entity(entity).remove::<Handle<Animation>>();
 
+                    if !**resimulating {
                         commands.spawn(AudioSourceBundle {
-                            source: game_assets.point_audio.clone(),
+                            source: game_assets.hit_audio.clone(),
                             settings: PlaybackSettings {
                                 mode: PlaybackMode::Remove,
                                 volume: bevy::audio::Volume::Absolute(VolumeLevel::new(0.1)),
@@ -391,8 +651,69 @@ fn collisions(
                             },
                         });
                     }
+
+                    run_death.get_or_insert(DeathCause::Pipe);
+                    dead_player.get_or_insert(*player_id);
+                    // the others keep flying, so drop out of the course rather than stopping it
+                    if **last_bird_standing {
+                        **velocity = Vec2::ZERO;
+                        **gravity_scale = 0.0;
+                        crash(&mut commands, DeathCause::Pipe);
+                        return;
+                    }
+                    next_state.set(GameState::Stopped);
+                }
+            }
+
+            // score collision, the area stays for every bird and counts once for each of them
+            for event in started_events.iter() {
+                if !event
+                    .other(entity)
+                    .is_some_and(|other| pipe_area_query.contains(other))
+                {
+                    continue;
+                }
+                if racing {
+                    score.score += 1;
+                } else {
+                    **game_score += 1;
+                }
+
+                if !**resimulating {
+                    commands.spawn(AudioSourceBundle {
+                        source: game_assets.point_audio.clone(),
+                        settings: PlaybackSettings {
+                            mode: PlaybackMode::Remove,
+                            volume: bevy::audio::Volume::Absolute(VolumeLevel::new(0.1)),
+                            ..default()
+                        },
+                    });
                 }
             }
         },
     );
+
+    // racing birds pass the same gaps, so the run has passed as many as the one furthest ahead
+    if racing && !**last_bird_standing {
+        let best = query
+            .iter()
+            .map(|(_, _, _, score, _, _, _)| score.score)
+            .max()
+            .unwrap_or_default();
+        **game_score = (**game_score).max(best);
+    }
+
+    // over once every bird has crashed and come to rest on the ground
+    if **last_bird_standing
+        && *game_state == GameState::Playing
+        && query
+            .iter()
+            .all(|(transform, _, gravity_scale, _, _, crashed, _)| {
+                crashed.is_some()
+                    && **gravity_scale == 0.0
+                    && transform.translation.y == game_boundaries.min.y
+            })
+    {
+        next_state.set(GameState::Dead);
+    }
 }
@@ -0,0 +1,114 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    game::{GameScore, GameSet, GameState, GameTick},
+    save::record_run,
+    tuning::Tuning,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Medal {
+    Bronze,
+    Silver,
+    Gold,
+    Platinum,
+}
+
+impl Medal {
+    pub const ALL: [Medal; 4] = [Medal::Bronze, Medal::Silver, Medal::Gold, Medal::Platinum];
+}
+
+// scores a run needs for each medal, part of the tuning
+#[derive(Resource, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct MedalThresholds {
+    pub bronze: i32,
+    pub silver: i32,
+    pub gold: i32,
+    pub platinum: i32,
+}
+
+impl Default for MedalThresholds {
+    fn default() -> Self {
+        Self {
+            bronze: 10,
+            silver: 20,
+            gold: 30,
+            platinum: 40,
+        }
+    }
+}
+
+impl MedalThresholds {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.bronze <= 0 {
+            return Err(format!("bronze must be positive, got {}", self.bronze));
+        }
+        for (lower, higher) in [
+            (("bronze", self.bronze), ("silver", self.silver)),
+            (("silver", self.silver), ("gold", self.gold)),
+            (("gold", self.gold), ("platinum", self.platinum)),
+        ] {
+            if higher.1 < lower.1 {
+                return Err(format!(
+                    "{} can't be below {}, got {} and {}",
+                    higher.0, lower.0, higher.1, lower.1
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn medal_for(&self, score: i32) -> Option<Medal> {
+        Medal::ALL
+            .into_iter()
+            .rev()
+            .find(|medal| score >= self.threshold(*medal))
+    }
+
+    pub fn threshold(&self, medal: Medal) -> i32 {
+        match medal {
+            Medal::Bronze => self.bronze,
+            Medal::Silver => self.silver,
+            Medal::Gold => self.gold,
+            Medal::Platinum => self.platinum,
+        }
+    }
+}
+
+#[derive(Resource, Default, Deref, DerefMut)]
+pub struct RunMedal(pub Option<Medal>);
+
+pub struct RatingPlugin;
+
+impl Plugin for RatingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MedalThresholds>()
+            .init_resource::<RunMedal>()
+            .add_systems(OnEnter(GameState::Waiting), restart)
+            .add_systems(
+                GameTick,
+                apply_thresholds
+                    .run_if(resource_changed::<Tuning>())
+                    .in_set(GameSet::Input),
+            )
+            .add_systems(OnEnter(GameState::Dead), award_medal.before(record_run));
+    }
+}
+
+fn restart(mut run_medal: ResMut<RunMedal>) {
+    **run_medal = None;
+}
+
+fn apply_thresholds(mut medal_thresholds: ResMut<MedalThresholds>, tuning: Res<Tuning>) {
+    *medal_thresholds = tuning.medals;
+}
+
+fn award_medal(
+    mut run_medal: ResMut<RunMedal>,
+    medal_thresholds: Res<MedalThresholds>,
+    game_score: Res<GameScore>,
+) {
+    **run_medal = medal_thresholds.medal_for(**game_score);
+}
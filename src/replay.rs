@@ -0,0 +1,493 @@
+use std::{
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+use bevy::{ecs::system::SystemParam, prelude::*};
+
+use crate::{
+    cli::Args,
+    difficulty::Difficulty,
+    game::{GameScore, GameSet, GameState, GameTick},
+    networking::NetSession,
+    patterns::PipePatterns,
+    player::{gather_inputs, QueuedFlap},
+    save::{record_run, SaveData, SavePath},
+    seed::{reseed, GameRng, GameSeed},
+    tuning::Tuning,
+    versus::LocalVersus,
+};
+
+const REPLAY_MAGIC: &[u8; 4] = b"FBRP";
+pub const REPLAY_VERSION: u8 = 6;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplayAction {
+    Flap,
+}
+
+impl ReplayAction {
+    fn to_byte(self) -> u8 {
+        match self {
+            ReplayAction::Flap => 0,
+        }
+    }
+
+    fn from_byte(byte: u8) -> Result<Self, ReplayError> {
+        match byte {
+            0 => Ok(ReplayAction::Flap),
+            _ => Err(ReplayError::InvalidAction(byte)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReplayInput {
+    pub tick: u32,
+    pub action: ReplayAction,
+}
+
+// everything besides the seed and inputs that changes how a run plays out
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReplayConfig {
+    pub tick_rate: f64,
+    pub tuning: Tuning,
+    pub difficulty: Difficulty,
+    pub patterns: PipePatterns,
+}
+
+#[derive(SystemParam)]
+pub struct GameConfig<'w> {
+    fixed_time: ResMut<'w, Time<Fixed>>,
+    tuning: ResMut<'w, Tuning>,
+    difficulty: ResMut<'w, Difficulty>,
+    patterns: ResMut<'w, PipePatterns>,
+}
+
+impl ReplayConfig {
+    pub fn capture(game_config: &GameConfig) -> Self {
+        Self {
+            tick_rate: 1.0 / game_config.fixed_time.timestep().as_secs_f64(),
+            tuning: *game_config.tuning,
+            difficulty: *game_config.difficulty,
+            patterns: game_config.patterns.clone(),
+        }
+    }
+
+    pub fn apply(&self, game_config: &mut GameConfig) {
+        game_config.fixed_time.set_timestep_hz(self.tick_rate);
+        *game_config.tuning = self.tuning;
+        *game_config.difficulty = self.difficulty;
+        if *game_config.patterns != self.patterns {
+            *game_config.patterns = self.patterns.clone();
+        }
+    }
+
+    pub fn encode(&self, bytes: &mut Vec<u8>) {
+        bytes.extend_from_slice(&self.tick_rate.to_le_bytes());
+        self.tuning.encode(bytes);
+        bytes.push(self.difficulty.to_byte());
+        self.patterns.encode(bytes);
+    }
+
+    pub fn decode(reader: &mut ByteReader) -> Result<Self, ReplayError> {
+        let tick_rate = f64::from_le_bytes(reader.take()?);
+        let tuning = Tuning::decode(reader)?;
+        let byte = reader.take::<1>()?[0];
+        Ok(Self {
+            tick_rate,
+            tuning,
+            difficulty: Difficulty::from_byte(byte).ok_or(ReplayError::InvalidDifficulty(byte))?,
+            patterns: PipePatterns::decode(reader)?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Replay {
+    pub seed: u64,
+    pub config: ReplayConfig,
+    pub score: i32,
+    pub inputs: Vec<ReplayInput>,
+}
+
+#[derive(Debug)]
+pub enum ReplayError {
+    Io(io::Error),
+    InvalidMagic,
+    UnsupportedVersion(u8),
+    InvalidAction(u8),
+    InvalidDifficulty(u8),
+    InvalidMotion(u8),
+    // the tick deltas add up past what a tick can hold
+    InvalidTick,
+    Truncated,
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplayError::Io(err) => write!(f, "io error: {err}"),
+            ReplayError::InvalidMagic => write!(f, "not a replay file"),
+            ReplayError::UnsupportedVersion(version) => {
+                write!(
+                    f,
+                    "unsupported replay version {version} (current is {REPLAY_VERSION})"
+                )
+            }
+            ReplayError::InvalidAction(action) => write!(f, "invalid action {action}"),
+            ReplayError::InvalidDifficulty(difficulty) => {
+                write!(f, "invalid difficulty {difficulty}")
+            }
+            ReplayError::InvalidMotion(motion) => write!(f, "invalid pipe motion {motion}"),
+            ReplayError::InvalidTick => write!(f, "input tick out of range"),
+            ReplayError::Truncated => write!(f, "replay file is truncated"),
+        }
+    }
+}
+
+impl From<io::Error> for ReplayError {
+    fn from(value: io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+impl Replay {
+    pub fn read(path: &Path) -> Result<Self, ReplayError> {
+        Self::decode(&fs::read(path)?)
+    }
+
+    pub fn write(&self, path: &Path) -> Result<(), ReplayError> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, self.encode())?;
+        Ok(())
+    }
+
+    // inputs are stored as varint tick deltas, so a typical run is a few hundred bytes
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(REPLAY_MAGIC);
+        bytes.push(REPLAY_VERSION);
+        bytes.extend_from_slice(&self.seed.to_le_bytes());
+        self.config.encode(&mut bytes);
+        bytes.extend_from_slice(&self.score.to_le_bytes());
+
+        write_varint(&mut bytes, self.inputs.len() as u32);
+        let mut last_tick = 0;
+        for input in self.inputs.iter() {
+            write_varint(&mut bytes, input.tick - last_tick);
+            bytes.push(input.action.to_byte());
+            last_tick = input.tick;
+        }
+        bytes
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Self, ReplayError> {
+        let mut reader = ByteReader(bytes);
+        if reader.take::<4>()? != *REPLAY_MAGIC {
+            return Err(ReplayError::InvalidMagic);
+        }
+        let version = reader.take::<1>()?[0];
+        if version != REPLAY_VERSION {
+            return Err(ReplayError::UnsupportedVersion(version));
+        }
+
+        let seed = u64::from_le_bytes(reader.take()?);
+        let config = ReplayConfig::decode(&mut reader)?;
+        let score = i32::from_le_bytes(reader.take()?);
+
+        let len = reader.varint()?;
+        let mut inputs = Vec::with_capacity(len.min(u16::MAX as u32) as usize);
+        let mut tick = 0u32;
+        for _ in 0..len {
+            tick = tick
+                .checked_add(reader.varint()?)
+                .ok_or(ReplayError::InvalidTick)?;
+            let action = ReplayAction::from_byte(reader.take::<1>()?[0])?;
+            inputs.push(ReplayInput { tick, action });
+        }
+
+        Ok(Self {
+            seed,
+            config,
+            score,
+            inputs,
+        })
+    }
+}
+
+fn write_varint(bytes: &mut Vec<u8>, mut value: u32) {
+    while value >= 0x80 {
+        bytes.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+pub struct ByteReader<'a>(pub &'a [u8]);
+
+impl ByteReader<'_> {
+    pub fn take<const N: usize>(&mut self) -> Result<[u8; N], ReplayError> {
+        if self.0.len() < N {
+            return Err(ReplayError::Truncated);
+        }
+        let (head, tail) = self.0.split_at(N);
+        self.0 = tail;
+        Ok(head.try_into().unwrap())
+    }
+
+    fn varint(&mut self) -> Result<u32, ReplayError> {
+        let mut value = 0u32;
+        for shift in (0..32).step_by(7) {
+            let byte = self.take::<1>()?[0];
+            value |= ((byte & 0x7f) as u32) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(ReplayError::Truncated)
+    }
+}
+
+pub fn replay_dir(save_path: &SavePath) -> Option<PathBuf> {
+    let path = save_path.0.as_ref()?;
+    Some(path.with_file_name("replays"))
+}
+
+// fixed ticks since the current run entered `Waiting`
+#[derive(Resource, Default, Deref, DerefMut)]
+pub struct RunTick(pub u32);
+
+#[derive(Resource, Default)]
+pub struct ReplayRecorder(Option<Replay>);
+
+// while this exists the keyboard is ignored and inputs come from the replay instead
+#[derive(Resource)]
+pub struct ReplayPlayback {
+    seed: u64,
+    inputs: Vec<ReplayInput>,
+    config: Option<ReplayConfig>,
+    expected_score: Option<i32>,
+    next_input: usize,
+    previous_config: Option<ReplayConfig>,
+}
+
+impl ReplayPlayback {
+    pub fn new(replay: Replay) -> Self {
+        Self {
+            config: Some(replay.config),
+            expected_score: Some(replay.score),
+            ..Self::scripted(replay.seed, replay.inputs)
+        }
+    }
+
+    // the inputs are fed in while it plays, e.g. by a spectator
+    pub fn live(seed: u64, config: ReplayConfig) -> Self {
+        Self {
+            config: Some(config),
+            ..Self::scripted(seed, Vec::new())
+        }
+    }
+
+    // hand written inputs, played with whatever config the game currently has
+    pub fn scripted(seed: u64, inputs: Vec<ReplayInput>) -> Self {
+        Self {
+            seed,
+            inputs,
+            config: None,
+            expected_score: None,
+            next_input: 0,
+            previous_config: None,
+        }
+    }
+}
+
+pub struct ReplayPlugin;
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RunTick>()
+            .init_resource::<ReplayRecorder>()
+            .add_systems(Startup, load_playback)
+            .add_systems(
+                OnEnter(GameState::MainMenu),
+                start_playback
+                    .run_if(resource_exists::<ReplayPlayback>())
+                    .run_if(not(resource_exists::<NetSession>())),
+            )
+            .add_systems(
+                OnEnter(GameState::Waiting),
+                (
+                    restart,
+                    apply_playback.run_if(resource_exists::<ReplayPlayback>()),
+                    start_recording.run_if(not(resource_exists::<ReplayPlayback>())),
+                )
+                    .chain()
+                    .after(reseed),
+            )
+            .add_systems(
+                GameTick,
+                (
+                    advance_tick,
+                    record_input.run_if(not(resource_exists::<ReplayPlayback>())),
+                    playback_input.run_if(resource_exists::<ReplayPlayback>()),
+                )
+                    .chain()
+                    .before(gather_inputs)
+                    .in_set(GameSet::Input)
+                    .run_if(in_state(GameState::Waiting).or_else(in_state(GameState::Playing))),
+            )
+            .add_systems(
+                OnEnter(GameState::Dead),
+                (
+                    save_replay
+                        .before(record_run)
+                        .run_if(not(resource_exists::<ReplayPlayback>()))
+                        .run_if(not(resource_exists::<NetSession>()))
+                        .run_if(not(resource_exists::<LocalVersus>())),
+                    finish_playback
+                        .after(record_run)
+                        .run_if(resource_exists::<ReplayPlayback>()),
+                ),
+            );
+    }
+}
+
+fn load_playback(mut commands: Commands, args: Option<Res<Args>>) {
+    let Some(path) = args.and_then(|args| args.replay.clone()) else {
+        return;
+    };
+
+    match Replay::read(&path) {
+        Ok(replay) => {
+            info!("playing back {path:?} with seed {}", replay.seed);
+            commands.insert_resource(ReplayPlayback::new(replay));
+        }
+        Err(err) => error!("failed to read replay {path:?}: {err}"),
+    }
+}
+
+fn start_playback(mut next_state: ResMut<NextState<GameState>>) {
+    next_state.set(GameState::Waiting);
+}
+
+fn restart(mut run_tick: ResMut<RunTick>) {
+    **run_tick = 0;
+}
+
+pub fn apply_playback(
+    mut playback: ResMut<ReplayPlayback>,
+    mut game_seed: ResMut<GameSeed>,
+    mut game_rng: ResMut<GameRng>,
+    mut game_config: GameConfig,
+) {
+    playback.next_input = 0;
+    if let Some(config) = playback.config.clone() {
+        if playback.previous_config.is_none() {
+            playback.previous_config = Some(ReplayConfig::capture(&game_config));
+        }
+        config.apply(&mut game_config);
+    }
+
+    **game_seed = playback.seed;
+    *game_rng = GameRng::from_seed(**game_seed);
+}
+
+fn start_recording(
+    mut recorder: ResMut<ReplayRecorder>,
+    game_seed: Res<GameSeed>,
+    game_config: GameConfig,
+) {
+    recorder.0 = Some(Replay {
+        seed: **game_seed,
+        config: ReplayConfig::capture(&game_config),
+        score: 0,
+        inputs: Vec::new(),
+    });
+}
+
+fn advance_tick(mut run_tick: ResMut<RunTick>) {
+    **run_tick += 1;
+}
+
+fn record_input(
+    mut recorder: ResMut<ReplayRecorder>,
+    queued_flap: Res<QueuedFlap>,
+    run_tick: Res<RunTick>,
+) {
+    let Some(replay) = &mut recorder.0 else {
+        return;
+    };
+
+    if **queued_flap {
+        replay.inputs.push(ReplayInput {
+            tick: **run_tick,
+            action: ReplayAction::Flap,
+        });
+    }
+}
+
+fn playback_input(
+    mut playback: ResMut<ReplayPlayback>,
+    mut queued_flap: ResMut<QueuedFlap>,
+    run_tick: Res<RunTick>,
+) {
+    while let Some(input) = playback.inputs.get(playback.next_input).copied() {
+        if input.tick > **run_tick {
+            break;
+        }
+
+        match input.action {
+            ReplayAction::Flap => **queued_flap = true,
+        }
+        playback.next_input += 1;
+    }
+}
+
+fn save_replay(
+    mut recorder: ResMut<ReplayRecorder>,
+    save_path: Res<SavePath>,
+    save_data: Res<SaveData>,
+    game_score: Res<GameScore>,
+) {
+    let Some(mut replay) = recorder.0.take() else {
+        return;
+    };
+    let Some(dir) = replay_dir(&save_path) else {
+        return;
+    };
+
+    replay.score = **game_score;
+    let mut paths = vec![dir.join("last.replay")];
+    if replay.score > save_data.best_score {
+        paths.push(dir.join("best.replay"));
+    }
+
+    for path in paths {
+        if let Err(err) = replay.write(&path) {
+            warn!("failed to write replay {path:?}: {err}");
+        }
+    }
+}
+
+fn finish_playback(
+    mut commands: Commands,
+    mut game_config: GameConfig,
+    playback: Res<ReplayPlayback>,
+    game_score: Res<GameScore>,
+) {
+    match playback.expected_score {
+        Some(score) if score != **game_score => warn!(
+            "replay diverged, finished with score {} instead of {score}",
+            **game_score
+        ),
+        _ => info!("replay finished with score {}", **game_score),
+    }
+
+    if let Some(config) = &playback.previous_config {
+        config.apply(&mut game_config);
+    }
+    commands.remove_resource::<ReplayPlayback>();
+}
@@ -0,0 +1,294 @@
+use std::{
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+use bevy::prelude::*;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{
+    game::{DistanceTraveled, GameScore, GameState},
+    networking::NetSession,
+    rating::{Medal, RunMedal},
+    replay::ReplayPlayback,
+    seed::GameSeed,
+    versus::LocalVersus,
+};
+
+const SAVE_VERSION: u32 = 2;
+const MAX_RUN_HISTORY: usize = 100;
+
+#[derive(Resource, Serialize, Deserialize, Clone, Debug, Default)]
+pub struct SaveData {
+    pub best_score: i32,
+    pub runs: Vec<RunRecord>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RunRecord {
+    pub score: i32,
+    pub distance: f32,
+    pub duration: f32,
+    pub seed: Option<u64>,
+    pub medal: Option<Medal>,
+}
+
+// layouts of older save versions, each one migrates to the next with a `From` so a file of any
+// version can be brought up to date one step at a time
+mod v1 {
+    use serde::Deserialize;
+
+    #[derive(Deserialize)]
+    pub struct SaveData {
+        pub best_score: i32,
+        pub runs: Vec<RunRecord>,
+    }
+
+    #[derive(Deserialize)]
+    pub struct RunRecord {
+        pub score: i32,
+        pub distance: f32,
+        pub duration: f32,
+        pub seed: Option<u64>,
+    }
+}
+
+impl From<v1::SaveData> for SaveData {
+    fn from(value: v1::SaveData) -> Self {
+        Self {
+            best_score: value.best_score,
+            runs: value
+                .runs
+                .into_iter()
+                .map(|run| RunRecord {
+                    score: run.score,
+                    distance: run.distance,
+                    duration: run.duration,
+                    seed: run.seed,
+                    medal: None,
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Resource)]
+pub struct SavePath(pub Option<PathBuf>);
+
+impl Default for SavePath {
+    fn default() -> Self {
+        Self(data_dir().map(|dir| dir.join("save.ron")))
+    }
+}
+
+#[derive(Resource, Default, Deref, DerefMut)]
+pub struct RunDuration(f32);
+
+#[derive(Serialize, Deserialize)]
+struct SaveFile<T> {
+    version: u32,
+    data: T,
+}
+
+#[derive(Deserialize)]
+struct SaveHeader {
+    version: u32,
+}
+
+#[derive(Debug)]
+pub enum SaveError {
+    Io(io::Error),
+    Parse(ron::error::SpannedError),
+    Serialize(ron::Error),
+    UnsupportedVersion(u32),
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SaveError::Io(err) => write!(f, "io error: {err}"),
+            SaveError::Parse(err) => write!(f, "parse error: {err}"),
+            SaveError::Serialize(err) => write!(f, "serialize error: {err}"),
+            SaveError::UnsupportedVersion(version) => {
+                write!(
+                    f,
+                    "unsupported save version {version} (current is {SAVE_VERSION})"
+                )
+            }
+        }
+    }
+}
+
+impl From<io::Error> for SaveError {
+    fn from(value: io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+impl From<ron::error::SpannedError> for SaveError {
+    fn from(value: ron::error::SpannedError) -> Self {
+        Self::Parse(value)
+    }
+}
+
+impl From<ron::Error> for SaveError {
+    fn from(value: ron::Error) -> Self {
+        Self::Serialize(value)
+    }
+}
+
+pub struct SavePlugin;
+
+impl Plugin for SavePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SavePath>()
+            .init_resource::<SaveData>()
+            .init_resource::<RunDuration>()
+            .add_systems(Startup, load)
+            .add_systems(OnEnter(GameState::Waiting), restart)
+            .add_systems(
+                OnEnter(GameState::Dead),
+                record_run
+                    .run_if(not(resource_exists::<ReplayPlayback>()))
+                    .run_if(not(resource_exists::<NetSession>()))
+                    .run_if(not(resource_exists::<LocalVersus>())),
+            )
+            .add_systems(
+                Update,
+                update_duration
+                    .run_if(in_state(GameState::Playing).or_else(in_state(GameState::Stopped))),
+            );
+    }
+}
+
+pub fn data_dir() -> Option<PathBuf> {
+    let base = if cfg!(target_os = "windows") {
+        std::env::var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        std::env::var_os("HOME").map(|home| PathBuf::from(home).join("Library/Application Support"))
+    } else {
+        std::env::var_os("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .or_else(|| {
+                std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share"))
+            })
+    };
+    base.map(|dir| dir.join("flappy_bird"))
+}
+
+impl SaveData {
+    pub fn read(path: &Path) -> Result<Self, SaveError> {
+        let contents = fs::read_to_string(path)?;
+        let header: SaveHeader = ron::from_str(&contents)?;
+        match header.version {
+            1 => Ok(parse::<v1::SaveData>(&contents)?.into()),
+            SAVE_VERSION => parse(&contents),
+            version => Err(SaveError::UnsupportedVersion(version)),
+        }
+    }
+
+    pub fn write(&self, path: &Path) -> Result<(), SaveError> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let file = SaveFile {
+            version: SAVE_VERSION,
+            data: self,
+        };
+        let contents = ron::ser::to_string_pretty(&file, ron::ser::PrettyConfig::default())?;
+
+        // write next to the save first so a crash mid-write never truncates it
+        let temp_path = path.with_extension("ron.tmp");
+        fs::write(&temp_path, contents)?;
+        if path.exists() {
+            fs::rename(path, backup_path(path))?;
+        }
+        fs::rename(temp_path, path)?;
+        Ok(())
+    }
+
+    pub fn push_run(&mut self, run: RunRecord) {
+        self.best_score = self.best_score.max(run.score);
+        self.runs.push(run);
+        if self.runs.len() > MAX_RUN_HISTORY {
+            let excess = self.runs.len() - MAX_RUN_HISTORY;
+            self.runs.drain(..excess);
+        }
+    }
+}
+
+fn parse<T: DeserializeOwned>(contents: &str) -> Result<T, SaveError> {
+    Ok(ron::from_str::<SaveFile<T>>(contents)?.data)
+}
+
+fn backup_path(path: &Path) -> PathBuf {
+    path.with_extension("ron.bak")
+}
+
+fn load(mut save_path: ResMut<SavePath>, mut save_data: ResMut<SaveData>) {
+    let Some(path) = save_path.0.clone() else {
+        return;
+    };
+    if !path.exists() && !backup_path(&path).exists() {
+        return;
+    }
+
+    match SaveData::read(&path) {
+        Ok(data) => *save_data = data,
+        Err(SaveError::UnsupportedVersion(version)) => {
+            // written by a newer build, leave it untouched
+            warn!("save file {path:?} has unsupported version {version}, saving disabled");
+            save_path.0 = None;
+        }
+        Err(err) => {
+            warn!("failed to load save file {path:?}: {err}");
+            if path.exists() {
+                let corrupt_path = path.with_extension("ron.corrupt");
+                if let Err(err) = fs::rename(&path, &corrupt_path) {
+                    warn!("failed to move corrupt save file aside: {err}");
+                }
+            }
+
+            match SaveData::read(&backup_path(&path)) {
+                Ok(data) => {
+                    info!("recovered save data from backup");
+                    *save_data = data;
+                }
+                Err(err) => warn!("no usable backup save: {err}"),
+            }
+        }
+    }
+}
+
+fn restart(mut run_duration: ResMut<RunDuration>) {
+    **run_duration = 0.0;
+}
+
+fn update_duration(mut run_duration: ResMut<RunDuration>, time: Res<Time>) {
+    **run_duration += time.delta_seconds();
+}
+
+pub fn record_run(
+    mut save_data: ResMut<SaveData>,
+    save_path: Res<SavePath>,
+    game_score: Res<GameScore>,
+    distance_traveled: Res<DistanceTraveled>,
+    run_duration: Res<RunDuration>,
+    run_medal: Res<RunMedal>,
+    game_seed: Res<GameSeed>,
+) {
+    save_data.push_run(RunRecord {
+        score: **game_score,
+        distance: **distance_traveled,
+        duration: **run_duration,
+        seed: Some(**game_seed),
+        medal: **run_medal,
+    });
+
+    if let Some(path) = &save_path.0 {
+        if let Err(err) = save_data.write(path) {
+            warn!("failed to write save file {path:?}: {err}");
+        }
+    }
+}
@@ -0,0 +1,54 @@
+use bevy::prelude::*;
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+
+use crate::{cli::Args, game::GameState, menu::GameSettings};
+
+// seed of the current run, either picked in the settings or rolled when the run starts
+#[derive(Resource, Default, Deref, DerefMut)]
+pub struct GameSeed(pub u64);
+
+// all gameplay randomness has to come from here to keep runs reproducible
+#[derive(Resource, Clone, Deref, DerefMut)]
+pub struct GameRng(ChaCha8Rng);
+
+impl GameRng {
+    pub fn from_seed(seed: u64) -> Self {
+        Self(ChaCha8Rng::seed_from_u64(seed))
+    }
+}
+
+impl Default for GameRng {
+    fn default() -> Self {
+        Self::from_seed(0)
+    }
+}
+
+pub struct SeedPlugin;
+
+impl Plugin for SeedPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GameSeed>()
+            .init_resource::<GameRng>()
+            .add_systems(Startup, apply_args)
+            .add_systems(OnEnter(GameState::Waiting), reseed);
+    }
+}
+
+fn apply_args(mut game_settings: ResMut<GameSettings>, args: Option<Res<Args>>) {
+    if let Some(seed) = args.and_then(|args| args.seed) {
+        game_settings.seed = Some(seed);
+    }
+}
+
+pub fn reseed(
+    mut game_seed: ResMut<GameSeed>,
+    mut game_rng: ResMut<GameRng>,
+    game_settings: Res<GameSettings>,
+) {
+    // keep rolled seeds short enough to read off the game over screen
+    **game_seed = game_settings
+        .seed
+        .unwrap_or_else(|| rand::random::<u32>() as u64);
+    *game_rng = GameRng::from_seed(**game_seed);
+}
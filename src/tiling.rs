@@ -4,7 +4,7 @@ use bevy::{
     sprite::Mesh2dHandle,
 };
 
-use crate::game::DistanceTraveled;
+use crate::game::{interpolate_distance, RenderedDistance};
 
 #[derive(Component, Default)]
 pub struct Tiling {
@@ -28,7 +28,14 @@ pub struct TilingPlugin;
 
 impl Plugin for TilingPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update, (tile_textures, apply_uvs, parllax));
+        app.add_systems(
+            Update,
+            (
+                tile_textures,
+                apply_uvs,
+                parllax.after(interpolate_distance),
+            ),
+        );
     }
 }
 
@@ -79,8 +86,8 @@ fn apply_uvs(
     });
 }
 
-fn parllax(mut query: Query<(&mut Tiling, &Parallax)>, distance_traveled: Res<DistanceTraveled>) {
+fn parllax(mut query: Query<(&mut Tiling, &Parallax)>, rendered_distance: Res<RenderedDistance>) {
     query.for_each_mut(|(mut tiling, parallax)| {
-        tiling.uv_offset.x = **distance_traveled * parallax.ratio;
+        tiling.uv_offset.x = **rendered_distance * parallax.ratio;
     });
 }
@@ -0,0 +1,333 @@
+use std::{collections::BTreeMap, fmt};
+
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext},
+    prelude::*,
+    utils::BoxedFuture,
+};
+use serde::Deserialize;
+
+use crate::{
+    difficulty::{Difficulty, DifficultyCurve, DifficultyLevel, DifficultyPresets},
+    game::{GameSet, GameState, GameTick},
+    hud::label_style,
+    networking::NetSession,
+    physics::Gravity,
+    pipes::GAP_MARGIN,
+    rating::MedalThresholds,
+    replay::{ByteReader, ReplayError},
+    seed::reseed,
+};
+
+const TUNING_PATH: &str = "game.tuning.ron";
+
+// gameplay constants, read from `assets/game.tuning.ron` and picked up again by the next run whenever
+// it changes
+#[derive(Resource, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Tuning {
+    pub gravity: f32,
+    pub flap_force: f32,
+    pub game_speed: f32,
+    // distance between two pipes along the track
+    pub pipe_spacing: f32,
+    // height of the opening between a top and bottom pipe
+    pub gap_spacing: f32,
+    // from the ground up to where birds can no longer flap
+    pub game_height: f32,
+    // how the values above change over the course of a run
+    #[serde(default)]
+    pub difficulty: DifficultyPresets,
+    #[serde(default)]
+    pub medals: MedalThresholds,
+}
+
+impl Default for Tuning {
+    fn default() -> Self {
+        Self {
+            gravity: 400.0,
+            flap_force: 150.0,
+            game_speed: 50.0,
+            pipe_spacing: 75.0,
+            gap_spacing: 48.0,
+            game_height: 175.0,
+            difficulty: DifficultyPresets::default(),
+            medals: MedalThresholds::default(),
+        }
+    }
+}
+
+impl Tuning {
+    pub const ENCODED_SIZE: usize = 4 * 6 + DifficultyCurve::ENCODED_SIZE * 3 + 4 * 4;
+
+    pub fn parse(contents: &str) -> Result<Self, TuningError> {
+        let tuning: Self = ron::from_str(contents)?;
+        tuning.validate()?;
+        Ok(tuning)
+    }
+
+    pub fn validate(&self) -> Result<(), TuningError> {
+        for (name, value) in [
+            ("gravity", self.gravity),
+            ("flap_force", self.flap_force),
+            ("game_speed", self.game_speed),
+            ("pipe_spacing", self.pipe_spacing),
+            ("gap_spacing", self.gap_spacing),
+            ("game_height", self.game_height),
+        ] {
+            if !value.is_finite() || value <= 0.0 {
+                return Err(TuningError::Invalid(format!(
+                    "{name} must be positive, got {value}"
+                )));
+            }
+        }
+        for difficulty in Difficulty::ALL {
+            let curve = self.difficulty.curve(difficulty);
+            curve
+                .validate()
+                .map_err(|reason| TuningError::Invalid(format!("{difficulty}: {reason}")))?;
+            // otherwise there's nowhere to place a gap
+            let gap_spacing = self.gap_spacing * curve.gap.0.max(curve.gap.1);
+            if self.game_height < gap_spacing + GAP_MARGIN {
+                return Err(TuningError::Invalid(format!(
+                    "{difficulty}: game_height must be at least {}, got {}",
+                    gap_spacing + GAP_MARGIN,
+                    self.game_height
+                )));
+            }
+        }
+        self.medals
+            .validate()
+            .map_err(|reason| TuningError::Invalid(format!("medals: {reason}")))?;
+        Ok(())
+    }
+
+    pub fn level(&self, difficulty: Difficulty, distance: f32) -> DifficultyLevel {
+        self.difficulty.curve(difficulty).sample(distance)
+    }
+
+    pub fn encode(&self, bytes: &mut Vec<u8>) {
+        for value in [
+            self.gravity,
+            self.flap_force,
+            self.game_speed,
+            self.pipe_spacing,
+            self.gap_spacing,
+            self.game_height,
+        ] {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        for difficulty in Difficulty::ALL {
+            self.difficulty.curve(difficulty).encode(bytes);
+        }
+        for value in [
+            self.medals.bronze,
+            self.medals.silver,
+            self.medals.gold,
+            self.medals.platinum,
+        ] {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+    }
+
+    pub fn decode(reader: &mut ByteReader) -> Result<Self, ReplayError> {
+        let mut value = || reader.take().map(f32::from_le_bytes);
+        let (gravity, flap_force, game_speed, pipe_spacing, gap_spacing, game_height) =
+            (value()?, value()?, value()?, value()?, value()?, value()?);
+        Ok(Self {
+            gravity,
+            flap_force,
+            game_speed,
+            pipe_spacing,
+            gap_spacing,
+            game_height,
+            difficulty: DifficultyPresets {
+                easy: DifficultyCurve::decode(reader)?,
+                normal: DifficultyCurve::decode(reader)?,
+                hard: DifficultyCurve::decode(reader)?,
+            },
+            medals: {
+                let mut value = || reader.take().map(i32::from_le_bytes);
+                MedalThresholds {
+                    bronze: value()?,
+                    silver: value()?,
+                    gold: value()?,
+                    platinum: value()?,
+                }
+            },
+        })
+    }
+}
+
+#[derive(Debug)]
+pub enum TuningError {
+    Parse(ron::error::SpannedError),
+    Invalid(String),
+}
+
+impl fmt::Display for TuningError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TuningError::Parse(err) => write!(f, "parse error: {err}"),
+            TuningError::Invalid(reason) => write!(f, "invalid tuning: {reason}"),
+        }
+    }
+}
+
+impl From<ron::error::SpannedError> for TuningError {
+    fn from(value: ron::error::SpannedError) -> Self {
+        Self::Parse(value)
+    }
+}
+
+// a broken file still loads so it can be reported, and fixed while the game keeps running
+#[derive(Asset, TypePath)]
+pub struct TuningFile(Result<Tuning, String>);
+
+#[derive(Default)]
+struct TuningLoader;
+
+impl AssetLoader for TuningLoader {
+    type Asset = TuningFile;
+    type Settings = ();
+    type Error = std::io::Error;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<TuningFile, std::io::Error>> {
+        Box::pin(async move {
+            let mut contents = String::new();
+            reader.read_to_string(&mut contents).await?;
+            Ok(TuningFile(
+                Tuning::parse(&contents).map_err(|err| err.to_string()),
+            ))
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["tuning.ron"]
+    }
+}
+
+// the last problem with each tuning or pattern file, by path, the previous values stay in use until
+// it's fixed
+#[derive(Resource, Default, Deref, DerefMut)]
+pub struct TuningStatus(BTreeMap<String, String>);
+
+// a reloaded file waiting for the next run, so nothing changes halfway through one
+#[derive(Resource, Default, Deref, DerefMut)]
+struct PendingTuning(Option<Tuning>);
+
+#[derive(Component)]
+struct TuningErrorLabel;
+
+pub struct TuningPlugin;
+
+impl Plugin for TuningPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Tuning>()
+            .init_resource::<TuningStatus>()
+            .init_resource::<PendingTuning>()
+            .init_asset::<TuningFile>()
+            .init_asset_loader::<TuningLoader>()
+            .add_systems(PreUpdate, load_tuning_file)
+            // peers compared their tuning when they connected, so it stays as it was for the session
+            .add_systems(OnExit(GameState::Loading), apply_pending_tuning)
+            .add_systems(
+                OnEnter(GameState::Waiting),
+                apply_pending_tuning
+                    .before(reseed)
+                    .run_if(not(resource_exists::<NetSession>())),
+            )
+            .add_systems(
+                GameTick,
+                apply_gravity
+                    .run_if(resource_changed::<Tuning>())
+                    .in_set(GameSet::Input),
+            )
+            .add_systems(
+                Update,
+                show_tuning_status.run_if(resource_changed::<TuningStatus>()),
+            );
+    }
+}
+
+fn load_tuning_file(
+    mut events: EventReader<AssetEvent<TuningFile>>,
+    mut pending: ResMut<PendingTuning>,
+    mut status: ResMut<TuningStatus>,
+    tuning: Res<Tuning>,
+    tuning_files: Res<Assets<TuningFile>>,
+) {
+    for event in events.read() {
+        let (AssetEvent::Added { id } | AssetEvent::Modified { id }) = event else {
+            continue;
+        };
+        let Some(TuningFile(result)) = tuning_files.get(*id) else {
+            continue;
+        };
+        match result {
+            Ok(loaded) => {
+                **pending = Some(*loaded).filter(|loaded| *loaded != *tuning);
+                if status.contains_key(TUNING_PATH) {
+                    status.remove(TUNING_PATH);
+                }
+            }
+            Err(err) => {
+                error!("{TUNING_PATH}: {err}");
+                status.insert(TUNING_PATH.to_string(), err.clone());
+            }
+        }
+    }
+}
+
+fn apply_pending_tuning(mut pending: ResMut<PendingTuning>, mut tuning: ResMut<Tuning>) {
+    if let Some(loaded) = pending.take() {
+        info!("tuning updated: {loaded:?}");
+        *tuning = loaded;
+    }
+}
+
+fn apply_gravity(mut gravity: ResMut<Gravity>, tuning: Res<Tuning>) {
+    **gravity = -Vec2::Y * tuning.gravity;
+}
+
+fn show_tuning_status(
+    mut commands: Commands,
+    label_query: Query<Entity, With<TuningErrorLabel>>,
+    status: Res<TuningStatus>,
+) {
+    label_query.for_each(|entity| {
+        commands.entity(entity).despawn_recursive();
+    });
+
+    // on top of everything, even the loading screen
+    if status.is_empty() {
+        return;
+    }
+    let errors = status
+        .iter()
+        .map(|(path, err)| format!("{path}: {err}"))
+        .collect::<Vec<_>>();
+    commands.spawn((
+        TextBundle::from_section(
+            errors.join("\n"),
+            TextStyle {
+                color: Color::RED,
+                ..label_style()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            left: Val::Px(4.0),
+            right: Val::Px(4.0),
+            bottom: Val::Px(4.0),
+            ..default()
+        }),
+        ZIndex::Global(i32::MAX),
+        TuningErrorLabel,
+    ));
+}
@@ -0,0 +1,547 @@
+use bevy::{
+    core_pipeline::clear_color::ClearColorConfig,
+    input::InputSystem,
+    prelude::*,
+    render::{camera::Viewport, view::RenderLayers},
+    window::PrimaryWindow,
+};
+use bevy_camera_shake::Shake2d;
+
+use crate::{
+    camera::{camera_bundle, spawn_camera, PlayerView},
+    game::{GameAssets, GameSet, GameState, GameTick},
+    hud::{
+        despawn_screen, label_style, screen_bundle, HudRoot, NumberDisplay, StateButton, UiAtlas,
+        UiSprite,
+    },
+    menu::GameSettings,
+    networking::NetSession,
+    player::{
+        self, flap_input, spawn_player, Crashed, FlapInputs, LastBirdStanding, LocalPlayer, Player,
+        PlayerAnimations, PlayerAtlas, PlayerId, PlayerScore,
+    },
+    replay::ReplayPlayback,
+};
+
+// one key per bird, top half of the screen first when it's split, gamepads work too
+pub const VERSUS_KEYS: [KeyCode; 4] = [KeyCode::W, KeyCode::Up, KeyCode::L, KeyCode::B];
+pub const MAX_LOCAL_PLAYERS: usize = VERSUS_KEYS.len();
+const TINTS: [Color; MAX_LOCAL_PLAYERS] = [
+    Color::WHITE,
+    Color::rgb(1.0, 0.55, 0.55),
+    Color::rgb(0.55, 1.0, 0.55),
+    Color::rgb(0.55, 0.7, 1.0),
+];
+
+// birds sharing one machine
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LocalVersus {
+    // two birds, each with its own half of the screen
+    SplitScreen,
+    // up to `MAX_LOCAL_PLAYERS` birds on the same screen
+    Party(usize),
+}
+
+impl LocalVersus {
+    pub fn players(&self) -> usize {
+        match self {
+            LocalVersus::SplitScreen => 2,
+            LocalVersus::Party(players) => (*players).clamp(1, MAX_LOCAL_PLAYERS),
+        }
+    }
+}
+
+// `None` when the last birds went down on the same tick with the same score
+#[derive(Resource, Default, Deref, DerefMut)]
+pub struct VersusWinner(pub Option<PlayerId>);
+
+// flaps are read every frame but only applied on the next fixed tick
+#[derive(Resource, Default, Deref, DerefMut)]
+struct QueuedFlaps(Vec<PlayerId>);
+
+#[derive(Component, Clone, Copy)]
+pub enum VersusButton {
+    SplitScreen,
+    Party,
+}
+
+// anything spawned for the split screen that has to go when leaving it
+#[derive(Component, Clone)]
+struct VersusCamera;
+
+#[derive(Component)]
+struct VersusScores;
+
+#[derive(Component)]
+struct VersusScore(PlayerId);
+
+#[derive(Component)]
+struct WinnerScreen;
+
+pub struct VersusPlugin;
+
+impl Plugin for VersusPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<VersusWinner>()
+            .init_resource::<QueuedFlaps>()
+            .add_systems(
+                OnEnter(GameState::Waiting),
+                start_versus.run_if(resource_exists::<LocalVersus>()),
+            )
+            .add_systems(
+                OnEnter(GameState::Dead),
+                (decide_winner, spawn_winner_screen)
+                    .chain()
+                    .run_if(resource_exists::<LocalVersus>()),
+            )
+            .add_systems(OnExit(GameState::Dead), despawn_screen::<WinnerScreen>)
+            .add_systems(
+                OnEnter(GameState::MainMenu),
+                // after the birds were reset, so that doesn't touch the ones despawned here
+                leave_versus
+                    .after(player::restart)
+                    .run_if(resource_exists::<LocalVersus>()),
+            )
+            .add_systems(
+                PreUpdate,
+                queue_flaps
+                    .after(InputSystem)
+                    .run_if(resource_exists::<LocalVersus>())
+                    .run_if(not(resource_exists::<ReplayPlayback>()))
+                    .run_if(in_state(GameState::Waiting).or_else(in_state(GameState::Playing))),
+            )
+            .add_systems(
+                GameTick,
+                gather_flaps
+                    .before(flap_input)
+                    .in_set(GameSet::Input)
+                    .run_if(resource_exists::<LocalVersus>())
+                    .run_if(in_state(GameState::Waiting).or_else(in_state(GameState::Playing))),
+            )
+            .add_systems(
+                Update,
+                (
+                    versus_button
+                        .run_if(in_state(GameState::MainMenu))
+                        .run_if(not(resource_exists::<NetSession>())),
+                    (update_viewports, update_scores).run_if(resource_exists::<LocalVersus>()),
+                ),
+            );
+    }
+}
+
+fn versus_button(
+    mut commands: Commands,
+    mut next_state: ResMut<NextState<GameState>>,
+    query: Query<(&Interaction, &VersusButton), Changed<Interaction>>,
+    game_settings: Res<GameSettings>,
+) {
+    for (interaction, versus_button) in query.iter() {
+        if *interaction == Interaction::Pressed {
+            commands.insert_resource(match versus_button {
+                VersusButton::SplitScreen => LocalVersus::SplitScreen,
+                VersusButton::Party => LocalVersus::Party(game_settings.party_size),
+            });
+            next_state.set(GameState::Waiting);
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn start_versus(
+    mut commands: Commands,
+    mut last_bird_standing: ResMut<LastBirdStanding>,
+    mut local_player: ResMut<LocalPlayer>,
+    mut queued_flaps: ResMut<QueuedFlaps>,
+    mut query: Query<(&PlayerId, &mut TextureAtlasSprite), With<Player>>,
+    camera_query: Query<(Entity, &Children), (With<Shake2d>, Without<PlayerView>)>,
+    scores_query: Query<(), With<VersusScores>>,
+    root_query: Query<Entity, With<HudRoot>>,
+    local_versus: Res<LocalVersus>,
+    player_atlas: Res<PlayerAtlas>,
+    player_animations: Res<PlayerAnimations>,
+) {
+    **last_bird_standing = true;
+    **local_player = PlayerId::default();
+    queued_flaps.clear();
+
+    let players = local_versus.players();
+    let mut spawned = [false; MAX_LOCAL_PLAYERS];
+    query.for_each_mut(|(player_id, mut sprite)| {
+        if let Some(spawned) = spawned.get_mut(**player_id) {
+            *spawned = true;
+            sprite.color = TINTS[**player_id];
+        }
+    });
+
+    for (id, _) in spawned
+        .iter()
+        .enumerate()
+        .take(players)
+        .filter(|(_, spawned)| !**spawned)
+    {
+        let entity = spawn_player(&mut commands, &player_atlas, PlayerId(id));
+        commands.entity(entity).insert((
+            TextureAtlasSprite {
+                index: player_atlas.index,
+                color: TINTS[id],
+                ..default()
+            },
+            // later birds are drawn behind, so overlapping ones don't flicker
+            Transform::from_translation(Vec3::Z * (500.0 - id as f32 * 0.1)),
+            player_animations.idle.clone(),
+        ));
+    }
+
+    // the rest only has to be set up once, restarting keeps the screen as it is
+    if !scores_query.is_empty() {
+        return;
+    }
+
+    if *local_versus != LocalVersus::SplitScreen {
+        let Ok(root) = root_query.get_single() else {
+            return;
+        };
+        commands.entity(root).with_children(|parent| {
+            parent
+                .spawn((
+                    NodeBundle {
+                        style: Style {
+                            position_type: PositionType::Absolute,
+                            top: Val::Px(10.0),
+                            width: Val::Percent(100.0),
+                            justify_content: JustifyContent::SpaceEvenly,
+                            ..default()
+                        },
+                        visibility: Visibility::Hidden,
+                        ..default()
+                    },
+                    VersusScores,
+                ))
+                .with_children(|parent| {
+                    for id in 0..players {
+                        spawn_score(parent, PlayerId(id), &player_atlas);
+                    }
+                });
+        });
+        return;
+    }
+
+    // the usual camera becomes the first player's view, the ui gets a camera of its own
+    let no_ui = UiCameraConfig { show_ui: false };
+    camera_query.for_each(|(entity, children)| {
+        commands.entity(entity).insert(PlayerView(PlayerId(0)));
+        for child in children.iter() {
+            commands
+                .entity(*child)
+                .insert((PlayerView(PlayerId(0)), no_ui.clone()));
+        }
+    });
+    for id in 1..players {
+        spawn_camera(
+            &mut commands,
+            camera_bundle(id as isize),
+            (PlayerView(PlayerId(id)), no_ui.clone(), VersusCamera),
+        );
+    }
+
+    let mut ui_camera = camera_bundle(players as isize);
+    ui_camera.camera_2d.clear_color = ClearColorConfig::None;
+    commands.spawn((ui_camera, RenderLayers::none(), VersusCamera));
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
+                    ..default()
+                },
+                visibility: Visibility::Hidden,
+                ..default()
+            },
+            VersusScores,
+        ))
+        .with_children(|parent| {
+            for id in 0..players {
+                parent
+                    .spawn(NodeBundle {
+                        style: Style {
+                            flex_grow: 1.0,
+                            justify_content: JustifyContent::Center,
+                            padding: UiRect::top(Val::Px(10.0)),
+                            ..default()
+                        },
+                        ..default()
+                    })
+                    .with_children(|parent| {
+                        spawn_score(parent, PlayerId(id), &player_atlas);
+                    });
+            }
+        });
+}
+
+// the bird's score next to a bird in its tint
+fn spawn_score(parent: &mut ChildBuilder, player_id: PlayerId, player_atlas: &PlayerAtlas) {
+    parent
+        .spawn(NodeBundle {
+            style: Style {
+                column_gap: Val::Px(2.0),
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|parent| {
+            parent.spawn(AtlasImageBundle {
+                style: Style {
+                    width: Val::Px(player_atlas.size.x),
+                    height: Val::Px(player_atlas.size.y),
+                    ..default()
+                },
+                texture_atlas: player_atlas.handle.clone(),
+                texture_atlas_image: UiTextureAtlasImage {
+                    index: player_atlas.index,
+                    ..default()
+                },
+                background_color: TINTS[*player_id].into(),
+                ..default()
+            });
+            parent.spawn((
+                NodeBundle::default(),
+                NumberDisplay::default(),
+                VersusScore(player_id),
+            ));
+        });
+}
+
+#[allow(clippy::type_complexity)]
+fn leave_versus(
+    mut commands: Commands,
+    mut last_bird_standing: ResMut<LastBirdStanding>,
+    mut player_query: Query<(Entity, &PlayerId, &mut TextureAtlasSprite), With<Player>>,
+    mut camera_query: Query<
+        (Entity, Option<&mut Camera>),
+        (With<PlayerView>, Without<VersusCamera>),
+    >,
+    versus_query: Query<Entity, Or<(With<VersusCamera>, With<VersusScores>)>>,
+) {
+    commands.remove_resource::<LocalVersus>();
+    **last_bird_standing = false;
+
+    player_query.for_each_mut(|(entity, player_id, mut sprite)| {
+        if *player_id == PlayerId::default() {
+            sprite.color = Color::WHITE;
+        } else {
+            commands.entity(entity).despawn_recursive();
+        }
+    });
+
+    camera_query.for_each_mut(|(entity, camera)| {
+        commands
+            .entity(entity)
+            .remove::<(PlayerView, UiCameraConfig)>();
+        if let Some(mut camera) = camera {
+            camera.viewport = None;
+        }
+    });
+
+    versus_query.for_each(|entity| {
+        commands.entity(entity).despawn_recursive();
+    });
+}
+
+fn queue_flaps(
+    mut queued_flaps: ResMut<QueuedFlaps>,
+    keyboard_input: Res<Input<KeyCode>>,
+    gamepad_input: Res<Input<GamepadButton>>,
+    gamepads: Res<Gamepads>,
+    local_versus: Res<LocalVersus>,
+) {
+    // handed out in the order they were connected
+    let mut gamepads = gamepads.iter().collect::<Vec<_>>();
+    gamepads.sort_by_key(|gamepad| gamepad.id);
+
+    for (id, key) in VERSUS_KEYS.iter().enumerate().take(local_versus.players()) {
+        let pressed = keyboard_input.just_pressed(*key)
+            || gamepads.get(id).is_some_and(|gamepad| {
+                gamepad_input.just_pressed(GamepadButton::new(*gamepad, GamepadButtonType::South))
+            });
+        if pressed && !queued_flaps.contains(&PlayerId(id)) {
+            queued_flaps.push(PlayerId(id));
+        }
+    }
+}
+
+fn gather_flaps(mut queued_flaps: ResMut<QueuedFlaps>, mut flap_inputs: ResMut<FlapInputs>) {
+    flap_inputs.append(&mut queued_flaps);
+}
+
+// stacked on top of each other, the window is taller than it is wide
+fn update_viewports(
+    mut query: Query<(&mut Camera, &PlayerView)>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+) {
+    let Ok(window) = window_query.get_single() else {
+        return;
+    };
+
+    let size = UVec2::new(
+        window.physical_width(),
+        window.physical_height() / LocalVersus::SplitScreen.players() as u32,
+    );
+    query.for_each_mut(|(mut camera, player_view)| {
+        let position = UVec2::new(0, size.y * *player_view.0 as u32);
+        let unchanged = camera.viewport.as_ref().is_some_and(|viewport| {
+            viewport.physical_position == position && viewport.physical_size == size
+        });
+        if !unchanged {
+            camera.viewport = Some(Viewport {
+                physical_position: position,
+                physical_size: size,
+                ..default()
+            });
+        }
+    });
+}
+
+fn update_scores(
+    mut query: Query<(&mut NumberDisplay, &VersusScore)>,
+    mut scores_query: Query<&mut Visibility, With<VersusScores>>,
+    player_query: Query<(&PlayerId, &PlayerScore)>,
+    game_state: Res<State<GameState>>,
+) {
+    query.for_each_mut(|(mut number_display, versus_score)| {
+        let score = player_query
+            .iter()
+            .find(|(player_id, _)| **player_id == versus_score.0)
+            .map_or(0, |(_, player_score)| player_score.score);
+        if number_display.0 != score {
+            number_display.0 = score;
+        }
+    });
+
+    let visible = *game_state == GameState::Playing || *game_state == GameState::Paused;
+    scores_query.for_each_mut(|mut visibility| {
+        visibility.set_if_neq(if visible {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        });
+    });
+}
+
+// the last bird to go down wins, or the better score if they went down together
+fn decide_winner(
+    mut winner: ResMut<VersusWinner>,
+    query: Query<(&PlayerId, &PlayerScore, Option<&Crashed>)>,
+) {
+    let mut birds = query
+        .iter()
+        .map(|(player_id, player_score, crashed)| {
+            let tick = crashed.map_or(u32::MAX, |crashed| crashed.tick);
+            (*player_id, (tick, player_score.score))
+        })
+        .collect::<Vec<_>>();
+    birds.sort_by_key(|(_, result)| std::cmp::Reverse(*result));
+
+    **winner = match birds.as_slice() {
+        [(_, best), (_, second), ..] if best == second => None,
+        [(first, _), ..] => Some(*first),
+        [] => None,
+    };
+}
+
+fn spawn_winner_screen(
+    mut commands: Commands,
+    root_query: Query<Entity, With<HudRoot>>,
+    player_query: Query<(&PlayerId, &PlayerScore)>,
+    ui_atlas: Res<UiAtlas>,
+    game_assets: Res<GameAssets>,
+    winner: Res<VersusWinner>,
+) {
+    let Ok(root) = root_query.get_single() else {
+        return;
+    };
+
+    let mut scores = player_query
+        .iter()
+        .map(|(player_id, player_score)| (*player_id, player_score.score))
+        .collect::<Vec<_>>();
+    scores.sort_by_key(|(player_id, _)| **player_id);
+
+    let label_style = label_style();
+    let title = match **winner {
+        Some(player_id) => format!("PLAYER {} WINS", *player_id + 1),
+        None => "DRAW".into(),
+    };
+
+    commands.entity(root).with_children(|parent| {
+        parent
+            .spawn((screen_bundle(), WinnerScreen))
+            .with_children(|parent| {
+                parent.spawn(ui_atlas.image(UiSprite::GameOver));
+
+                parent
+                    .spawn(ImageBundle {
+                        style: Style {
+                            width: Val::Px(100.0),
+                            flex_direction: FlexDirection::Column,
+                            align_items: AlignItems::Center,
+                            row_gap: Val::Px(4.0),
+                            padding: UiRect::axes(Val::Px(12.0), Val::Px(8.0)),
+                            ..default()
+                        },
+                        image: UiImage::new(game_assets.ui_background.clone()),
+                        ..default()
+                    })
+                    .with_children(|parent| {
+                        parent.spawn(TextBundle::from_section(title, label_style.clone()));
+
+                        for (player_id, score) in scores {
+                            parent
+                                .spawn(NodeBundle {
+                                    style: Style {
+                                        width: Val::Percent(100.0),
+                                        justify_content: JustifyContent::SpaceBetween,
+                                        align_items: AlignItems::Center,
+                                        ..default()
+                                    },
+                                    ..default()
+                                })
+                                .with_children(|parent| {
+                                    parent.spawn(TextBundle::from_section(
+                                        format!("PLAYER {}", *player_id + 1),
+                                        label_style.clone(),
+                                    ));
+                                    parent.spawn((NodeBundle::default(), NumberDisplay(score)));
+                                });
+                        }
+                    });
+
+                parent
+                    .spawn(NodeBundle {
+                        style: Style {
+                            column_gap: Val::Px(8.0),
+                            align_items: AlignItems::Center,
+                            ..default()
+                        },
+                        ..default()
+                    })
+                    .with_children(|parent| {
+                        ui_atlas.spawn_button(
+                            parent,
+                            UiSprite::PlayButton,
+                            StateButton(GameState::Waiting),
+                        );
+                        ui_atlas.spawn_button(
+                            parent,
+                            UiSprite::MenuButton,
+                            StateButton(GameState::MainMenu),
+                        );
+                    });
+            });
+    });
+}
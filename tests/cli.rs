@@ -0,0 +1,71 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use flappy_bird::{
+    cli::Args,
+    game::GameState,
+    headless::{self, RunSummary},
+    physics::TickRate,
+    replay::{ReplayPlayback, RunTick},
+};
+
+mod common;
+use common::{flaps, state, tick_until, MAX_TICKS};
+
+fn parse(args: &[&str]) -> Result<Args, String> {
+    Args::parse(args.iter().map(|arg| arg.to_string()))
+}
+
+// a run at `tick_rate` flapping every `interval` ticks
+fn app(tick_rate: f64, interval: u32) -> App {
+    common::app(
+        Args {
+            seed: Some(4),
+            tick_rate: Some(tick_rate),
+            ..default()
+        },
+        Some(ReplayPlayback::scripted(4, flaps(interval))),
+    )
+}
+
+fn run(tick_rate: f64, interval: u32) -> RunSummary {
+    let mut app = app(tick_rate, interval);
+    for _ in 0..MAX_TICKS * 4 {
+        if state(&app) == GameState::Dead {
+            return headless::summary(&app.world);
+        }
+        app.update();
+    }
+    panic!("run at {tick_rate} ticks per second never ended");
+}
+
+#[test]
+fn tick_rate_is_picked_on_the_command_line() {
+    assert_eq!(
+        parse(&["--tick-rate", "120"]).unwrap().tick_rate,
+        Some(120.0)
+    );
+    assert!(parse(&["--tick-rate", "5"]).is_err());
+    assert!(parse(&["--tick-rate", "120", "--loopback"]).is_err());
+    assert!(parse(&["--tick-rate", "120", "--replay", "best.replay"]).is_err());
+
+    let mut app = app(120.0, 40);
+    tick_until(&mut app, |app| state(app) == GameState::Playing);
+    assert_eq!(**app.world.resource::<TickRate>(), 120.0);
+    assert_eq!(
+        app.world.resource::<Time<Fixed>>().timestep(),
+        Duration::from_secs_f64(1.0 / 120.0)
+    );
+
+    // still one tick per update headless
+    let tick = **app.world.resource::<RunTick>();
+    app.update();
+    assert_eq!(**app.world.resource::<RunTick>(), tick + 1);
+}
+
+#[test]
+fn same_inputs_at_the_same_tick_rate_play_out_the_same() {
+    let summary = run(120.0, 40);
+    assert!(summary.ticks > 0);
+    assert_eq!(run(120.0, 40), summary);
+}
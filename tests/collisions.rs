@@ -0,0 +1,428 @@
+use std::{collections::HashSet, f32::consts::PI, time::Duration};
+
+use bevy::{
+    prelude::*,
+    render::render_resource::{Extent3d, TextureDimension, TextureFormat},
+    sprite::collide_aabb::Collision,
+};
+use flappy_bird::{
+    collider::{Collider, CollisionLayers, Layer, Sensor},
+    game::GameTick,
+    physics::{CollisionEnded, CollisionEvent, CollisionStarted, PhysicsPlugin, Velocity},
+};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+const TICK: Duration = Duration::from_millis(100);
+
+// just the physics, stepped one tick at a time
+fn app() -> App {
+    let mut app = App::new();
+    app.add_plugins(PhysicsPlugin)
+        .insert_resource(Time::<()>::default());
+    app
+}
+
+fn spawn(app: &mut App, position: Vec2, size: Vec2, velocity: Vec2) -> Entity {
+    app.world
+        .spawn((
+            TransformBundle::from_transform(Transform::from_translation(position.extend(0.0))),
+            Collider::Box { size },
+            Velocity(velocity),
+        ))
+        .id()
+}
+
+// anything that stays put, placed however it needs to be
+fn place(app: &mut App, transform: Transform, collider: Collider) -> Entity {
+    app.world
+        .spawn((TransformBundle::from_transform(transform), collider))
+        .id()
+}
+
+fn tick(app: &mut App) -> Vec<CollisionEvent> {
+    app.world.resource_mut::<Time>().advance_by(TICK);
+    app.world.run_schedule(GameTick);
+    drain(app)
+}
+
+fn drain<E: Event>(app: &mut App) -> Vec<E> {
+    app.world.resource_mut::<Events<E>>().drain().collect()
+}
+
+#[test]
+fn fast_colliders_hit_thin_ones_in_between_ticks() {
+    let mut app = app();
+    // 100 units per tick, straight past a wall 1 unit thick
+    let bird = spawn(
+        &mut app,
+        Vec2::new(-130.0, 10.0),
+        Vec2::splat(10.0),
+        Vec2::X * 1000.0,
+    );
+    let wall = spawn(
+        &mut app,
+        Vec2::new(20.0, 10.0),
+        Vec2::new(1.0, 50.0),
+        Vec2::ZERO,
+    );
+    assert!(tick(&mut app).is_empty());
+
+    let events = tick(&mut app);
+    assert_eq!(events.len(), 1);
+    let event = &events[0];
+    assert_eq!((event.entity_a, event.entity_b), (bird, wall));
+    assert_eq!(event.collision, Collision::Left);
+    // the tick starts with the bird at -30 and touching the wall 44.5 units in
+    let expected = (20.0 - 0.5 - 5.0 + 30.0) / 100.0;
+    assert!((event.time_of_impact - expected).abs() < 0.001);
+
+    // and is left behind once the bird is through
+    assert!(tick(&mut app).is_empty());
+}
+
+#[test]
+fn both_sides_moving_are_swept_together() {
+    let mut app = app();
+    // falling onto a floor that rises to meet it, neither of them fast enough alone
+    spawn(
+        &mut app,
+        Vec2::new(5.0, 36.0),
+        Vec2::splat(10.0),
+        Vec2::NEG_Y * 100.0,
+    );
+    spawn(
+        &mut app,
+        Vec2::new(5.0, 0.0),
+        Vec2::new(40.0, 2.0),
+        Vec2::Y * 100.0,
+    );
+    assert!(tick(&mut app).is_empty());
+
+    let events = tick(&mut app);
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].collision, Collision::Top);
+    // 10 units apart at the start of the tick, closing 20 units over it
+    assert!((events[0].time_of_impact - 0.5).abs() < 0.001);
+}
+
+#[test]
+fn overlapping_colliders_hit_from_the_start() {
+    let mut app = app();
+    spawn(
+        &mut app,
+        Vec2::new(10.0, 10.0),
+        Vec2::splat(10.0),
+        Vec2::ZERO,
+    );
+    spawn(
+        &mut app,
+        Vec2::new(14.0, 10.0),
+        Vec2::splat(10.0),
+        Vec2::ZERO,
+    );
+    // passing alongside without touching
+    spawn(
+        &mut app,
+        Vec2::new(-200.0, 40.0),
+        Vec2::splat(10.0),
+        Vec2::X * 4000.0,
+    );
+    tick(&mut app);
+
+    let events = tick(&mut app);
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].time_of_impact, 0.0);
+}
+
+#[test]
+fn colliders_at_the_origin_are_checked_too() {
+    let mut app = app();
+    let a = spawn(&mut app, Vec2::ZERO, Vec2::splat(10.0), Vec2::ZERO);
+    let b = spawn(&mut app, Vec2::new(4.0, 0.0), Vec2::splat(10.0), Vec2::ZERO);
+
+    let events = tick(&mut app);
+    assert_eq!(events.len(), 1);
+    assert_eq!((events[0].entity_a, events[0].entity_b), (a, b));
+}
+
+#[test]
+fn crowds_collide_like_every_pair_was_checked() {
+    let mut app = app();
+    let mut rng = ChaCha8Rng::seed_from_u64(7);
+    let mut circles = Vec::new();
+    for _ in 0..400 {
+        let position = Vec2::new(rng.gen_range(0.0..400.0), rng.gen_range(0.0..100.0));
+        let radius = rng.gen_range(1.0..8.0);
+        let entity = place(
+            &mut app,
+            Transform::from_translation(position.extend(0.0)),
+            Collider::Circle { radius },
+        );
+        circles.push((entity, position, radius));
+    }
+
+    let mut expected = HashSet::new();
+    for (i, (a, a_position, a_radius)) in circles.iter().enumerate() {
+        for (b, b_position, b_radius) in circles.iter().skip(i + 1) {
+            if a_position.distance(*b_position) <= a_radius + b_radius {
+                expected.insert((*a, *b));
+            }
+        }
+    }
+    let found = tick(&mut app)
+        .into_iter()
+        .map(|event| (event.entity_a, event.entity_b))
+        .collect::<HashSet<_>>();
+    assert!(!expected.is_empty());
+    assert_eq!(found, expected);
+}
+
+// static colliders are checked again every tick, two are enough for the sweep to settle
+fn touching(app: &mut App, a: Entity, b: Entity) -> bool {
+    tick(app);
+    tick(app).iter().any(|event| {
+        (event.entity_a, event.entity_b) == (a, b) || (event.entity_a, event.entity_b) == (b, a)
+    })
+}
+
+#[test]
+fn turned_boxes_reach_past_their_bounds() {
+    for (x, hit) in [(27.5, true), (28.5, false)] {
+        let mut app = app();
+        // a corner of a 10 by 10 box turned by 45 degrees pokes out to 27.07
+        let turned = place(
+            &mut app,
+            Transform::from_xyz(20.0, 10.0, 0.0).with_rotation(Quat::from_rotation_z(0.25 * PI)),
+            Collider::Box {
+                size: Vec2::splat(10.0),
+            },
+        );
+        let wall = place(
+            &mut app,
+            Transform::from_xyz(x, 10.0, 0.0),
+            Collider::Box {
+                size: Vec2::new(2.0, 40.0),
+            },
+        );
+        assert_eq!(touching(&mut app, turned, wall), hit, "wall at {x}");
+    }
+}
+
+#[test]
+fn round_shapes_miss_corners() {
+    for (offset, hit) in [(7.0, true), (9.0, false)] {
+        let mut app = app();
+        let circle = place(
+            &mut app,
+            Transform::from_xyz(10.0, 10.0, 0.0),
+            Collider::Circle { radius: 5.0 },
+        );
+        // the boxes overlap the circle's bounds either way
+        let corner = place(
+            &mut app,
+            Transform::from_xyz(10.0 + offset, 10.0 + offset, 0.0),
+            Collider::Box {
+                size: Vec2::splat(10.0),
+            },
+        );
+        assert_eq!(touching(&mut app, circle, corner), hit, "offset {offset}");
+    }
+}
+
+#[test]
+fn capsules_turn_and_scale_with_their_transform() {
+    let capsule = Collider::Capsule {
+        half_length: 10.0,
+        radius: 2.0,
+    };
+    let probe = Collider::Box {
+        size: Vec2::splat(2.0),
+    };
+    for (transform, hit) in [
+        (Transform::from_xyz(10.0, 10.0, 0.0), false),
+        // standing up it reaches 12 above its middle
+        (
+            Transform::from_xyz(10.0, 10.0, 0.0).with_rotation(Quat::from_rotation_z(0.5 * PI)),
+            true,
+        ),
+        // and twice the size lying down is still only 8 high
+        (
+            Transform::from_xyz(10.0, 10.0, 0.0).with_scale(Vec3::splat(2.0)),
+            false,
+        ),
+    ] {
+        let mut app = app();
+        let a = place(&mut app, transform, capsule.clone());
+        let b = place(
+            &mut app,
+            Transform::from_xyz(10.0, 20.0, 0.0),
+            probe.clone(),
+        );
+        assert_eq!(touching(&mut app, a, b), hit, "{transform:?}");
+    }
+}
+
+#[test]
+fn masks_follow_flipped_sprites() {
+    // only the bottom row of a 4 by 4 image is solid
+    let mut data = vec![0; 4 * 4 * 4];
+    for pixel in data.chunks_exact_mut(4).skip(12) {
+        pixel.copy_from_slice(&[255; 4]);
+    }
+    let image = Image::new(
+        Extent3d {
+            width: 4,
+            height: 4,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+    );
+    let mask = Collider::mask(&image, 128).unwrap();
+    let probe = Collider::Box {
+        size: Vec2::new(6.0, 2.0),
+    };
+
+    // scaled up to 16 by 16, the solid row is 6 below the middle or above it once flipped
+    for (scale_y, probe_y, hit) in [
+        (4.0, -6.0, true),
+        (4.0, 6.0, false),
+        (-4.0, -6.0, false),
+        (-4.0, 6.0, true),
+    ] {
+        let mut app = app();
+        let sprite = place(
+            &mut app,
+            Transform::from_xyz(20.0, 20.0, 0.0).with_scale(Vec3::new(4.0, scale_y, 1.0)),
+            mask.clone(),
+        );
+        let b = place(
+            &mut app,
+            Transform::from_xyz(20.0, 20.0 + probe_y, 0.0),
+            probe.clone(),
+        );
+        assert_eq!(
+            touching(&mut app, sprite, b),
+            hit,
+            "scale {scale_y}, probe at {probe_y}"
+        );
+    }
+}
+
+#[test]
+fn layers_pick_what_collides() {
+    let mut app = app();
+    let bird = CollisionLayers::new([Layer::Bird], [Layer::Pipe]);
+    let pipe = CollisionLayers::new([Layer::Pipe], [Layer::Bird]);
+    // all on top of each other
+    let a = spawn(&mut app, Vec2::splat(10.0), Vec2::splat(10.0), Vec2::ZERO);
+    let b = spawn(&mut app, Vec2::splat(12.0), Vec2::splat(10.0), Vec2::ZERO);
+    let c = spawn(&mut app, Vec2::splat(14.0), Vec2::splat(10.0), Vec2::ZERO);
+    app.world.entity_mut(a).insert(bird);
+    app.world.entity_mut(b).insert(pipe);
+    app.world.entity_mut(c).insert(pipe);
+
+    // pipes don't look for each other, and colliders without layers look for everything
+    let d = spawn(&mut app, Vec2::splat(16.0), Vec2::splat(10.0), Vec2::ZERO);
+    // found in whichever order the query has them
+    let pairs = tick(&mut app)
+        .into_iter()
+        .map(|event| {
+            let (a, b) = (event.entity_a, event.entity_b);
+            (a.min(b), a.max(b))
+        })
+        .collect::<HashSet<_>>();
+    assert_eq!(
+        pairs,
+        HashSet::from([(a, b), (a, c), (a, d), (b, d), (c, d)])
+    );
+
+    app.world.entity_mut(d).insert(CollisionLayers::new(
+        [Layer::Pipe],
+        [Layer::Bird, Layer::Score],
+    ));
+    assert_eq!(tick(&mut app).len(), 3);
+}
+
+#[test]
+fn sensors_only_report_starting_and_ending() {
+    let mut app = app();
+    let bird = spawn(
+        &mut app,
+        Vec2::new(-20.0, 0.0),
+        Vec2::splat(10.0),
+        Vec2::X * 100.0,
+    );
+    let area = spawn(&mut app, Vec2::ZERO, Vec2::new(1.0, 40.0), Vec2::ZERO);
+    app.world.entity_mut(area).insert(Sensor);
+
+    let mut started = Vec::new();
+    let mut ended = Vec::new();
+    for _ in 0..5 {
+        assert!(tick(&mut app).is_empty());
+        started.extend(drain::<CollisionStarted>(&mut app));
+        ended.extend(drain::<CollisionEnded>(&mut app));
+    }
+    assert_eq!(started.len(), 1);
+    assert!(started[0].sensor);
+    assert_eq!(started[0].other(bird), Some(area));
+    assert_eq!(started[0].other(area), Some(bird));
+    assert_eq!(ended.len(), 1);
+    assert_eq!(ended[0].other(area), Some(bird));
+}
+
+#[test]
+fn solid_contacts_end_when_despawned() {
+    let mut app = app();
+    let a = spawn(&mut app, Vec2::splat(10.0), Vec2::splat(10.0), Vec2::ZERO);
+    let b = spawn(&mut app, Vec2::splat(14.0), Vec2::splat(10.0), Vec2::ZERO);
+    tick(&mut app);
+    tick(&mut app);
+    let started = drain::<CollisionStarted>(&mut app);
+    assert_eq!(started.len(), 1);
+    assert!(!started[0].sensor);
+
+    app.world.despawn(b);
+    tick(&mut app);
+    let ended = drain::<CollisionEnded>(&mut app);
+    assert_eq!(ended.len(), 1);
+    assert_eq!(ended[0].other(a), Some(b));
+}
+
+#[test]
+fn events_read_the_same_from_either_side() {
+    let mut app = app();
+    let a = spawn(
+        &mut app,
+        Vec2::new(10.0, 20.0),
+        Vec2::splat(10.0),
+        Vec2::ZERO,
+    );
+    let b = spawn(
+        &mut app,
+        Vec2::new(10.0, 12.0),
+        Vec2::splat(10.0),
+        Vec2::ZERO,
+    );
+    let c = spawn(
+        &mut app,
+        Vec2::new(100.0, 0.0),
+        Vec2::splat(10.0),
+        Vec2::ZERO,
+    );
+    let events = tick(&mut app);
+    assert_eq!(events.len(), 1);
+
+    let from_a = events[0].seen_from(a).unwrap();
+    let from_b = events[0].seen_from(b).unwrap();
+    assert_eq!((from_a.entity_a, from_a.entity_b), (a, b));
+    assert_eq!((from_b.entity_a, from_b.entity_b), (b, a));
+    // `a` sits on top of `b`
+    assert_eq!(from_a.collision, Collision::Top);
+    assert_eq!(from_b.collision, Collision::Bottom);
+    assert_eq!(from_a.normal, -from_b.normal);
+    assert_eq!(from_a.depth, from_b.depth);
+    assert!(events[0].seen_from(c).is_none());
+}
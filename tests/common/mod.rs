@@ -0,0 +1,119 @@
+// helpers shared by the integration tests, not every test file uses all of them
+#![allow(dead_code)]
+
+use bevy::{
+    input::{keyboard::KeyboardInput, ButtonState},
+    prelude::*,
+};
+use flappy_bird::{
+    cli::Args,
+    game::GameState,
+    headless,
+    physics::{GravityScale, Velocity},
+    pipes::Pipes,
+    player::{Player, PlayerId},
+    replay::{ReplayAction, ReplayInput, ReplayPlayback},
+};
+
+// generous upper bound for anything that should happen within a few seconds of game time
+pub const MAX_TICKS: u32 = 60 * 20;
+
+// headless game following `playback`, or driven by the keyboard without one
+pub fn app(args: Args, playback: Option<ReplayPlayback>) -> App {
+    let mut app = headless::app(Args {
+        headless: true,
+        ..args
+    })
+    .unwrap();
+    app.world.remove_resource::<ReplayPlayback>();
+    if let Some(playback) = playback {
+        app.insert_resource(playback);
+    }
+    app.finish();
+    app.cleanup();
+    app
+}
+
+// a flap every `interval` ticks, for longer than any of the tests run
+pub fn flaps(interval: u32) -> Vec<ReplayInput> {
+    (1..100)
+        .map(|i| ReplayInput {
+            tick: i * interval,
+            action: ReplayAction::Flap,
+        })
+        .collect()
+}
+
+pub fn state(app: &App) -> GameState {
+    *app.world.resource::<State<GameState>>().get()
+}
+
+pub fn tick_until(app: &mut App, condition: impl Fn(&App) -> bool) {
+    for _ in 0..MAX_TICKS {
+        if condition(app) {
+            return;
+        }
+        app.update();
+    }
+    panic!(
+        "condition not met after {MAX_TICKS} ticks, state is {:?}",
+        state(app)
+    );
+}
+
+// from the main menu straight into a new run
+pub fn start_waiting(app: &mut App) {
+    tick_until(app, |app| state(app) == GameState::MainMenu);
+    app.world
+        .resource_mut::<NextState<GameState>>()
+        .set(GameState::Waiting);
+    tick_until(app, |app| state(app) == GameState::Waiting);
+}
+
+pub fn key(key_code: KeyCode, state: ButtonState) -> KeyboardInput {
+    KeyboardInput {
+        scan_code: 0,
+        key_code: Some(key_code),
+        state,
+        window: Entity::PLACEHOLDER,
+    }
+}
+
+// pins every bird at a height so only the pipes moving towards them matter
+pub fn hover_birds(app: &mut App, y: f32) {
+    let mut query = app
+        .world
+        .query_filtered::<(&mut Transform, &mut Velocity, &mut GravityScale), With<Player>>();
+    for (mut transform, mut velocity, mut gravity_scale) in query.iter_mut(&mut app.world) {
+        transform.translation.y = y;
+        **velocity = Vec2::ZERO;
+        **gravity_scale = 0.0;
+    }
+}
+
+// keeps the birds in the middle of whichever gap is coming up next
+pub fn follow_gap(app: &mut App) {
+    let mut pipes_query = app.world.query_filtered::<&GlobalTransform, With<Pipes>>();
+    let Some(gap) = pipes_query
+        .iter(&app.world)
+        .map(|transform| transform.translation())
+        .filter(|translation| translation.x > -20.0)
+        .min_by(|a, b| a.x.total_cmp(&b.x))
+    else {
+        return;
+    };
+    hover_birds(app, gap.y);
+}
+
+// every bird's position and velocity, in player order
+pub fn birds(app: &mut App) -> Vec<(PlayerId, Vec3, Vec2)> {
+    let mut query = app
+        .world
+        .query::<(&PlayerId, &GlobalTransform, &Velocity)>();
+    let mut birds = query
+        .iter(&app.world)
+        .map(|(player_id, transform, velocity)| (*player_id, transform.translation(), **velocity))
+        .collect::<Vec<_>>();
+    birds.sort_by_key(|(player_id, _, _)| **player_id);
+    birds
+}
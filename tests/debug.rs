@@ -0,0 +1,57 @@
+use bevy::{input::ButtonState, prelude::*};
+use flappy_bird::{
+    cli::Args,
+    debug::{DebugOverlay, DebugPanel},
+    game::GameState,
+    replay::ReplayPlayback,
+};
+
+mod common;
+use common::{key, state, tick_until};
+
+fn app() -> App {
+    let mut app = common::app(
+        Args {
+            seed: Some(2),
+            ..default()
+        },
+        Some(ReplayPlayback::scripted(2, Vec::new())),
+    );
+    tick_until(&mut app, |app| state(app) == GameState::MainMenu);
+    app
+}
+
+fn press_f3(app: &mut App) {
+    app.world.send_event(key(KeyCode::F3, ButtonState::Pressed));
+    app.update();
+    app.world
+        .send_event(key(KeyCode::F3, ButtonState::Released));
+    app.update();
+}
+
+fn panel_text(app: &mut App) -> Option<String> {
+    let mut query = app.world.query_filtered::<&Text, With<DebugPanel>>();
+    query
+        .get_single(&app.world)
+        .ok()
+        .map(|text| text.sections[0].value.clone())
+}
+
+#[test]
+fn f3_toggles_the_panel() {
+    let mut app = app();
+    assert!(!**app.world.resource::<DebugOverlay>());
+    assert_eq!(panel_text(&mut app), None);
+
+    // nothing to draw the colliders with headless, but the panel still shows up
+    press_f3(&mut app);
+    assert!(**app.world.resource::<DebugOverlay>());
+    let text = panel_text(&mut app).unwrap();
+    assert!(text.starts_with("state: "), "{text}");
+    assert!(text.contains("score: 0"), "{text}");
+    assert!(text.contains("birds: 1"), "{text}");
+
+    press_f3(&mut app);
+    assert!(!**app.world.resource::<DebugOverlay>());
+    assert_eq!(panel_text(&mut app), None);
+}
@@ -0,0 +1,80 @@
+use bevy::prelude::*;
+use flappy_bird::{
+    cli::Args,
+    difficulty::Difficulty,
+    game::{DistanceTraveled, GameState},
+    pipes::Pipes,
+    replay::ReplayPlayback,
+    tuning::Tuning,
+};
+
+mod common;
+use common::{flaps, state, tick_until};
+
+const MAX_TICKS: u32 = 60 * 10;
+
+#[test]
+fn curves_ramp_over_distance() {
+    let tuning = Tuning::default();
+    for difficulty in Difficulty::ALL {
+        let curve = tuning.difficulty.curve(difficulty);
+        let start = tuning.level(difficulty, 0.0);
+        let end = tuning.level(difficulty, curve.ramp_distance);
+        assert_eq!((start.speed, start.gap), (curve.speed.0, curve.gap.0));
+        assert_eq!((end.speed, end.gap), (curve.speed.1, curve.gap.1));
+        assert_eq!(tuning.level(difficulty, curve.ramp_distance * 2.0), end);
+
+        let middle = tuning.level(difficulty, curve.ramp_distance * 0.5);
+        assert!(middle.speed > start.speed && middle.speed < end.speed);
+        assert!(middle.gap < start.gap && middle.gap > end.gap);
+    }
+}
+
+#[test]
+fn presets_are_picked_on_the_command_line() {
+    let args = Args::parse(["--difficulty", "HARD"].map(String::from).into_iter()).unwrap();
+    assert_eq!(args.difficulty, Some(Difficulty::Hard));
+    assert!(Args::parse(["--difficulty", "brutal"].map(String::from).into_iter()).is_err());
+}
+
+fn distance(app: &App) -> f32 {
+    **app.world.resource::<DistanceTraveled>()
+}
+
+// distance covered in the first tick of a run and the gap of the first pipes
+fn run(difficulty: Difficulty) -> (f32, f32) {
+    let mut app = common::app(
+        Args {
+            seed: Some(3),
+            difficulty: Some(difficulty),
+            ..default()
+        },
+        Some(ReplayPlayback::scripted(3, flaps(20))),
+    );
+    tick_until(&mut app, |app| state(app) == GameState::Playing);
+    let start = distance(&app);
+    app.update();
+    let step = distance(&app) - start;
+
+    let mut query = app.world.query::<&Pipes>();
+    for _ in 0..MAX_TICKS {
+        if let Some(pipes) = query.iter(&app.world).next() {
+            return (step, pipes.gap_spacing);
+        }
+        app.update();
+    }
+    panic!("no pipes after {MAX_TICKS} ticks");
+}
+
+#[test]
+fn harder_presets_are_faster_and_tighter() {
+    let tuning = Tuning::default();
+    let (easy_step, easy_gap) = run(Difficulty::Easy);
+    let (hard_step, hard_gap) = run(Difficulty::Hard);
+    assert!(hard_step > easy_step);
+    assert!(hard_gap < easy_gap);
+    // the first pipes are barely into the ramp
+    let easy = tuning.level(Difficulty::Easy, 0.0);
+    assert!((easy_step - tuning.game_speed * easy.speed / 60.0).abs() < 0.01);
+    assert!(easy_gap <= tuning.gap_spacing * easy.gap);
+}
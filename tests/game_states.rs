@@ -0,0 +1,146 @@
+use bevy::{app::StateTransition, input::ButtonState, prelude::*};
+use flappy_bird::{
+    cli::Args,
+    game::{DistanceTraveled, GameScore, GameState},
+    physics::{GravityScale, Velocity},
+    pipes::Pipes,
+    player::{DeathCause, Player, RunDeath},
+};
+
+mod common;
+use common::{hover_birds, key, start_waiting, state, tick_until};
+
+// headless game driven by the keyboard instead of a script, sitting in `Waiting`
+fn app() -> App {
+    let mut app = common::app(
+        Args {
+            seed: Some(1),
+            ..default()
+        },
+        None,
+    );
+    start_waiting(&mut app);
+    app
+}
+
+fn score(app: &App) -> i32 {
+    **app.world.resource::<GameScore>()
+}
+
+// the release is only picked up by the next update
+fn press_space(app: &mut App) {
+    app.world
+        .send_event(key(KeyCode::Space, ButtonState::Pressed));
+    app.update();
+    app.world
+        .send_event(key(KeyCode::Space, ButtonState::Released));
+}
+
+fn start_playing(app: &mut App) {
+    press_space(app);
+    tick_until(app, |app| state(app) == GameState::Playing);
+}
+
+// gap height of the closest pipe pair once it has spawned
+fn next_gap(app: &mut App) -> f32 {
+    tick_until(app, |app| {
+        app.world
+            .iter_entities()
+            .any(|entity| entity.contains::<Pipes>())
+    });
+    let mut query = app.world.query_filtered::<&Transform, With<Pipes>>();
+    query.iter(&app.world).next().unwrap().translation.y
+}
+
+fn release_player(app: &mut App) {
+    let mut query = app
+        .world
+        .query_filtered::<&mut GravityScale, With<Player>>();
+    for mut gravity_scale in query.iter_mut(&mut app.world) {
+        **gravity_scale = 1.0;
+    }
+}
+
+#[test]
+fn flap_in_waiting_starts_playing() {
+    let mut app = app();
+    for _ in 0..30 {
+        app.update();
+    }
+    assert_eq!(state(&app), GameState::Waiting);
+
+    start_playing(&mut app);
+    assert_eq!(state(&app), GameState::Playing);
+}
+
+#[test]
+fn hitting_the_ground_kills_the_player() {
+    let mut app = app();
+    start_playing(&mut app);
+
+    tick_until(&mut app, |app| state(app) == GameState::Dead);
+    assert_eq!(**app.world.resource::<RunDeath>(), Some(DeathCause::Ground));
+}
+
+#[test]
+fn hitting_a_pipe_stops_then_kills_the_player() {
+    let mut app = app();
+    start_playing(&mut app);
+
+    // just below the gap, inside the bottom pipe
+    let gap = next_gap(&mut app);
+    hover_birds(&mut app, gap - 32.0);
+
+    tick_until(&mut app, |app| state(app) == GameState::Stopped);
+    assert_eq!(**app.world.resource::<RunDeath>(), Some(DeathCause::Pipe));
+
+    tick_until(&mut app, |app| state(app) == GameState::Dead);
+    assert_eq!(**app.world.resource::<RunDeath>(), Some(DeathCause::Pipe));
+}
+
+#[test]
+fn passing_a_pipe_area_scores_once() {
+    let mut app = app();
+    start_playing(&mut app);
+
+    let gap = next_gap(&mut app);
+    hover_birds(&mut app, gap);
+    assert_eq!(score(&app), 0);
+
+    // until the first pipe pair is well behind the bird
+    tick_until(&mut app, |app| {
+        let distance = **app.world.resource::<DistanceTraveled>();
+        distance > 150.0
+    });
+    assert_eq!(state(&app), GameState::Playing);
+    assert_eq!(score(&app), 1);
+}
+
+#[test]
+fn restart_resets_pipes_player_and_score() {
+    let mut app = app();
+    start_playing(&mut app);
+
+    let gap = next_gap(&mut app);
+    hover_birds(&mut app, gap);
+    tick_until(&mut app, |app| score(app) == 1);
+    release_player(&mut app);
+    tick_until(&mut app, |app| state(app) == GameState::Dead);
+
+    // apply the restart without simulating a tick of the new run
+    press_space(&mut app);
+    app.world.run_schedule(StateTransition);
+    assert_eq!(state(&app), GameState::Waiting);
+
+    assert_eq!(score(&app), 0);
+    assert_eq!(**app.world.resource::<DistanceTraveled>(), 0.0);
+    assert_eq!(**app.world.resource::<RunDeath>(), None);
+    let mut pipes = app.world.query_filtered::<Entity, With<Pipes>>();
+    assert_eq!(pipes.iter(&app.world).count(), 0);
+    let mut player = app
+        .world
+        .query_filtered::<(&Transform, &Velocity), With<Player>>();
+    let (transform, velocity) = player.single(&app.world);
+    assert_eq!(transform.translation.xy(), Vec2::ZERO);
+    assert_eq!(**velocity, Vec2::ZERO);
+}
@@ -0,0 +1,43 @@
+use bevy::{
+    prelude::*,
+    render::texture::{CompressedImageFormats, ImageSampler, ImageType},
+};
+use flappy_bird::{hud::UiSprite, rating::Medal};
+
+fn spritesheet() -> Image {
+    let bytes = std::fs::read("assets/sprites/spritesheet.png").unwrap();
+    Image::from_buffer(
+        &bytes,
+        ImageType::Extension("png"),
+        CompressedImageFormats::NONE,
+        true,
+        ImageSampler::Default,
+    )
+    .unwrap()
+}
+
+// whether any pixel of row `y` between `min_x` and `max_x` shows
+fn row_shows(image: &Image, y: f32, min_x: f32, max_x: f32) -> bool {
+    let width = image.width() as usize;
+    (min_x as usize..max_x as usize).any(|x| image.data[(y as usize * width + x) * 4 + 3] > 0)
+}
+
+#[test]
+fn medal_rects_fit_the_spritesheet() {
+    let image = spritesheet();
+    for medal in Medal::ALL {
+        let rect = UiSprite::Medal(medal).rect();
+        let (min, max) = (rect.min, rect.max);
+        // the first and last rows hold the medal's tips, the ones around it are empty
+        assert!(row_shows(&image, min.y, min.x, max.x), "{medal:?} top");
+        assert!(
+            row_shows(&image, max.y - 1.0, min.x, max.x),
+            "{medal:?} bottom"
+        );
+        assert!(
+            !row_shows(&image, min.y - 1.0, min.x, max.x),
+            "{medal:?} above"
+        );
+        assert!(!row_shows(&image, max.y, min.x, max.x), "{medal:?} below");
+    }
+}
@@ -0,0 +1,189 @@
+use std::net::Ipv4Addr;
+
+use bevy::prelude::*;
+use flappy_bird::{
+    game::GameState, headless, lobby::LobbyClient, networking::NetSession, player::PlayerId,
+    replay::ReplayPlayback,
+};
+use flappy_lobby::{
+    protocol::{LobbyError, RoomCode},
+    server::Server,
+};
+
+mod common;
+use common::{birds, flaps, state};
+
+const MAX_UPDATES: u32 = 60 * 60;
+
+// headless game sitting in the online screen of a lobby on a local port
+fn player(server: &Server, relay: bool) -> App {
+    let mut app = common::app(default(), None);
+    app.insert_resource(LobbyClient::connect(server.local_addr().unwrap(), relay).unwrap());
+
+    app.update();
+    app.world
+        .resource_mut::<NextState<GameState>>()
+        .set(GameState::Lobby);
+    app.update();
+    assert_eq!(state(&app), GameState::Lobby);
+    app
+}
+
+fn server() -> Server {
+    Server::bind((Ipv4Addr::LOCALHOST, 0).into()).unwrap()
+}
+
+fn client(app: &mut App) -> Mut<'_, LobbyClient> {
+    app.world.resource_mut::<LobbyClient>()
+}
+
+fn update_until(
+    server: &mut Server,
+    players: &mut [&mut App],
+    condition: impl Fn(&mut [&mut App]) -> bool,
+) {
+    for _ in 0..MAX_UPDATES {
+        if condition(players) {
+            return;
+        }
+        server.poll().unwrap();
+        for app in players.iter_mut() {
+            app.update();
+        }
+    }
+    panic!("condition not met after {MAX_UPDATES} updates");
+}
+
+#[derive(Debug, PartialEq)]
+struct RoundResult {
+    seed: u64,
+    birds: Vec<(PlayerId, Vec3, Vec2)>,
+    relayed: bool,
+    desync_frame: Option<u32>,
+}
+
+// both players meet in a room, ready up and race until both birds are down
+fn race(relay: bool) -> (Server, RoundResult, RoundResult) {
+    let mut server = server();
+    let mut host = player(&server, relay);
+    let mut guest = player(&server, relay);
+
+    client(&mut host).create(Some(21));
+    update_until(&mut server, &mut [&mut host], |players| {
+        client(players[0]).room().is_some()
+    });
+    let code = client(&mut host).room().unwrap().code;
+    client(&mut guest).join(code);
+    update_until(&mut server, &mut [&mut host, &mut guest], |players| {
+        players
+            .iter_mut()
+            .all(|app| client(app).room().is_some_and(|room| room.ready.len() == 2))
+    });
+
+    for app in [&mut host, &mut guest] {
+        client(app).set_ready(true);
+    }
+    update_until(&mut server, &mut [&mut host, &mut guest], |players| {
+        players
+            .iter()
+            .all(|app| app.world.contains_resource::<NetSession>())
+    });
+
+    for (app, interval) in [(&mut host, 37), (&mut guest, 41)] {
+        app.insert_resource(ReplayPlayback::scripted(0, flaps(interval)));
+    }
+
+    // each side is left alone once its round is over, so the results are from the same tick
+    let mut peers = [host, guest];
+    let mut results = [None, None];
+    for _ in 0..MAX_UPDATES {
+        server.poll().unwrap();
+        for (app, result) in peers.iter_mut().zip(results.iter_mut()) {
+            if result.is_some() {
+                continue;
+            }
+            app.update();
+            if state(app) == GameState::Dead {
+                let session = app.world.resource::<NetSession>();
+                *result = Some(RoundResult {
+                    seed: headless::summary(&app.world).seed,
+                    relayed: session.relayed(),
+                    desync_frame: session.desync_frame(),
+                    birds: birds(app),
+                });
+            }
+        }
+        if let [Some(_), Some(_)] = results {
+            let [host, guest] = results;
+            return (server, host.unwrap(), guest.unwrap());
+        }
+    }
+    panic!("round didn't finish after {MAX_UPDATES} updates");
+}
+
+#[test]
+fn players_race_after_meeting_in_a_room() {
+    let (server, host, guest) = race(false);
+    assert_eq!(server.relayed(), 0);
+    assert_eq!(host.seed, 21);
+    assert_eq!(host.birds.len(), 2);
+    assert!(!host.relayed);
+    assert_eq!(host.desync_frame, None);
+    assert_eq!(host, guest);
+}
+
+#[test]
+fn relayed_players_agree_on_the_round() {
+    let (server, host, guest) = race(true);
+    assert!(server.relayed() > 0);
+    assert!(host.relayed);
+    assert_eq!(host.desync_frame, None);
+    assert_eq!(host, guest);
+}
+
+#[test]
+fn rooms_turn_away_strangers() {
+    let mut server = server();
+    let mut host = player(&server, false);
+    let mut guest = player(&server, false);
+    let mut stranger = player(&server, false);
+
+    client(&mut stranger).join(RoomCode::parse("zzzz").unwrap());
+    update_until(&mut server, &mut [&mut stranger], |players| {
+        client(players[0]).error().is_some()
+    });
+    assert_eq!(
+        client(&mut stranger).error(),
+        Some(LobbyError::RoomNotFound)
+    );
+
+    client(&mut host).create(None);
+    update_until(&mut server, &mut [&mut host], |players| {
+        client(players[0]).room().is_some()
+    });
+    let code = client(&mut host).room().unwrap().code;
+    for app in [&mut guest, &mut stranger] {
+        client(app).join(code);
+        update_until(&mut server, &mut [app], |players| {
+            let client = client(players[0]);
+            client.room().is_some() || client.error().is_some()
+        });
+    }
+    assert_eq!(client(&mut guest).room().unwrap().player, 1);
+    assert_eq!(client(&mut stranger).error(), Some(LobbyError::RoomFull));
+
+    update_until(&mut server, &mut [&mut host], |players| {
+        client(players[0]).room().unwrap().ready.len() == 2
+    });
+
+    // leaving the screen leaves the room
+    guest
+        .world
+        .resource_mut::<NextState<GameState>>()
+        .set(GameState::MainMenu);
+    update_until(&mut server, &mut [&mut host, &mut guest], |players| {
+        client(players[0]).room().unwrap().ready.len() == 1
+    });
+    assert!(!guest.world.contains_resource::<LobbyClient>());
+    assert_eq!(server.rooms(), 1);
+}
@@ -0,0 +1,268 @@
+use std::fs;
+
+use bevy::prelude::*;
+use flappy_bird::{
+    desync::desync_dir,
+    difficulty::Difficulty,
+    game::{GameScore, GameState},
+    headless::{self, RunSummary},
+    networking::NetSession,
+    patterns::{PatternStep, PipePattern, PipePatterns},
+    player::{DeadPlayer, PlayerId, PlayerScore},
+    replay::ReplayPlayback,
+    save::SavePath,
+    tuning::Tuning,
+};
+
+mod common;
+use common::{birds, flaps, follow_gap, state};
+
+// a round lasts a few seconds, waiting on the other peer included
+const MAX_UPDATES: u32 = 60 * 60;
+
+#[derive(Debug, PartialEq)]
+struct RoundResult {
+    summary: RunSummary,
+    dead_player: Option<PlayerId>,
+    birds: Vec<(PlayerId, Vec3, Vec2)>,
+}
+
+// headless peer flapping every `interval` ticks
+fn peer(session: NetSession, interval: u32) -> App {
+    let mut app = common::app(
+        default(),
+        Some(ReplayPlayback::scripted(0, flaps(interval))),
+    );
+    app.insert_resource(session);
+    app
+}
+
+fn result(app: &mut App) -> RoundResult {
+    RoundResult {
+        summary: headless::summary(&app.world),
+        dead_player: **app.world.resource::<DeadPlayer>(),
+        birds: birds(app),
+    }
+}
+
+// audio is never played headless, so every sound started is still around
+fn sounds(app: &mut App) -> usize {
+    app.world
+        .query_filtered::<(), With<Handle<AudioSource>>>()
+        .iter(&app.world)
+        .count()
+}
+
+// runs both peers in turns of `lag` updates each until their round is over, returns the results,
+// the rollbacks and the sounds started by each peer
+fn race(seed: u64, lag: u32, intervals: [u32; 2]) -> (RoundResult, RoundResult, u32, [usize; 2]) {
+    let (host, guest) = NetSession::loopback(seed).unwrap();
+    let mut peers = [peer(host, intervals[0]), peer(guest, intervals[1])];
+    let mut results = [None, None];
+    let mut rollbacks = 0;
+    let mut sounds = [0; 2];
+
+    for _ in 0..MAX_UPDATES / lag {
+        for ((app, result), sounds) in peers
+            .iter_mut()
+            .zip(results.iter_mut())
+            .zip(sounds.iter_mut())
+        {
+            for _ in 0..lag {
+                if result.is_some() {
+                    break;
+                }
+                app.update();
+                if state(app) == GameState::Dead {
+                    let session = app.world.resource::<NetSession>();
+                    assert_eq!(session.desync_frame(), None);
+                    rollbacks += session.rollbacks();
+                    *result = Some(self::result(app));
+                    *sounds = self::sounds(app);
+                }
+            }
+        }
+        if results.iter().all(Option::is_some) {
+            let [host, guest] = results;
+            return (host.unwrap(), guest.unwrap(), rollbacks, sounds);
+        }
+    }
+    panic!("round didn't finish after {MAX_UPDATES} updates");
+}
+
+#[test]
+fn peers_agree_on_the_round() {
+    let (host, guest, _, _) = race(5, 1, [37, 41]);
+    assert_eq!(host.summary.seed, 5);
+    assert!(host.dead_player.is_some());
+    assert_eq!(host.birds.len(), 2);
+    assert_eq!(host, guest);
+}
+
+#[test]
+fn peers_agree_after_rolling_back() {
+    let (host, guest, rollbacks, _) = race(9, 8, [37, 41]);
+    assert!(rollbacks > 0, "peers never had to roll back");
+    assert_eq!(host, guest);
+}
+
+#[test]
+fn rolling_back_does_not_replay_sounds() {
+    // flapping together, every rollback to the peer's flap runs the local one again
+    let (_, _, rollbacks, steady) = race(9, 1, [37, 37]);
+    assert_eq!(rollbacks, 0);
+    let (_, _, rollbacks, lagging) = race(9, 8, [37, 37]);
+    assert!(rollbacks > 0, "peers never had to roll back");
+    // a flap only learned about while rolling back goes unheard, nothing is heard twice
+    for (steady, lagging) in steady.into_iter().zip(lagging) {
+        assert!(lagging > 0);
+        assert!(
+            lagging <= steady,
+            "{lagging} sounds, {steady} without rolling back"
+        );
+    }
+}
+
+#[test]
+fn racing_birds_score_each_gap_once() {
+    let (host, guest) = NetSession::loopback(4).unwrap();
+    let mut peers = [peer(host, 37), peer(guest, 41)];
+    for _ in 0..MAX_UPDATES {
+        for app in peers.iter_mut() {
+            follow_gap(app);
+            app.update();
+        }
+        let host = &mut peers[0];
+        assert_ne!(state(host), GameState::Dead);
+        if **host.world.resource::<GameScore>() == 0 {
+            continue;
+        }
+
+        // both birds flew through the same gap together
+        let mut query = host.world.query::<(&PlayerId, &PlayerScore)>();
+        let mut scores = query
+            .iter(&host.world)
+            .map(|(player_id, score)| (**player_id, score.score))
+            .collect::<Vec<_>>();
+        scores.sort();
+        assert_eq!(scores, [(0, 1), (1, 1)]);
+        assert_eq!(**host.world.resource::<GameScore>(), 1);
+        return;
+    }
+    panic!("no gap was passed after {MAX_UPDATES} updates");
+}
+
+#[test]
+fn guests_race_at_the_host_difficulty() {
+    let (host, guest) = NetSession::loopback(8).unwrap();
+    let mut peers = [peer(host, 37), peer(guest, 41)];
+    peers[0].insert_resource(Difficulty::Hard);
+    peers[1].insert_resource(Difficulty::Easy);
+
+    for _ in 0..MAX_UPDATES {
+        for app in peers.iter_mut() {
+            app.update();
+        }
+        if peers.iter().all(|app| state(app) == GameState::Playing) {
+            for app in peers.iter() {
+                assert_eq!(*app.world.resource::<Difficulty>(), Difficulty::Hard);
+            }
+            return;
+        }
+    }
+    panic!("peers never started racing");
+}
+
+fn assert_refused(mut peers: [App; 2], reason: &str) {
+    for _ in 0..60 {
+        for app in peers.iter_mut() {
+            app.update();
+        }
+    }
+    for app in peers.iter() {
+        let session = app.world.resource::<NetSession>();
+        assert!(!session.connected());
+        assert_eq!(session.refused(), Some(reason));
+        assert_eq!(state(app), GameState::MainMenu);
+    }
+}
+
+#[test]
+fn peers_with_different_tuning_refuse_to_race() {
+    let (host, guest) = NetSession::loopback(6).unwrap();
+    let mut peers = [peer(host, 37), peer(guest, 41)];
+    peers[1].insert_resource(Tuning {
+        gravity: 300.0,
+        ..default()
+    });
+    assert_refused(peers, "tuning doesn't match the peer's");
+}
+
+#[test]
+fn peers_with_different_patterns_refuse_to_race() {
+    let (host, guest) = NetSession::loopback(10).unwrap();
+    let mut peers = [peer(host, 37), peer(guest, 41)];
+    peers[1].insert_resource(PipePatterns(vec![PipePattern {
+        weight: (1.0, 1.0),
+        steps: vec![PatternStep {
+            height: 0.5,
+            spacing: 1.0,
+            gap: 1.0,
+            motion: None,
+        }],
+    }]));
+    assert_refused(peers, "pipe patterns don't match the peer's");
+}
+
+#[test]
+fn peers_report_a_desync() {
+    let save_path = SavePath(Some(
+        std::env::temp_dir()
+            .join(format!("flappy_bird_desync_test_{}", std::process::id()))
+            .join("save"),
+    ));
+    let dir = desync_dir(&save_path);
+    let _ = fs::remove_dir_all(&dir);
+
+    let (host, guest) = NetSession::loopback(3).unwrap();
+    let mut peers = [peer(host, 37), peer(guest, 41)];
+    for app in peers.iter_mut() {
+        app.insert_resource(SavePath(save_path.0.clone()));
+    }
+
+    // only the guest's score jumps, as if it had counted a pipe the host didn't
+    let mut corrupted = false;
+    for _ in 0..MAX_UPDATES {
+        for app in peers.iter_mut() {
+            app.update();
+        }
+        let [host, guest] = &mut peers;
+        if !corrupted && state(guest) == GameState::Playing {
+            **guest.world.resource_mut::<GameScore>() += 1;
+            corrupted = true;
+        }
+        let reported = fs::read_dir(&dir).map_or(0, |entries| entries.count());
+        if reported == 2 {
+            let desync_frame = |app: &App| app.world.resource::<NetSession>().desync_frame();
+            assert!(desync_frame(host).is_some());
+            assert_eq!(desync_frame(host), desync_frame(guest));
+            break;
+        }
+        assert_ne!(state(host), GameState::Dead, "desync was never reported");
+    }
+
+    for entry in fs::read_dir(&dir).unwrap() {
+        let report = fs::read_to_string(entry.unwrap().path()).unwrap();
+        let score = report
+            .lines()
+            .find(|line| line.starts_with("score"))
+            .unwrap();
+        assert!(score.ends_with("diverged"), "{report}");
+        let distance = report
+            .lines()
+            .find(|line| line.starts_with("distance"))
+            .unwrap();
+        assert!(!distance.ends_with("diverged"), "{report}");
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
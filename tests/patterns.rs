@@ -0,0 +1,167 @@
+use std::collections::BTreeMap;
+
+use bevy::prelude::*;
+use flappy_bird::{
+    cli::Args,
+    difficulty::Difficulty,
+    game::GameState,
+    patterns::{PatternStep, PipePattern, PipePatterns},
+    pipes::{MotionKind, PipeMotion, Pipes},
+    replay::{Replay, ReplayAction, ReplayConfig, ReplayInput, ReplayPlayback},
+    tuning::{Tuning, TuningError},
+};
+
+mod common;
+use common::{follow_gap, state};
+
+const MAX_TICKS: u32 = 60 * 30;
+
+fn step(height: f32) -> PatternStep {
+    PatternStep {
+        height,
+        spacing: 1.0,
+        gap: 1.0,
+        motion: None,
+    }
+}
+
+#[test]
+fn shipped_patterns_are_valid() {
+    let mut count = 0;
+    for entry in std::fs::read_dir("assets/patterns").unwrap() {
+        let path = entry.unwrap().path();
+        let contents = std::fs::read_to_string(&path).unwrap();
+        if let Err(err) = PipePattern::parse(&contents) {
+            panic!("{path:?}: {err}");
+        }
+        count += 1;
+    }
+    assert!(count > 0);
+}
+
+#[test]
+fn broken_patterns_are_reported() {
+    let valid = "(weight: (0.1, 0.2), steps: [(height: 0.5), (height: 1.0, spacing: 0.8)])";
+    let pattern = PipePattern::parse(valid).unwrap();
+    assert_eq!(pattern.steps[0], step(0.5));
+
+    for contents in ["(weight: (0.1, 0.2))", &valid.replace("height", "heigth")] {
+        assert!(matches!(
+            PipePattern::parse(contents),
+            Err(TuningError::Parse(_))
+        ));
+    }
+    for contents in [
+        valid.replace("0.1", "-0.1"),
+        valid.replace("1.0,", "1.5,"),
+        valid.replace("0.8", "0.0"),
+        valid.replace("(height: 0.5)", "(height: 0.5, gap: 1.2)"),
+        "(weight: (0.1, 0.2), steps: [])".to_string(),
+    ] {
+        assert!(matches!(
+            PipePattern::parse(&contents),
+            Err(TuningError::Invalid(_))
+        ));
+    }
+}
+
+#[test]
+fn spawner_plays_patterns_back_to_back() {
+    let inputs = vec![ReplayInput {
+        tick: 10,
+        action: ReplayAction::Flap,
+    }];
+    let mut app = common::app(
+        Args {
+            seed: Some(9),
+            ..default()
+        },
+        Some(ReplayPlayback::scripted(9, inputs)),
+    );
+    let mut tuning = Tuning::default();
+    tuning.difficulty.normal.gap = (1.0, 1.0);
+    tuning.difficulty.normal.spacing = (1.0, 1.0);
+    tuning.difficulty.normal.moving = (0.0, 0.0);
+    app.insert_resource(tuning);
+    // picked so often random pairs hardly ever come up
+    let zigzag = PipePattern {
+        weight: (1000.0, 1000.0),
+        steps: vec![
+            step(0.0),
+            PatternStep {
+                spacing: 1.5,
+                motion: Some(MotionKind::Oscillate),
+                ..step(1.0)
+            },
+        ],
+    };
+    app.insert_resource(PipePatterns(vec![zigzag]));
+
+    let mut spawned = BTreeMap::new();
+    for _ in 0..MAX_TICKS {
+        follow_gap(&mut app);
+        app.update();
+        let mut query = app.world.query::<(&Pipes, Option<&PipeMotion>)>();
+        for (pipes, motion) in query.iter(&app.world) {
+            spawned.insert(
+                pipes.spawn_position.x as i32,
+                (pipes.spawn_position.y, motion.is_some()),
+            );
+        }
+        if spawned.len() >= 8 {
+            break;
+        }
+    }
+    assert_eq!(state(&app), GameState::Playing);
+    assert!(spawned.len() >= 8, "only {} pairs spawned", spawned.len());
+
+    let pairs = spawned.into_iter().collect::<Vec<_>>();
+    let low = pairs[0].1 .0;
+    let high = pairs[1].1 .0;
+    assert!(low < high);
+    for (index, (x, (y, moving))) in pairs.iter().enumerate() {
+        assert_eq!(*moving, index % 2 == 1);
+        if index % 2 == 0 {
+            assert_eq!(*y, low);
+        } else {
+            // oscillating pairs are kept far enough from the edge to swing
+            assert!(*y < high + 0.001);
+        }
+        // the high step leaves more room after it
+        if let Some((next, _)) = pairs.get(index + 1) {
+            let gap = (next - x) as f32;
+            let expected = if index % 2 == 0 { 75.0 } else { 75.0 * 1.5 };
+            assert!((gap - expected).abs() <= 1.0, "{gap} after pair {index}");
+        }
+    }
+}
+
+#[test]
+fn patterns_are_part_of_a_replay() {
+    let patterns = PipePatterns(vec![PipePattern {
+        weight: (0.0, 0.5),
+        steps: vec![
+            step(0.25),
+            PatternStep {
+                gap: 0.8,
+                motion: Some(MotionKind::DriftDown),
+                ..step(0.75)
+            },
+        ],
+    }]);
+    let replay = Replay {
+        seed: 3,
+        config: ReplayConfig {
+            tick_rate: 60.0,
+            tuning: Tuning::default(),
+            difficulty: Difficulty::Easy,
+            patterns,
+        },
+        score: 2,
+        inputs: vec![ReplayInput {
+            tick: 4,
+            action: ReplayAction::Flap,
+        }],
+    };
+    assert_eq!(Replay::decode(&replay.encode()).unwrap(), replay);
+}
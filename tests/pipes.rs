@@ -0,0 +1,115 @@
+use bevy::prelude::*;
+use flappy_bird::{
+    cli::Args,
+    collider::Collider,
+    game::{GameScore, GameState},
+    pipes::{Pipe, PipeArea, PipeMotion, Pipes},
+    replay::{ReplayAction, ReplayInput, ReplayPlayback},
+    tuning::Tuning,
+};
+
+mod common;
+use common::{follow_gap, state, tick_until};
+
+const MAX_TICKS: u32 = 60 * 30;
+
+// headless run where every pair moves, started with a single flap
+fn app() -> App {
+    let inputs = vec![ReplayInput {
+        tick: 10,
+        action: ReplayAction::Flap,
+    }];
+    let mut app = common::app(
+        Args {
+            seed: Some(5),
+            ..default()
+        },
+        Some(ReplayPlayback::scripted(5, inputs)),
+    );
+    let mut tuning = Tuning::default();
+    tuning.difficulty.normal.moving = (1.0, 1.0);
+    app.insert_resource(tuning);
+
+    tick_until(&mut app, |app| state(app) == GameState::Playing);
+    app
+}
+
+// height of a pair and of the opening between its pipes, measured from the pipes themselves
+fn measure(app: &mut App, entity: Entity) -> (f32, f32, Option<f32>) {
+    let y = app.world.get::<Transform>(entity).unwrap().translation.y;
+    let children = app.world.get::<Children>(entity).unwrap().to_vec();
+    let pipe_height = 160.0;
+    let mut edges = Vec::new();
+    let mut area = None;
+    for child in children.into_iter().map(|child| app.world.entity(child)) {
+        if child.contains::<Pipe>() {
+            edges.push(child.get::<Transform>().unwrap().translation.y.abs() - pipe_height * 0.5);
+        }
+        if child.contains::<PipeArea>() {
+            if let Some(Collider::Box { size }) = child.get::<Collider>() {
+                area = Some(size.y);
+            }
+        }
+    }
+    assert_eq!(edges.len(), 2);
+    assert_eq!(edges[0], edges[1]);
+    (y, edges[0] * 2.0, area)
+}
+
+#[test]
+fn moving_pipes_follow_their_motion() {
+    let mut app = app();
+    let mut seen = [false; 3];
+    for _ in 0..MAX_TICKS {
+        follow_gap(&mut app);
+        app.update();
+        if seen.iter().all(|seen| *seen) {
+            return;
+        }
+
+        let mut query = app.world.query::<(Entity, &Pipes, &PipeMotion)>();
+        let moving = query
+            .iter(&app.world)
+            .map(|(entity, pipes, motion)| (entity, pipes.clone(), *motion))
+            .collect::<Vec<_>>();
+        for (entity, pipes, motion) in moving {
+            let (y, gap, area) = measure(&mut app, entity);
+            match motion {
+                PipeMotion::Oscillate { amplitude } => {
+                    assert!((y - pipes.spawn_position.y).abs() <= amplitude + 0.001);
+                    assert!((gap - pipes.gap_spacing).abs() < 0.001);
+                    seen[0] |= (y - pipes.spawn_position.y).abs() > amplitude * 0.5;
+                }
+                PipeMotion::Pulse { amount } => {
+                    assert_eq!(y, pipes.spawn_position.y);
+                    assert!(gap <= pipes.gap_spacing + 0.001);
+                    assert!(gap >= pipes.gap_spacing - amount - 0.001);
+                    if let Some(area) = area {
+                        assert!((area - (gap - 1.0)).abs() < 0.001);
+                    }
+                    seen[1] |= gap < pipes.gap_spacing - amount * 0.5;
+                }
+                PipeMotion::Drift { offset } => {
+                    let moved = y - pipes.spawn_position.y;
+                    assert!(moved * offset >= 0.0 && moved.abs() <= offset.abs() + 0.001);
+                    seen[2] |= offset != 0.0 && (moved - offset).abs() < 0.001;
+                }
+            }
+        }
+    }
+    panic!("not every motion was seen in full: {seen:?}");
+}
+
+#[test]
+fn moving_gaps_still_score() {
+    let mut app = app();
+    for _ in 0..MAX_TICKS {
+        follow_gap(&mut app);
+        app.update();
+        assert_eq!(state(&app), GameState::Playing);
+        if **app.world.resource::<GameScore>() >= 5 {
+            return;
+        }
+    }
+    panic!("only scored {}", **app.world.resource::<GameScore>());
+}
@@ -0,0 +1,81 @@
+use bevy::{input::ButtonState, prelude::*};
+use flappy_bird::{
+    cli::Args,
+    game::{GameScore, GameState},
+    physics::GravityScale,
+    player::Player,
+    rating::{Medal, MedalThresholds, RunMedal},
+    save::SaveData,
+    tuning::Tuning,
+};
+
+mod common;
+use common::{follow_gap, key, start_waiting, state, tick_until, MAX_TICKS};
+
+#[test]
+fn medals_start_at_their_threshold() {
+    let thresholds = MedalThresholds::default();
+    for (score, medal) in [
+        (0, None),
+        (9, None),
+        (10, Some(Medal::Bronze)),
+        (19, Some(Medal::Bronze)),
+        (20, Some(Medal::Silver)),
+        (39, Some(Medal::Gold)),
+        (40, Some(Medal::Platinum)),
+        (400, Some(Medal::Platinum)),
+    ] {
+        assert_eq!(thresholds.medal_for(score), medal, "score {score}");
+    }
+}
+
+#[test]
+fn runs_are_saved_with_their_medal() {
+    let mut app = common::app(
+        Args {
+            seed: Some(5),
+            ..default()
+        },
+        None,
+    );
+    // low enough to reach in a couple of gaps
+    app.insert_resource(Tuning {
+        medals: MedalThresholds {
+            bronze: 1,
+            silver: 2,
+            gold: 3,
+            platinum: 4,
+        },
+        ..default()
+    });
+    start_waiting(&mut app);
+
+    app.world
+        .send_event(key(KeyCode::Space, ButtonState::Pressed));
+    app.update();
+    app.world
+        .send_event(key(KeyCode::Space, ButtonState::Released));
+    tick_until(&mut app, |app| state(app) == GameState::Playing);
+
+    for _ in 0..MAX_TICKS {
+        if **app.world.resource::<GameScore>() == 2 {
+            break;
+        }
+        follow_gap(&mut app);
+        app.update();
+    }
+    assert_eq!(**app.world.resource::<GameScore>(), 2);
+
+    // then let the bird drop
+    let mut query = app
+        .world
+        .query_filtered::<&mut GravityScale, With<Player>>();
+    for mut gravity_scale in query.iter_mut(&mut app.world) {
+        **gravity_scale = 1.0;
+    }
+    tick_until(&mut app, |app| state(app) == GameState::Dead);
+
+    assert_eq!(**app.world.resource::<RunMedal>(), Some(Medal::Silver));
+    let run = app.world.resource::<SaveData>().runs.last().unwrap();
+    assert_eq!((run.score, run.medal), (2, Some(Medal::Silver)));
+}
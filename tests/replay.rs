@@ -0,0 +1,40 @@
+use flappy_bird::{
+    difficulty::Difficulty,
+    patterns::PipePatterns,
+    replay::{Replay, ReplayAction, ReplayConfig, ReplayError, ReplayInput},
+    tuning::Tuning,
+};
+
+fn replay(inputs: Vec<ReplayInput>) -> Replay {
+    Replay {
+        seed: 1,
+        config: ReplayConfig {
+            tick_rate: 60.0,
+            tuning: Tuning::default(),
+            difficulty: Difficulty::Normal,
+            patterns: PipePatterns::default(),
+        },
+        score: 0,
+        inputs,
+    }
+}
+
+#[test]
+fn overflowing_ticks_are_refused() {
+    let last = ReplayInput {
+        tick: u32::MAX,
+        action: ReplayAction::Flap,
+    };
+    let mut bytes = replay(vec![last]).encode();
+    assert_eq!(Replay::decode(&bytes).unwrap().inputs, vec![last]);
+
+    // one more flap a tick after the last one there is, the count sits before its 5 byte delta
+    let count = bytes.len() - 7;
+    assert_eq!(bytes[count], 1);
+    bytes[count] = 2;
+    bytes.extend_from_slice(&[1, 0]);
+    assert!(matches!(
+        Replay::decode(&bytes),
+        Err(ReplayError::InvalidTick)
+    ));
+}
@@ -0,0 +1,85 @@
+use std::{fs, path::PathBuf};
+
+use flappy_bird::{
+    cli::Args,
+    rating::Medal,
+    save::{RunRecord, SaveData, SaveError, SavePath},
+};
+
+mod common;
+
+fn save_path(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!(
+        "flappy_bird_save_test_{name}_{}",
+        std::process::id()
+    ));
+    let _ = fs::remove_dir_all(&dir);
+    dir.join("save.ron")
+}
+
+fn run(score: i32, medal: Option<Medal>) -> RunRecord {
+    RunRecord {
+        score,
+        distance: score as f32 * 100.0,
+        duration: 10.0,
+        seed: Some(7),
+        medal,
+    }
+}
+
+#[test]
+fn old_saves_are_migrated() {
+    let path = save_path("migrate");
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(
+        &path,
+        "(version: 1, data: (best_score: 12, runs: [(score: 12, distance: 950.5, duration: 20.0, seed: Some(3))]))",
+    )
+    .unwrap();
+
+    let data = SaveData::read(&path).unwrap();
+    assert_eq!(data.best_score, 12);
+    assert_eq!(data.runs.len(), 1);
+    assert_eq!(
+        (data.runs[0].score, data.runs[0].seed, data.runs[0].medal),
+        (12, Some(3), None)
+    );
+
+    // and are written back in the current layout
+    data.write(&path).unwrap();
+    let data = SaveData::read(&path).unwrap();
+    assert_eq!((data.best_score, data.runs.len()), (12, 1));
+}
+
+#[test]
+fn newer_saves_are_refused() {
+    let path = save_path("newer");
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(&path, "(version: 99, data: ())").unwrap();
+    assert!(matches!(
+        SaveData::read(&path),
+        Err(SaveError::UnsupportedVersion(99))
+    ));
+}
+
+#[test]
+fn corrupt_saves_fall_back_to_the_backup() {
+    let path = save_path("corrupt");
+    let mut data = SaveData::default();
+    data.push_run(run(5, Some(Medal::Bronze)));
+    data.write(&path).unwrap();
+    data.push_run(run(15, Some(Medal::Silver)));
+    data.write(&path).unwrap();
+    fs::write(&path, "(version: 2, data: (best_score: ").unwrap();
+
+    let mut app = common::app(Args::default(), None);
+    app.insert_resource(SavePath(Some(path.clone())));
+    app.update();
+
+    // the backup is the save from before the last write
+    let loaded = app.world.resource::<SaveData>();
+    assert_eq!(loaded.best_score, 5);
+    assert_eq!(loaded.runs.len(), 1);
+    assert_eq!(loaded.runs[0].medal, Some(Medal::Bronze));
+    assert!(path.with_extension("ron.corrupt").exists());
+}
@@ -0,0 +1,79 @@
+use std::{net::Ipv4Addr, time::Duration};
+
+use bevy::prelude::*;
+use flappy_bird::{
+    game::GameState,
+    headless::{self, RunSummary},
+    networking::{Broadcast, Spectator},
+    replay::ReplayPlayback,
+};
+
+mod common;
+use common::{flaps, state};
+
+const MAX_UPDATES: u32 = 60 * 60;
+
+// a scripted run flapping every 37 ticks, broadcast on a local port
+fn broadcaster(seed: u64) -> App {
+    let mut app = common::app(default(), Some(ReplayPlayback::scripted(seed, flaps(37))));
+    app.insert_resource(Broadcast::bind((Ipv4Addr::LOCALHOST, 0).into()).unwrap());
+    app
+}
+
+fn spectator(broadcaster: &App, delay: Duration) -> App {
+    let address = broadcaster
+        .world
+        .resource::<Broadcast>()
+        .local_addr()
+        .unwrap();
+    let mut app = common::app(default(), None);
+    app.insert_resource(Spectator::connect(address, delay).unwrap());
+    app
+}
+
+// updates both games in turns until the spectator has seen the bird die too, returns how
+// many updates later that was
+fn watch(broadcaster: &mut App, spectator: &mut App) -> (RunSummary, RunSummary, u32) {
+    let mut summary = None;
+    let mut lag = 0;
+    for _ in 0..MAX_UPDATES {
+        broadcaster.update();
+        if summary.is_none() && state(broadcaster) == GameState::Dead {
+            summary = Some(headless::summary(&broadcaster.world));
+        }
+        spectator.update();
+        if let Some(summary) = &summary {
+            if state(spectator) == GameState::Dead {
+                return (summary.clone(), headless::summary(&spectator.world), lag);
+            }
+            lag += 1;
+        }
+    }
+    panic!("spectator didn't see the run end after {MAX_UPDATES} updates");
+}
+
+#[test]
+fn spectator_replays_the_run() {
+    let mut broadcaster = broadcaster(12);
+    let mut spectator = spectator(&broadcaster, Duration::from_millis(500));
+    let (broadcast, watched, lag) = watch(&mut broadcaster, &mut spectator);
+
+    assert_eq!(broadcast.seed, 12);
+    assert_eq!(broadcast, watched);
+    // half a second at 60 ticks per second
+    assert!((28..=32).contains(&lag), "spectator was {lag} ticks behind");
+}
+
+#[test]
+fn late_spectators_catch_up() {
+    let mut broadcaster = broadcaster(3);
+    for _ in 0..100 {
+        broadcaster.update();
+    }
+    assert_eq!(state(&broadcaster), GameState::Playing);
+
+    let mut spectator = spectator(&broadcaster, Duration::ZERO);
+    let (broadcast, watched, lag) = watch(&mut broadcaster, &mut spectator);
+    assert_eq!(broadcast, watched);
+    assert!(lag <= 2, "spectator was {lag} ticks behind");
+}
@@ -0,0 +1,107 @@
+use bevy::prelude::*;
+use flappy_bird::{
+    cli::Args,
+    difficulty::Difficulty,
+    game::GameState,
+    patterns::PipePatterns,
+    physics::Gravity,
+    pipes::Pipes,
+    rating::MedalThresholds,
+    replay::{Replay, ReplayConfig, ReplayPlayback},
+    tuning::{Tuning, TuningError},
+};
+
+mod common;
+use common::{flaps, state};
+
+const MAX_TICKS: u32 = 60 * 10;
+
+#[test]
+fn shipped_tuning_matches_the_defaults() {
+    let contents = std::fs::read_to_string("assets/game.tuning.ron").unwrap();
+    assert_eq!(Tuning::parse(&contents).unwrap(), Tuning::default());
+}
+
+#[test]
+fn broken_tuning_is_reported() {
+    let valid = "(gravity: 400.0, flap_force: 150.0, game_speed: 50.0, pipe_spacing: 75.0, gap_spacing: 48.0, game_height: 175.0)";
+    assert!(Tuning::parse(valid).is_ok());
+
+    for contents in ["(gravity: 400.0)", &valid.replace("gravity", "gravitty")] {
+        assert!(matches!(
+            Tuning::parse(contents),
+            Err(TuningError::Parse(_))
+        ));
+    }
+    for contents in [
+        valid.replace("400.0", "-400.0"),
+        valid.replace("75.0", "0.0"),
+        // no room left for the gap
+        valid.replace("175.0", "60.0"),
+        valid.replace(
+            ")",
+            ", medals: (bronze: 0, silver: 20, gold: 30, platinum: 40))",
+        ),
+        valid.replace(
+            ")",
+            ", medals: (bronze: 10, silver: 20, gold: 15, platinum: 40))",
+        ),
+    ] {
+        assert!(matches!(
+            Tuning::parse(&contents),
+            Err(TuningError::Invalid(_))
+        ));
+    }
+}
+
+#[test]
+fn tuning_changes_take_effect() {
+    let mut app = common::app(
+        Args {
+            seed: Some(1),
+            ..default()
+        },
+        Some(ReplayPlayback::scripted(1, flaps(20))),
+    );
+
+    let mut tuning = Tuning {
+        gravity: 300.0,
+        gap_spacing: 60.0,
+        ..default()
+    };
+    tuning.difficulty.normal.gap = (1.0, 1.0);
+    tuning.medals.bronze = 5;
+    app.insert_resource(tuning);
+    for _ in 0..MAX_TICKS {
+        if app
+            .world
+            .iter_entities()
+            .any(|entity| entity.contains::<Pipes>())
+        {
+            break;
+        }
+        app.update();
+    }
+    assert_eq!(state(&app), GameState::Playing);
+
+    assert_eq!(**app.world.resource::<Gravity>(), Vec2::new(0.0, -300.0));
+    assert_eq!(app.world.resource::<MedalThresholds>().bronze, 5);
+    let mut query = app.world.query::<&Pipes>();
+    assert!(query
+        .iter(&app.world)
+        .all(|pipes| pipes.gap_spacing == tuning.gap_spacing));
+
+    // and are part of what a replay remembers
+    let replay = Replay {
+        seed: 1,
+        config: ReplayConfig {
+            tick_rate: 60.0,
+            tuning,
+            difficulty: Difficulty::Hard,
+            patterns: PipePatterns::default(),
+        },
+        score: 0,
+        inputs: Vec::new(),
+    };
+    assert_eq!(Replay::decode(&replay.encode()).unwrap(), replay);
+}
@@ -0,0 +1,199 @@
+use bevy::{
+    input::{
+        gamepad::{
+            GamepadButtonChangedEvent, GamepadConnection, GamepadConnectionEvent, GamepadEvent,
+            GamepadInfo,
+        },
+        ButtonState,
+    },
+    prelude::*,
+};
+use flappy_bird::{
+    camera::PlayerView,
+    cli::Args,
+    game::{GameBoundaries, GameState},
+    physics::Velocity,
+    player::{Crashed, PlayerId},
+    versus::{LocalVersus, VersusWinner, VERSUS_KEYS},
+};
+
+mod common;
+use common::{key, start_waiting, state, tick_until, MAX_TICKS};
+
+// headless versus round sitting in `Waiting`
+fn app(local_versus: LocalVersus) -> App {
+    let mut app = common::app(
+        Args {
+            seed: Some(4),
+            ..default()
+        },
+        None,
+    );
+    // leaving for the main menu ends any versus round
+    tick_until(&mut app, |app| state(app) == GameState::MainMenu);
+    app.insert_resource(local_versus);
+    start_waiting(&mut app);
+    app
+}
+
+fn flap(app: &mut App, player_id: PlayerId) {
+    let key_code = VERSUS_KEYS[*player_id];
+    app.world.send_event(key(key_code, ButtonState::Pressed));
+    app.update();
+    app.world.send_event(key(key_code, ButtonState::Released));
+    app.update();
+}
+
+fn birds(app: &mut App) -> Vec<(PlayerId, Option<Crashed>)> {
+    let mut query = app.world.query::<(&PlayerId, Option<&Crashed>)>();
+    let mut birds = query
+        .iter(&app.world)
+        .map(|(player_id, crashed)| (*player_id, crashed.copied()))
+        .collect::<Vec<_>>();
+    birds.sort_by_key(|(player_id, _)| **player_id);
+    birds
+}
+
+fn bird<T: Component + Clone>(app: &mut App, player_id: PlayerId) -> T {
+    let mut query = app.world.query::<(&PlayerId, &T)>();
+    query
+        .iter(&app.world)
+        .find(|(id, _)| **id == player_id)
+        .map(|(_, component)| component.clone())
+        .unwrap()
+}
+
+fn crashed(app: &mut App, player_id: PlayerId) -> bool {
+    birds(app)
+        .iter()
+        .any(|(id, crashed)| *id == player_id && crashed.is_some())
+}
+
+fn views(app: &mut App) -> Vec<PlayerId> {
+    let mut query = app.world.query_filtered::<&PlayerView, With<Camera>>();
+    let mut views = query
+        .iter(&app.world)
+        .map(|view| **view)
+        .collect::<Vec<_>>();
+    views.sort_by_key(|player_id| **player_id);
+    views
+}
+
+#[test]
+fn each_bird_gets_a_view() {
+    let mut app = app(LocalVersus::SplitScreen);
+    assert_eq!(
+        birds(&mut app),
+        vec![(PlayerId(0), None), (PlayerId(1), None)]
+    );
+    assert_eq!(views(&mut app), vec![PlayerId(0), PlayerId(1)]);
+
+    app.world
+        .resource_mut::<NextState<GameState>>()
+        .set(GameState::MainMenu);
+    app.update();
+    app.update();
+    assert!(!app.world.contains_resource::<LocalVersus>());
+    assert_eq!(birds(&mut app), vec![(PlayerId(0), None)]);
+    assert!(views(&mut app).is_empty());
+}
+
+#[test]
+fn round_ends_once_both_birds_are_down() {
+    let mut app = app(LocalVersus::SplitScreen);
+    flap(&mut app, PlayerId(0));
+    tick_until(&mut app, |app| state(app) == GameState::Playing);
+
+    // the second bird keeps flapping while the first one drops
+    for _ in 0..MAX_TICKS / 20 {
+        if crashed(&mut app, PlayerId(0)) {
+            break;
+        }
+        flap(&mut app, PlayerId(1));
+        for _ in 0..18 {
+            app.update();
+        }
+    }
+    assert!(crashed(&mut app, PlayerId(0)));
+    assert!(!crashed(&mut app, PlayerId(1)));
+    assert_eq!(state(&app), GameState::Playing);
+
+    tick_until(&mut app, |app| state(app) == GameState::Dead);
+    assert!(birds(&mut app).iter().all(|(_, crashed)| crashed.is_some()));
+    assert_eq!(**app.world.resource::<VersusWinner>(), Some(PlayerId(1)));
+}
+
+#[test]
+fn party_birds_share_one_screen() {
+    let mut app = app(LocalVersus::Party(4));
+    assert_eq!(birds(&mut app).len(), 4);
+    assert!(views(&mut app).is_empty());
+
+    let mut tints = (0..4)
+        .map(|id| bird::<TextureAtlasSprite>(&mut app, PlayerId(id)).color)
+        .collect::<Vec<_>>();
+    tints.dedup();
+    assert_eq!(tints.len(), 4);
+}
+
+#[test]
+fn crashed_birds_stay_down_while_the_others_fly() {
+    let mut app = app(LocalVersus::Party(3));
+    flap(&mut app, PlayerId(0));
+    tick_until(&mut app, |app| state(app) == GameState::Playing);
+
+    let ground = app.world.resource::<GameBoundaries>().min.y;
+    let landed = |app: &mut App| bird::<Transform>(app, PlayerId(0)).translation.y == ground;
+    for _ in 0..MAX_TICKS / 20 {
+        if landed(&mut app) {
+            break;
+        }
+        flap(&mut app, PlayerId(1));
+        flap(&mut app, PlayerId(2));
+        for _ in 0..16 {
+            app.update();
+        }
+    }
+    assert!(landed(&mut app));
+
+    // flapping doesn't bring it back up
+    flap(&mut app, PlayerId(0));
+    for _ in 0..10 {
+        app.update();
+    }
+    assert!(landed(&mut app));
+    assert_eq!(state(&app), GameState::Playing);
+
+    tick_until(&mut app, |app| state(app) == GameState::Dead);
+    assert_ne!(**app.world.resource::<VersusWinner>(), Some(PlayerId(0)));
+}
+
+#[test]
+fn gamepads_flap_in_the_order_they_connected() {
+    let mut app = app(LocalVersus::Party(2));
+    for id in [3, 7] {
+        app.world
+            .send_event(GamepadEvent::Connection(GamepadConnectionEvent::new(
+                Gamepad::new(id),
+                GamepadConnection::Connected(GamepadInfo {
+                    name: "gamepad".into(),
+                }),
+            )));
+    }
+    app.update();
+
+    let button = |value| {
+        GamepadEvent::Button(GamepadButtonChangedEvent::new(
+            Gamepad::new(7),
+            GamepadButtonType::South,
+            value,
+        ))
+    };
+    app.world.send_event(button(1.0));
+    app.update();
+    app.world.send_event(button(0.0));
+    tick_until(&mut app, |app| state(app) == GameState::Playing);
+
+    let velocity = |app: &mut App, id| bird::<Velocity>(app, PlayerId(id)).y;
+    assert!(velocity(&mut app, 1) > velocity(&mut app, 0));
+}